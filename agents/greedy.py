@@ -0,0 +1,59 @@
+#!/usr/bin/env python3
+"""An example agent for snake-ggez: heads for the fruit, avoiding to die on the next move.
+
+    snake-ggez --agent "python3 agents/greedy.py"
+
+The game writes a JSON message per line on the standard input; a "turn" is answered
+with the move on a line of the standard output, an "end" is not answered.
+"""
+
+import json
+import sys
+
+MOVES = {"Up": (0, -1), "Down": (0, 1), "Left": (-1, 0), "Right": (1, 0)}
+REVERSE = {"Up": "Down", "Down": "Up", "Left": "Right", "Right": "Left"}
+
+
+def next_cell(view, x, y, move):
+    """The cell after a move, or None beyond a walled edge"""
+    dx, dy = MOVES[move]
+    x, y = x + dx, y + dy
+    wraps_x = view["boundary"] in ("wrap", "wrap-x")
+    wraps_y = view["boundary"] in ("wrap", "wrap-y")
+    if wraps_x:
+        x %= view["width"]
+    if wraps_y:
+        y %= view["height"]
+    if not (0 <= x < view["width"] and 0 <= y < view["height"]):
+        return None
+    return x, y
+
+
+def choose(view):
+    me = view["snakes"][view["you"]]
+    fruit = view["fruits"][view["you"] if len(view["fruits"]) > 1 else 0]
+    blocked = {(w["x"], w["y"]) for w in view["walls"]}
+    for snake in view["snakes"]:
+        blocked.add((snake["head"]["x"], snake["head"]["y"]))
+        blocked.update((s["x"], s["y"]) for s in snake["body"])
+
+    best = None
+    for move in MOVES:
+        if me["body"] and move == REVERSE[me["dir"]]:
+            continue
+        cell = next_cell(view, me["head"]["x"], me["head"]["y"], move)
+        if cell is None or cell in blocked:
+            continue
+        distance = abs(cell[0] - fruit["x"]) + abs(cell[1] - fruit["y"])
+        if best is None or distance < best[0]:
+            best = (distance, move)
+    return best[1] if best else me["dir"]
+
+
+for line in sys.stdin:
+    message = json.loads(line)
+    if message["type"] == "end":
+        # Another game may follow, until the standard input is closed
+        print("score", message["snakes"][message["you"]]["score"], file=sys.stderr)
+        continue
+    print(json.dumps({"move": choose(message)}), flush=True)
@@ -0,0 +1,59 @@
+"""A Gym-style wrapper around snake-gym, to train agents in Python.
+
+    env = SnakeEnv(["--encoding", "features"])
+    observation = env.reset(seed=1)
+    observation, reward, done, info = env.step(0)  # 0 straight, 1 left, 2 right
+
+Run from the project directory, after `cargo build --release`.
+"""
+
+import json
+import random
+import subprocess
+
+
+class SnakeEnv:
+    def __init__(self, args=(), program="target/release/snake-gym"):
+        self.process = subprocess.Popen(
+            [program, *args],
+            stdin=subprocess.PIPE,
+            stdout=subprocess.PIPE,
+            text=True,
+        )
+        self.shape = None
+        self.actions = None
+
+    def _call(self, request):
+        self.process.stdin.write(json.dumps(request) + "\n")
+        self.process.stdin.flush()
+        response = json.loads(self.process.stdout.readline())
+        if "error" in response:
+            raise ValueError(response["error"])
+        return response
+
+    def reset(self, seed=0):
+        response = self._call({"cmd": "reset", "seed": seed})
+        self.shape = response["shape"]
+        self.actions = response["actions"]
+        return response["observation"]
+
+    def step(self, action):
+        response = self._call({"cmd": "step", "action": action})
+        return response["observation"], response["reward"], response["done"], response["info"]
+
+    def close(self):
+        self.process.stdin.close()
+        self.process.wait()
+
+
+if __name__ == "__main__":
+    # A random agent, as a smoke test
+    env = SnakeEnv(["--encoding", "features"])
+    for episode in range(3):
+        env.reset(seed=episode)
+        total, done = 0.0, False
+        while not done:
+            _, reward, done, info = env.step(random.randrange(env.actions))
+            total += reward
+        print("episode", episode, "reward", total, "info", info)
+    env.close()
@@ -0,0 +1,308 @@
+//! Snakes driven by programs.
+//!
+//! A `SnakeAgent` gets a `View` of the board at every tick and answers with the
+//! direction of its snake. Agents written in Rust implement the trait; agents
+//! written in any other language run as a `Subprocess` and talk JSON, one
+//! message per line.
+//!
+//! At every tick the game writes a `turn` message on the standard input of the
+//! agent, and waits for the move on its standard output:
+//!
+//! ```text
+//! > {"type":"turn","you":0,"tick":0,"width":25,"height":20,"boundary":"wrap","walls":[],
+//!    "fruits":[{"x":7,"y":12}],"snakes":[{"head":{"x":4,"y":4},"body":[],"dir":"Right",
+//!    "alive":true,"score":0}]}
+//! < {"move":"Down"}
+//! ```
+//!
+//! The move is `"Up"`, `"Down"`, `"Left"` or `"Right"`; `{}` goes straight on,
+//! and so does a move back onto the body. When the game is over the agent gets
+//! an `end` message, with the same fields, and doesn't answer.
+//! Anything written on the standard error goes to the terminal, for debugging.
+
+use crate::autopilot::Autopilot;
+use crate::simulation::Boundary;
+use crate::simulation::Direction;
+use crate::simulation::GridPosition;
+use crate::simulation::Simulation;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How long an agent may think about a move, by default
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Everything an agent knows about the game
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct View {
+    /// The index of the agent's snake in `snakes`
+    pub you: usize,
+    pub tick: u64,
+    pub width: u32,
+    pub height: u32,
+    pub boundary: Boundary,
+    pub walls: Vec<GridPosition>,
+    /// The fruits: a single one, or the one of each snake in the same order
+    pub fruits: Vec<GridPosition>,
+    /// All the snakes, the player's first
+    pub snakes: Vec<SnakeView>,
+}
+
+/// A snake, as the agents see it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnakeView {
+    pub head: GridPosition,
+    /// The body segments, from the neck to the tail
+    pub body: Vec<GridPosition>,
+    pub dir: Direction,
+    pub alive: bool,
+    pub score: u32,
+}
+
+impl View {
+    /// The game as the given snake sees it, by its index in `Simulation::snakes`
+    pub fn new(sim: &Simulation, you: usize) -> View {
+        let board = sim.board();
+        View {
+            you,
+            tick: sim.tick(),
+            width: board.width,
+            height: board.height,
+            boundary: sim.rules().boundary,
+            walls: sim.rules().walls.clone(),
+            fruits: sim.fruits().to_vec(),
+            snakes: sim
+                .snakes()
+                .iter()
+                .map(|snake| SnakeView {
+                    head: snake.head_pos(),
+                    body: snake.body().iter().copied().collect(),
+                    dir: snake.dir(),
+                    alive: snake.is_alive(),
+                    score: snake.score(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The snake of the agent
+    pub fn me(&self) -> &SnakeView {
+        &self.snakes[self.you]
+    }
+}
+
+/// A message from the game to an agent
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Message {
+    /// Choose the next move, answered with a `Reply`
+    Turn(View),
+    /// The game is over, nothing to answer
+    End(View),
+}
+
+/// The answer of an agent to a `turn` message
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reply {
+    /// The direction to go, `None` to go straight on
+    #[serde(default, rename = "move")]
+    pub dir: Option<Direction>,
+}
+
+/// Something that plays a snake from a `View` of the game
+pub trait SnakeAgent {
+    /// The direction of the snake at the next tick: its current one to go
+    /// straight on. A move back onto the body is ignored.
+    fn act(&mut self, view: &View) -> Direction;
+
+    /// The game is over
+    fn end(&mut self, _view: &View) {}
+}
+
+/// Drives a snake of the game with an agent, like the autopilot does
+pub struct AgentPilot<A: SnakeAgent>(pub A);
+
+impl<A: SnakeAgent> Autopilot for AgentPilot<A> {
+    fn choose(&mut self, sim: &Simulation, snake: usize) -> Option<Direction> {
+        let me = sim.snakes().get(snake)?;
+        if !me.is_alive() {
+            return None;
+        }
+        let dir = self.0.act(&View::new(sim, snake));
+        Some(dir).filter(|dir| *dir != me.dir())
+    }
+
+    fn end(&mut self, sim: &Simulation, snake: usize) {
+        self.0.end(&View::new(sim, snake));
+    }
+}
+
+/// Everything that can go wrong with an agent running as a subprocess
+#[derive(Debug)]
+pub enum AgentError {
+    /// The command line is empty
+    NoCommand,
+    /// The agent could not be started
+    Spawn(String, io::Error),
+    /// The agent could not be written to
+    Io(io::Error),
+    /// The agent closed its output, or exited
+    Closed,
+    /// The agent took too long to answer
+    Timeout(Duration),
+    /// The answer is not a move
+    Parse(String, serde_json::Error),
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentError::NoCommand => write!(f, "no command to run"),
+            AgentError::Spawn(command, e) => write!(f, "could not run '{}': {}", command, e),
+            AgentError::Io(e) => write!(f, "{}", e),
+            AgentError::Closed => write!(f, "the agent exited"),
+            AgentError::Timeout(timeout) => {
+                write!(f, "no answer within {}ms", timeout.as_millis())
+            }
+            AgentError::Parse(line, e) => write!(f, "'{}' is not a move: {}", line, e),
+        }
+    }
+}
+
+impl std::error::Error for AgentError {}
+
+impl From<io::Error> for AgentError {
+    fn from(e: io::Error) -> Self {
+        AgentError::Io(e)
+    }
+}
+
+/// An agent running as another program, talking JSON lines on its standard
+/// input and output.
+///
+/// An agent that exits, answers late or doesn't answer with a move loses
+/// control: its snake goes straight on from then on.
+pub struct Subprocess {
+    child: Child,
+    /// `None` once closed, to let the agent exit
+    stdin: Option<ChildStdin>,
+    /// The lines written by the agent, read by a thread not to block the game
+    lines: Receiver<String>,
+    timeout: Duration,
+    error: Option<AgentError>,
+}
+
+impl Subprocess {
+    /// Start an agent: the program and its arguments, separated by spaces
+    pub fn spawn(command: &str, timeout: Duration) -> Result<Subprocess, AgentError> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or(AgentError::NoCommand)?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| AgentError::Spawn(command.to_string(), e))?;
+
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Subprocess {
+            child,
+            stdin,
+            lines,
+            timeout,
+            error: None,
+        })
+    }
+
+    /// What made the agent lose control of its snake, if anything
+    pub fn error(&self) -> Option<&AgentError> {
+        self.error.as_ref()
+    }
+
+    /// Write a message on a line
+    fn send(&mut self, message: &Message) -> Result<(), AgentError> {
+        let stdin = self.stdin.as_mut().ok_or(AgentError::Closed)?;
+        let line = serde_json::to_string(message).map_err(io::Error::other)?;
+        writeln!(stdin, "{}", line)?;
+        stdin.flush()?;
+        Ok(())
+    }
+
+    /// Ask for the next move
+    fn ask(&mut self, view: &View) -> Result<Option<Direction>, AgentError> {
+        self.send(&Message::Turn(view.clone()))?;
+        let line = match self.lines.recv_timeout(self.timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => return Err(AgentError::Timeout(self.timeout)),
+            Err(RecvTimeoutError::Disconnected) => return Err(AgentError::Closed),
+        };
+        let reply: Reply = serde_json::from_str(&line).map_err(|e| AgentError::Parse(line, e))?;
+        Ok(reply.dir)
+    }
+}
+
+impl SnakeAgent for Subprocess {
+    fn act(&mut self, view: &View) -> Direction {
+        let straight = view.me().dir;
+        if self.error.is_some() {
+            return straight;
+        }
+        match self.ask(view) {
+            Ok(dir) => dir.unwrap_or(straight),
+            Err(e) => {
+                self.error = Some(e);
+                straight
+            }
+        }
+    }
+
+    fn end(&mut self, view: &View) {
+        if self.error.is_none() {
+            // The agent may be gone already, and there's nothing more to ask
+            let _ = self.send(&Message::End(view.clone()));
+        }
+    }
+}
+
+impl Drop for Subprocess {
+    /// Give the agent a moment to exit once its input is closed, then stop it
+    fn drop(&mut self) {
+        self.stdin = None;
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,644 @@
+//! Tournaments between snakes driven by programs.
+//!
+//! An `Arena` plays many seeded games between its entrants, the bots, the
+//! autopilots and the agents of the `agent` module, without a window. All
+//! the entrants play each game together, each from another start in turn so
+//! that no one keeps the best corner, and the games run on several threads.
+//!
+//! The `Report` gathers the results of each entrant: the games won, the
+//! length and the survival time of its snake, and how it died. It also keeps
+//! the replays of a few notable games.
+
+use crate::agent::AgentError;
+use crate::agent::AgentPilot;
+use crate::agent::Subprocess;
+use crate::agent::DEFAULT_TIMEOUT;
+use crate::autopilot::Autopilot;
+use crate::autopilot::Strategy;
+use crate::bot::bot_starts;
+use crate::bot::Bot;
+use crate::bot::Difficulty;
+use crate::bot::MAX_BOTS;
+use crate::replay::Replay;
+use crate::simulation::Death;
+use crate::simulation::Direction;
+use crate::simulation::Event;
+use crate::simulation::GameState;
+use crate::simulation::Rules;
+use crate::simulation::Simulation;
+use serde::Serialize;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// The most snakes in a game: the player's start and the ones of the bots
+pub const MAX_ENTRANTS: usize = MAX_BOTS + 1;
+
+/// The longest game, if not given
+pub const DEFAULT_MAX_TICKS: u64 = 10_000;
+
+/// What drives the snake of an entrant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pilot {
+    Bot(Difficulty),
+    Autopilot(Strategy),
+    /// The command running an agent, see `agent::Subprocess`
+    Agent(String),
+}
+
+impl fmt::Display for Pilot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pilot::Bot(difficulty) => write!(f, "{}", difficulty),
+            Pilot::Autopilot(strategy) => write!(f, "{}", strategy),
+            Pilot::Agent(command) => write!(f, "agent:{}", command),
+        }
+    }
+}
+
+impl std::str::FromStr for Pilot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(command) = s.strip_prefix("agent:") {
+            if command.trim().is_empty() {
+                return Err("agent: needs a command".to_string());
+            }
+            return Ok(Pilot::Agent(command.to_string()));
+        }
+        if let Ok(difficulty) = s.parse() {
+            return Ok(Pilot::Bot(difficulty));
+        }
+        if let Ok(strategy) = s.parse() {
+            return Ok(Pilot::Autopilot(strategy));
+        }
+        Err(format!(
+            "unknown pilot '{}', expected greedy, pathfinding, cautious, safe-path, cycle or agent:<command>",
+            s
+        ))
+    }
+}
+
+/// A contestant of the tournament
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entrant {
+    /// The name in the report
+    pub name: String,
+    pub pilot: Pilot,
+}
+
+impl std::str::FromStr for Entrant {
+    type Err = String;
+
+    /// A pilot, named after itself, or `name=pilot`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, pilot) = match s.split_once('=') {
+            Some((name, pilot)) if !name.is_empty() && !name.starts_with("agent:") => {
+                (name.to_string(), pilot)
+            }
+            _ => (s.to_string(), s),
+        };
+        Ok(Entrant {
+            name,
+            pilot: pilot.parse()?,
+        })
+    }
+}
+
+/// Everything that can stop a tournament
+#[derive(Debug)]
+pub enum ArenaError {
+    /// There is no one to play, or too many entrants for the board
+    Entrants(String),
+    /// The rules don't leave room for all the snakes
+    Rules(String),
+    /// The agent of an entrant could not be started
+    Agent(String, AgentError),
+}
+
+impl fmt::Display for ArenaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaError::Entrants(msg) => write!(f, "{}", msg),
+            ArenaError::Rules(msg) => write!(f, "no room for all the snakes: {}", msg),
+            ArenaError::Agent(name, e) => write!(f, "{}: {}", name, e),
+        }
+    }
+}
+
+impl std::error::Error for ArenaError {}
+
+/// A tournament: how many games, between whom, on which board
+#[derive(Clone, Debug)]
+pub struct Arena {
+    /// The rules of the games; the starts of the rivals are replaced by the
+    /// ones of the bots
+    pub rules: Rules,
+    pub entrants: Vec<Entrant>,
+    pub games: u64,
+    /// The seed of the first game, the next ones count up from there
+    pub seed: u64,
+    /// A game still going after this many ticks is a draw
+    pub max_ticks: u64,
+    /// How many games are played at once
+    pub threads: usize,
+    /// How long the agents may think about a move
+    pub agent_timeout: Duration,
+}
+
+impl Arena {
+    /// A tournament of `games` games on the given rules
+    pub fn new(rules: Rules, entrants: Vec<Entrant>, games: u64) -> Arena {
+        Arena {
+            rules,
+            entrants,
+            games,
+            seed: 0,
+            max_ticks: DEFAULT_MAX_TICKS,
+            threads: 1,
+            agent_timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// The rules of a game, with a snake for each entrant
+    pub fn game_rules(&self) -> Result<Rules, ArenaError> {
+        let count = self.entrants.len();
+        if count == 0 {
+            return Err(ArenaError::Entrants("no entrants".to_string()));
+        }
+        if count > MAX_ENTRANTS {
+            return Err(ArenaError::Entrants(format!(
+                "too many entrants: {}, at most {}",
+                count, MAX_ENTRANTS
+            )));
+        }
+        let mut rules = self.rules.clone();
+        rules.rivals = bot_starts(&rules, count - 1);
+        rules.validate().map_err(ArenaError::Rules)?;
+        Ok(rules)
+    }
+
+    /// Which entrant plays each snake of a game, the player's first: the
+    /// entrants move to the next start at every game
+    pub fn seats(&self, game: u64) -> Vec<usize> {
+        let count = self.entrants.len();
+        (0..count)
+            .map(|snake| (snake + (game % count as u64) as usize) % count)
+            .collect()
+    }
+
+    /// Play all the games and gather the results
+    pub fn run(&self) -> Result<Report, ArenaError> {
+        let rules = self.game_rules()?;
+        let next = AtomicU64::new(0);
+        let mut report = Report::new(&self.entrants);
+
+        thread::scope(|scope| {
+            let (sender, results) = mpsc::channel();
+            for _ in 0..self.threads.max(1) {
+                let sender = sender.clone();
+                let (rules, next) = (&rules, &next);
+                scope.spawn(move || {
+                    let mut drivers = match self.drivers() {
+                        Ok(drivers) => drivers,
+                        Err(e) => {
+                            // No more games for anyone
+                            next.store(self.games, Ordering::Relaxed);
+                            let _ = sender.send(Err(e));
+                            return;
+                        }
+                    };
+                    loop {
+                        let game = next.fetch_add(1, Ordering::Relaxed);
+                        if game >= self.games {
+                            break;
+                        }
+                        let result = self.play(rules, game, &mut drivers);
+                        if sender.send(Ok(result)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            for result in results {
+                let (result, replay) = result?;
+                report.add(result, replay);
+            }
+            Ok(report)
+        })
+    }
+
+    /// The pilots of all the entrants, for a thread
+    fn drivers(&self) -> Result<Vec<Driver>, ArenaError> {
+        self.entrants
+            .iter()
+            .map(|entrant| {
+                Driver::new(&entrant.pilot, self.agent_timeout)
+                    .map_err(|e| ArenaError::Agent(entrant.name.clone(), e))
+            })
+            .collect()
+    }
+
+    /// Play a game, and record it
+    fn play(&self, rules: &Rules, game: u64, drivers: &mut [Driver]) -> (GameResult, Replay) {
+        let seed = self.seed.wrapping_add(game);
+        let seats = self.seats(game);
+        let mut sim = Simulation::new(rules.clone(), seed);
+        sim.restart(seed);
+        let mut replay = Replay::new(seed, rules.clone());
+        let mut survived = vec![None; seats.len()];
+        let mut winner = None;
+
+        while sim.state() == GameState::Running && sim.tick() < self.max_ticks {
+            let inputs: Vec<_> = seats
+                .iter()
+                .enumerate()
+                .map(|(snake, &entrant)| drivers[entrant].choose(&sim, snake))
+                .collect();
+            replay.record_all(sim.tick(), &inputs);
+
+            let tick = sim.tick();
+            for event in sim.step_all(&inputs) {
+                match event {
+                    Event::Died { snake, .. } => survived[snake] = Some(tick),
+                    Event::GameOver(_) => survived[0] = Some(tick),
+                    Event::RoundOver {
+                        winner: Some(snake),
+                    } => winner = Some(seats[snake]),
+                    Event::Victory { .. } | Event::LevelComplete => winner = Some(seats[0]),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut faults = Vec::new();
+        for (snake, &entrant) in seats.iter().enumerate() {
+            if drivers[entrant].end(&sim, snake) {
+                faults.push(entrant);
+            }
+        }
+
+        let snakes = seats
+            .iter()
+            .zip(sim.snakes())
+            .zip(survived)
+            .map(|((&entrant, snake), survived)| SnakeResult {
+                entrant,
+                length: snake.length(),
+                score: snake.score(),
+                survived: survived.unwrap_or(sim.tick()),
+                death: snake.death(),
+                fault: faults.contains(&entrant),
+            })
+            .collect();
+        let result = GameResult {
+            game,
+            seed,
+            ticks: sim.tick(),
+            winner,
+            snakes,
+        };
+        (result, replay)
+    }
+}
+
+/// The pilot of an entrant, on a thread
+enum Driver {
+    Autopilot(Box<dyn Autopilot>),
+    /// An agent, started again for the next game when it loses control
+    Agent {
+        command: String,
+        timeout: Duration,
+        agent: AgentPilot<Subprocess>,
+    },
+}
+
+impl Driver {
+    fn new(pilot: &Pilot, timeout: Duration) -> Result<Driver, AgentError> {
+        Ok(match pilot {
+            Pilot::Bot(difficulty) => Driver::Autopilot(Box::new(Bot::new(*difficulty))),
+            Pilot::Autopilot(strategy) => Driver::Autopilot(strategy.autopilot()),
+            Pilot::Agent(command) => Driver::Agent {
+                command: command.clone(),
+                timeout,
+                agent: AgentPilot(Subprocess::spawn(command, timeout)?),
+            },
+        })
+    }
+
+    fn choose(&mut self, sim: &Simulation, snake: usize) -> Option<Direction> {
+        match self {
+            Driver::Autopilot(pilot) => pilot.choose(sim, snake),
+            Driver::Agent { agent, .. } => agent.choose(sim, snake),
+        }
+    }
+
+    /// The game is over; returns whether an agent lost control during the game
+    fn end(&mut self, sim: &Simulation, snake: usize) -> bool {
+        match self {
+            Driver::Autopilot(pilot) => {
+                pilot.end(sim, snake);
+                false
+            }
+            Driver::Agent {
+                command,
+                timeout,
+                agent,
+            } => {
+                agent.end(sim, snake);
+                if agent.0.error().is_none() {
+                    return false;
+                }
+                if let Ok(restarted) = Subprocess::spawn(command, *timeout) {
+                    *agent = AgentPilot(restarted);
+                }
+                true
+            }
+        }
+    }
+}
+
+/// How a snake did in a game
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SnakeResult {
+    /// The index of the entrant in `Arena::entrants`
+    pub entrant: usize,
+    pub length: u32,
+    pub score: u32,
+    /// The ticks the snake lived
+    pub survived: u64,
+    /// How the snake died; `None` if it was still alive at the end
+    pub death: Option<Death>,
+    /// The agent lost control of the snake during the game
+    pub fault: bool,
+}
+
+/// How a game went
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GameResult {
+    /// The number of the game, from 0
+    pub game: u64,
+    pub seed: u64,
+    pub ticks: u64,
+    /// The entrant who won; `None` for a draw, or a game alone without a victory
+    pub winner: Option<usize>,
+    /// The snakes, the player's first
+    pub snakes: Vec<SnakeResult>,
+}
+
+/// The results of an entrant over the whole tournament
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Standing {
+    pub name: String,
+    pub pilot: String,
+    pub games: u64,
+    pub wins: u64,
+    /// The share of the games won, from 0 to 1
+    pub win_rate: f64,
+    /// The mean length of its snake at the end of the games
+    pub mean_length: f64,
+    /// The mean number of ticks its snake lived
+    pub mean_survival: f64,
+    /// The sum of the lengths of its snake at the end of the games
+    pub total_length: u64,
+    /// The sum of the ticks its snake lived
+    pub total_survived: u64,
+    pub best_score: u32,
+    pub crashes: Crashes,
+    /// The games where the agent lost control of its snake
+    pub faults: u64,
+}
+
+impl Standing {
+    /// Count how the snake of the entrant did in a game
+    fn add(&mut self, snake: &SnakeResult, won: bool) {
+        self.games += 1;
+        self.wins += u64::from(won);
+        self.total_length += u64::from(snake.length);
+        self.total_survived += snake.survived;
+        self.best_score = self.best_score.max(snake.score);
+        self.crashes.add(snake.death);
+        self.faults += u64::from(snake.fault);
+
+        let games = self.games as f64;
+        self.win_rate = self.wins as f64 / games;
+        self.mean_length = self.total_length as f64 / games;
+        self.mean_survival = self.total_survived as f64 / games;
+    }
+}
+
+/// How the snakes of an entrant died, game by game
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Crashes {
+    pub bit_itself: u64,
+    pub hit_wall: u64,
+    pub hit_snake: u64,
+    pub head_on: u64,
+    /// Still alive when the game ended
+    pub alive: u64,
+}
+
+impl Crashes {
+    fn add(&mut self, death: Option<Death>) {
+        let count = match death {
+            Some(Death::BitItself) => &mut self.bit_itself,
+            Some(Death::HitWall) => &mut self.hit_wall,
+            Some(Death::HitSnake) => &mut self.hit_snake,
+            Some(Death::HeadOn) => &mut self.head_on,
+            None => &mut self.alive,
+        };
+        *count += 1;
+    }
+}
+
+/// A game worth watching again
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Notable {
+    /// Why it's notable, also the name of its replay file
+    pub kind: &'static str,
+    pub result: GameResult,
+    #[serde(skip)]
+    pub replay: Replay,
+}
+
+impl Notable {
+    /// The notable games: the longest, the one with the best score, and the
+    /// one where a snake died the soonest
+    pub const KINDS: [&'static str; 3] = ["longest", "best-score", "earliest-crash"];
+
+    /// How notable a game is of the given kind, the larger the better
+    fn rank(kind: &str, result: &GameResult) -> Option<i64> {
+        match kind {
+            "longest" => Some(result.ticks as i64),
+            "best-score" => result.snakes.iter().map(|s| s.score as i64).max(),
+            "earliest-crash" => result
+                .snakes
+                .iter()
+                .filter(|s| s.death.is_some())
+                .map(|s| -(s.survived as i64))
+                .max(),
+            _ => None,
+        }
+    }
+}
+
+/// The results of a tournament
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub games: u64,
+    /// The games without a winner
+    pub draws: u64,
+    /// The entrants, in the order of `Arena::entrants`
+    pub standings: Vec<Standing>,
+    /// One game of each of `Notable::KINDS`, if any
+    pub notable: Vec<Notable>,
+}
+
+impl Report {
+    fn new(entrants: &[Entrant]) -> Report {
+        Report {
+            games: 0,
+            draws: 0,
+            standings: entrants
+                .iter()
+                .map(|entrant| Standing {
+                    name: entrant.name.clone(),
+                    pilot: entrant.pilot.to_string(),
+                    ..Standing::default()
+                })
+                .collect(),
+            notable: Vec::new(),
+        }
+    }
+
+    /// Count a game, and keep it if notable. The games come in any order
+    /// from the threads: on a tie the first game is kept, so that the report
+    /// doesn't depend on the threads.
+    fn add(&mut self, result: GameResult, replay: Replay) {
+        self.games += 1;
+        if result.winner.is_none() {
+            self.draws += 1;
+        }
+        for snake in result.snakes.iter() {
+            let won = result.winner == Some(snake.entrant);
+            self.standings[snake.entrant].add(snake, won);
+        }
+
+        for kind in Notable::KINDS {
+            let Some(rank) = Notable::rank(kind, &result) else {
+                continue;
+            };
+            let kept = self.notable.iter_mut().find(|n| n.kind == kind);
+            let better = |n: &Notable| {
+                let kept = Notable::rank(kind, &n.result);
+                (Some(rank), std::cmp::Reverse(result.game))
+                    > (kept, std::cmp::Reverse(n.result.game))
+            };
+            match kept {
+                Some(n) if better(n) => {
+                    n.result = result.clone();
+                    n.replay = replay.clone();
+                }
+                Some(_) => {}
+                None => self.notable.push(Notable {
+                    kind,
+                    result: result.clone(),
+                    replay: replay.clone(),
+                }),
+            }
+        }
+        self.notable
+            .sort_by_key(|n| Notable::KINDS.iter().position(|k| *k == n.kind));
+    }
+
+    /// The standings as a text table, the best win rate first
+    pub fn table(&self) -> String {
+        let mut order: Vec<&Standing> = self.standings.iter().collect();
+        order.sort_by(|a, b| b.win_rate.total_cmp(&a.win_rate));
+        let width = order
+            .iter()
+            .map(|s| s.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+
+        let mut table = String::new();
+        let _ = writeln!(
+            table,
+            "{:<width$}  {:>6}  {:>6}  {:>6}  {:>6}  {:>5}  {:>8}  {:>8}  {:>8}  {:>9}  {:>7}  {:>5}  {:>6}",
+            "Name", "Games", "Wins", "Win %", "Length", "Best", "Survival", "Bit self",
+            "Hit wall", "Hit snake", "Head-on", "Alive", "Faults",
+            width = width
+        );
+        for s in order {
+            let _ = writeln!(
+                table,
+                "{:<width$}  {:>6}  {:>6}  {:>5.1}%  {:>6.1}  {:>5}  {:>8.1}  {:>8}  {:>8}  {:>9}  {:>7}  {:>5}  {:>6}",
+                s.name,
+                s.games,
+                s.wins,
+                100.0 * s.win_rate,
+                s.mean_length,
+                s.best_score,
+                s.mean_survival,
+                s.crashes.bit_itself,
+                s.crashes.hit_wall,
+                s.crashes.hit_snake,
+                s.crashes.head_on,
+                s.crashes.alive,
+                s.faults,
+                width = width
+            );
+        }
+        let _ = writeln!(table, "{} games, {} draws", self.games, self.draws);
+        table
+    }
+
+    /// The standings as CSV, a line for each entrant
+    pub fn csv(&self) -> String {
+        let mut csv = String::from(
+            "name,pilot,games,wins,win_rate,mean_length,best_score,mean_survival,bit_itself,hit_wall,hit_snake,head_on,alive,faults\n",
+        );
+        for s in self.standings.iter() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{:.4},{:.2},{},{:.2},{},{},{},{},{},{}",
+                csv_field(&s.name),
+                csv_field(&s.pilot),
+                s.games,
+                s.wins,
+                s.win_rate,
+                s.mean_length,
+                s.best_score,
+                s.mean_survival,
+                s.crashes.bit_itself,
+                s.crashes.hit_wall,
+                s.crashes.hit_snake,
+                s.crashes.head_on,
+                s.crashes.alive,
+                s.faults
+            );
+        }
+        csv
+    }
+
+    /// The whole report as JSON, the notable games without their replays
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report is valid JSON")
+    }
+}
+
+/// A CSV field, quoted when needed
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,285 @@
+//! Snakes that play by themselves.
+//!
+//! An `Autopilot` chooses the turns of a snake for the next tick, like a
+//! `Bot`, but it plays to stay alive rather than to race. It drives the demo
+//! of the start screen, and the snake of the player when the assist is on.
+//!
+//! There are two of them, see `Strategy`: `SafePath` goes for the fruit only
+//! when the snake can still reach its own tail once there, and `Cycle` follows
+//! a Hamiltonian cycle of the board, slowly but without ever dying.
+
+use crate::bot::Bot;
+use crate::bot::Map;
+use crate::bot::DIRECTIONS;
+use crate::simulation::Board;
+use crate::simulation::Direction;
+use crate::simulation::GridPosition;
+use crate::simulation::Simulation;
+use crate::simulation::Snake;
+use std::fmt;
+
+/// Something that drives a snake
+pub trait Autopilot {
+    /// The turn of a snake of the simulation for the next tick, by its index
+    /// in `Simulation::snakes`; `None` to go straight on
+    fn choose(&mut self, sim: &Simulation, snake: usize) -> Option<Direction>;
+
+    /// The game is over
+    fn end(&mut self, _sim: &Simulation, _snake: usize) {}
+}
+
+impl Autopilot for Bot {
+    fn choose(&mut self, sim: &Simulation, snake: usize) -> Option<Direction> {
+        Bot::choose(self, sim, snake)
+    }
+}
+
+/// The autopilots to choose from in the settings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// See `SafePath`
+    #[default]
+    SafePath,
+    /// See `Cycle`
+    Cycle,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 2] = [Strategy::SafePath, Strategy::Cycle];
+
+    /// A new autopilot playing this way
+    pub fn autopilot(&self) -> Box<dyn Autopilot> {
+        match self {
+            Strategy::SafePath => Box::new(SafePath),
+            Strategy::Cycle => Box::<Cycle>::default(),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Strategy::SafePath => "safe-path",
+            Strategy::Cycle => "cycle",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Strategy::ALL
+            .into_iter()
+            .find(|st| st.to_string() == s)
+            .ok_or_else(|| format!("unknown autopilot '{}', expected safe-path or cycle", s))
+    }
+}
+
+/// Takes the shortest path to the fruit if the snake can still reach its tail
+/// once there, so that it can't get trapped by its own body. Otherwise it
+/// follows its tail the long way round until the fruit is safe to take.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SafePath;
+
+impl Autopilot for SafePath {
+    fn choose(&mut self, sim: &Simulation, snake: usize) -> Option<Direction> {
+        let me = sim.snakes().get(snake)?;
+        if !me.is_alive() {
+            return None;
+        }
+        let map = Map::new(sim);
+        let moves = map.moves(me);
+        if moves.is_empty() {
+            return None;
+        }
+
+        let chosen = path_to_fruit(&map, me, &moves, sim.fruit_of(snake))
+            .or_else(|| follow_tail(&map, me, &moves))
+            .or_else(|| map.roomiest(&moves));
+        chosen.map(|(dir, _)| dir).filter(|dir| *dir != me.dir())
+    }
+}
+
+/// The first move of the shortest path to the fruit after which the tail can
+/// be reached from the fruit, once the snake ate it
+fn path_to_fruit(
+    map: &Map,
+    me: &Snake,
+    moves: &[(Direction, GridPosition)],
+    fruit: GridPosition,
+) -> Option<(Direction, GridPosition)> {
+    let distances = map.distances_from(fruit);
+    let mut paths: Vec<((Direction, GridPosition), u32)> = moves
+        .iter()
+        .filter_map(|m| distances[map.index(m.1)].map(|d| (*m, d)))
+        .collect();
+    paths.sort_by_key(|(_, d)| *d);
+
+    paths.into_iter().find_map(|(first, d)| {
+        // Walk the path down to the fruit
+        let mut path = vec![first.1];
+        let mut pos = first.1;
+        for d in (0..d).rev() {
+            pos = DIRECTIONS
+                .into_iter()
+                .filter_map(|dir| map.next(pos, dir))
+                .find(|next| distances[map.index(*next)] == Some(d))?;
+            path.push(pos);
+        }
+
+        // The snake at the fruit, one cell longer, from the head to the tail
+        let cells: Vec<GridPosition> = path
+            .iter()
+            .rev()
+            .copied()
+            .chain(std::iter::once(me.head_pos()))
+            .chain(me.body().iter().copied())
+            .take(me.length() as usize + 1)
+            .collect();
+        tail_distance(map, me, &cells).map(|_| first)
+    })
+}
+
+/// The move that keeps the tail reachable the longest way round
+fn follow_tail(
+    map: &Map,
+    me: &Snake,
+    moves: &[(Direction, GridPosition)],
+) -> Option<(Direction, GridPosition)> {
+    moves
+        .iter()
+        .copied()
+        .rev()
+        .filter_map(|(dir, pos)| {
+            // The snake after the move, without eating
+            let cells: Vec<GridPosition> = std::iter::once(pos)
+                .chain(std::iter::once(me.head_pos()))
+                .chain(me.body().iter().copied())
+                .take(me.length() as usize)
+                .collect();
+            tail_distance(map, me, &cells).map(|d| ((dir, pos), d))
+        })
+        .max_by_key(|(_, d)| *d)
+        .map(|(m, _)| m)
+}
+
+/// The length of the free path from the head to the tail of a snake moved to
+/// the given cells, head first. The tail can't be entered on the next move,
+/// so the path goes through a free cell next to the head; a snake shorter
+/// than three cells can't block its own way.
+fn tail_distance(map: &Map, me: &Snake, cells: &[GridPosition]) -> Option<u32> {
+    let (head, tail) = (*cells.first()?, *cells.last()?);
+    if cells.len() < 3 {
+        return Some(u32::MAX);
+    }
+    let mut moved = map.clone();
+    moved.blocked[map.index(me.head_pos())] = false;
+    for seg in me.body().iter() {
+        moved.blocked[map.index(*seg)] = false;
+    }
+    for cell in cells {
+        moved.blocked[map.index(*cell)] = true;
+    }
+    let distances = moved.distances_from(tail);
+    DIRECTIONS
+        .into_iter()
+        .filter_map(|dir| moved.next(head, dir))
+        .filter(|next| !moved.is_blocked(*next))
+        .filter_map(|next| distances[map.index(next)])
+        .min()
+        .map(|d| d + 1)
+}
+
+/// Follows a Hamiltonian cycle, a path through every cell of the board that
+/// comes back to where it started: the snake can grow until it fills the board
+/// without ever biting itself.
+///
+/// There is such a cycle only on boards without walls with an even width or
+/// height, for a snake alone; elsewhere, or until the snake is on the cycle,
+/// `Cycle` plays like `SafePath`.
+#[derive(Clone, Debug, Default)]
+pub struct Cycle {
+    /// The board of the last game, with the direction to take on each cell
+    cycle: Option<(Board, Option<Vec<Direction>>)>,
+}
+
+impl Cycle {
+    /// The cycle of a board, built on the first move on it
+    fn cycle(&mut self, board: Board) -> Option<&[Direction]> {
+        if self.cycle.as_ref().map(|(b, _)| *b) != Some(board) {
+            self.cycle = Some((board, hamiltonian_cycle(board)));
+        }
+        self.cycle.as_ref().and_then(|(_, cycle)| cycle.as_deref())
+    }
+}
+
+impl Autopilot for Cycle {
+    fn choose(&mut self, sim: &Simulation, snake: usize) -> Option<Direction> {
+        let me = sim.snakes().get(snake)?;
+        if !me.is_alive() {
+            return None;
+        }
+        let board = sim.board();
+        if sim.rules().walls.is_empty() && sim.snakes().len() == 1 {
+            if let Some(cycle) = self.cycle(board) {
+                let dir = cycle[me.head_pos().index(board)];
+                let map = Map::new(sim);
+                if map.moves(me).iter().any(|(d, _)| *d == dir) {
+                    return Some(dir).filter(|dir| *dir != me.dir());
+                }
+            }
+        }
+        SafePath.choose(sim, snake)
+    }
+}
+
+/// The direction to take on each cell to go round the board through every
+/// cell: up the left column, then snaking across the other columns row by
+/// row. Turned sideways when the height is odd; `None` when both sides are.
+fn hamiltonian_cycle(board: Board) -> Option<Vec<Direction>> {
+    let (width, height, sideways) = if board.height.is_multiple_of(2) {
+        (board.width, board.height, false)
+    } else if board.width.is_multiple_of(2) {
+        (board.height, board.width, true)
+    } else {
+        return None;
+    };
+
+    let mut cycle = vec![Direction::Up; board.cells()];
+    for y in 0..height {
+        for x in 0..width {
+            let dir = if x == 0 {
+                if y == 0 {
+                    Direction::Right
+                } else {
+                    Direction::Up
+                }
+            } else if y.is_multiple_of(2) {
+                if x == width - 1 {
+                    Direction::Down
+                } else {
+                    Direction::Right
+                }
+            } else if x == 1 && y != height - 1 {
+                Direction::Down
+            } else {
+                Direction::Left
+            };
+            let (pos, dir) = if sideways {
+                let dir = match dir {
+                    Direction::Up => Direction::Left,
+                    Direction::Down => Direction::Right,
+                    Direction::Left => Direction::Up,
+                    Direction::Right => Direction::Down,
+                };
+                (GridPosition::new(y, x), dir)
+            } else {
+                (GridPosition::new(x, y), dir)
+            };
+            cycle[pos.index(board)] = dir;
+        }
+    }
+    Some(cycle)
+}
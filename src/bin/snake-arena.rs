@@ -0,0 +1,249 @@
+//! Play tournaments between bots, autopilots and agents, without a window.
+//!
+//! See `snake_ggez::arena` for how the games are played and counted.
+
+use snake_ggez::arena::Arena;
+use snake_ggez::arena::Entrant;
+use snake_ggez::arena::Report;
+use snake_ggez::arena::MAX_ENTRANTS;
+use snake_ggez::level::find_level;
+use snake_ggez::level::Level;
+use snake_ggez::settings::Overrides;
+use snake_ggez::settings::Settings;
+use snake_ggez::simulation::random_seed;
+use snake_ggez::simulation::Board;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The number of games, if not given
+const DEFAULT_GAMES: u64 = 1000;
+
+/// The command line arguments
+#[derive(Default)]
+struct Args {
+    /// The entrants, at least one
+    entrants: Vec<Entrant>,
+    /// The settings file for the rules, if not the default settings
+    config: Option<PathBuf>,
+    /// The size of the board, if not the one of the settings
+    board: Option<Board>,
+    /// The level to play on, if not the one of the settings
+    level: Option<String>,
+    /// The number of games, if not `DEFAULT_GAMES`
+    games: Option<u64>,
+    /// The seed of the first game instead of a random one
+    seed: Option<u64>,
+    /// The number of threads, if not one for each CPU
+    threads: Option<usize>,
+    /// The longest game, if not `arena::DEFAULT_MAX_TICKS`
+    max_ticks: Option<u64>,
+    /// How long the agents may think about a move, if not `agent::DEFAULT_TIMEOUT`
+    agent_timeout: Option<Duration>,
+    /// Write the standings to this CSV file
+    csv: Option<PathBuf>,
+    /// Write the report to this JSON file
+    json: Option<PathBuf>,
+    /// Save the replays of the notable games in this directory
+    replays: Option<PathBuf>,
+}
+
+impl Args {
+    const USAGE: &'static str =
+        "Usage: snake-arena [--config <file>] [--grid <width>x<height> | --level <name>] [--games <count>] [--seed <number>] [--threads <count>] [--max-ticks <count>] [--agent-timeout <ms>] [--csv <file>] [--json <file>] [--replays <dir>] <entrant>...\n       <entrant>: [<name>=]greedy | pathfinding | cautious | safe-path | cycle | agent:<command>";
+
+    /// Parse the command line arguments
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let value = iter.next().ok_or("--config needs a file")?;
+                    args.config = Some(PathBuf::from(value));
+                }
+                "--grid" => {
+                    let value = iter.next().ok_or("--grid needs a size")?;
+                    args.board = Some(value.parse()?);
+                }
+                "--level" => {
+                    let value = iter.next().ok_or("--level needs a name or a file")?;
+                    args.level = Some(value);
+                }
+                "--games" => {
+                    let value = iter.next().ok_or("--games needs a count")?;
+                    let games = value
+                        .parse()
+                        .ok()
+                        .filter(|g| *g > 0)
+                        .ok_or_else(|| format!("Invalid game count '{}'", value))?;
+                    args.games = Some(games);
+                }
+                "--seed" => {
+                    let value = iter.next().ok_or("--seed needs a value")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed '{}'", value))?;
+                    args.seed = Some(seed);
+                }
+                "--threads" => {
+                    let value = iter.next().ok_or("--threads needs a count")?;
+                    let threads = value
+                        .parse()
+                        .ok()
+                        .filter(|t| *t > 0)
+                        .ok_or_else(|| format!("Invalid thread count '{}'", value))?;
+                    args.threads = Some(threads);
+                }
+                "--max-ticks" => {
+                    let value = iter.next().ok_or("--max-ticks needs a count")?;
+                    let ticks = value
+                        .parse()
+                        .ok()
+                        .filter(|t| *t > 0)
+                        .ok_or_else(|| format!("Invalid tick count '{}'", value))?;
+                    args.max_ticks = Some(ticks);
+                }
+                "--agent-timeout" => {
+                    let value = iter.next().ok_or("--agent-timeout needs a value")?;
+                    let ms = value
+                        .parse()
+                        .ok()
+                        .filter(|ms| *ms > 0)
+                        .ok_or_else(|| format!("Invalid agent timeout '{}'", value))?;
+                    args.agent_timeout = Some(Duration::from_millis(ms));
+                }
+                "--csv" => {
+                    let value = iter.next().ok_or("--csv needs a file")?;
+                    args.csv = Some(PathBuf::from(value));
+                }
+                "--json" => {
+                    let value = iter.next().ok_or("--json needs a file")?;
+                    args.json = Some(PathBuf::from(value));
+                }
+                "--replays" => {
+                    let value = iter.next().ok_or("--replays needs a directory")?;
+                    args.replays = Some(PathBuf::from(value));
+                }
+                "-h" | "--help" => {
+                    println!("{}", Args::USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown argument '{}'", arg)),
+                _ => args.entrants.push(arg.parse()?),
+            }
+        }
+
+        if args.entrants.is_empty() {
+            return Err("No entrants".to_string());
+        }
+        if args.entrants.len() > MAX_ENTRANTS {
+            return Err(format!("At most {} entrants", MAX_ENTRANTS));
+        }
+        if args.board.is_some() && args.level.is_some() {
+            return Err("--grid and --level can't be used together".to_string());
+        }
+
+        Ok(args)
+    }
+}
+
+/// Set up the tournament from the arguments and the settings
+fn arena(args: &Args) -> Result<Arena, String> {
+    let overrides = Overrides {
+        board: args.board,
+        ..Overrides::default()
+    };
+    let settings =
+        Settings::load_with(args.config.as_deref(), &overrides).map_err(|e| e.to_string())?;
+
+    let mut rules = match args.level.as_ref().or(settings.game.level.as_ref()) {
+        Some(name) => {
+            let path = find_level(name)?;
+            let level = Level::load(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            level.rules
+        }
+        None => settings.rules().map_err(|e| e.to_string())?,
+    };
+    rules.fruit_points = settings.game.fruit_points;
+    rules.fruits = settings.game.fruits.parse()?;
+
+    let mut arena = Arena::new(
+        rules,
+        args.entrants.clone(),
+        args.games.unwrap_or(DEFAULT_GAMES),
+    );
+    arena.seed = args.seed.unwrap_or_else(random_seed);
+    arena.threads = args
+        .threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |threads| threads.get()));
+    if let Some(ticks) = args.max_ticks {
+        arena.max_ticks = ticks;
+    }
+    if let Some(timeout) = args.agent_timeout {
+        arena.agent_timeout = timeout;
+    }
+    Ok(arena)
+}
+
+/// Write the report files asked for, and the replays of the notable games
+fn save(args: &Args, report: &Report) -> Result<(), String> {
+    if let Some(path) = &args.csv {
+        fs::write(path, report.csv()).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &args.json {
+        fs::write(path, report.json()).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(dir) = &args.replays {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        for notable in report.notable.iter() {
+            let path = dir.join(format!("{}.replay", notable.kind));
+            notable
+                .replay
+                .save(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            println!(
+                "{}: game {}, seed {}, {} ticks, saved to {}",
+                notable.kind,
+                notable.result.game,
+                notable.result.seed,
+                notable.result.ticks,
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The main function
+fn main() {
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, Args::USAGE);
+        std::process::exit(2);
+    });
+
+    let arena = arena(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let started = Instant::now();
+    let report = arena.run().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    print!("{}", report.table());
+    println!(
+        "Seed {}, {} threads, {:.1}s",
+        arena.seed,
+        arena.threads,
+        started.elapsed().as_secs_f64()
+    );
+
+    if let Err(e) = save(&args, &report) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,210 @@
+//! Serve a reinforcement-learning environment to other programs, one JSON
+//! message per line on the standard input and output.
+//!
+//! ```text
+//! > {"cmd":"reset","seed":42}
+//! < {"observation":[0.0,1.0,...],"shape":[12],"actions":3}
+//! > {"cmd":"step","action":1}
+//! < {"observation":[...],"reward":0.0,"done":false,"info":{"tick":1,"score":0,"length":1,"death":null,"truncated":false}}
+//! ```
+//!
+//! See `snake_ggez::env` for the actions, the observations and the rewards.
+
+use serde::Deserialize;
+use serde::Serialize;
+use snake_ggez::bot::Difficulty;
+use snake_ggez::env::Action;
+use snake_ggez::env::Env;
+use snake_ggez::env::EnvConfig;
+use snake_ggez::env::Info;
+use snake_ggez::settings::Overrides;
+use snake_ggez::settings::Settings;
+use snake_ggez::simulation::Board;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A message from the trainer
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+enum Request {
+    /// Start a new game
+    Reset {
+        #[serde(default)]
+        seed: u64,
+    },
+    /// Play a tick, with the index of the action in `Action::ALL`
+    Step { action: usize },
+}
+
+/// The answer to a `Request`
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Response {
+    Reset {
+        observation: Vec<f32>,
+        shape: Vec<usize>,
+        actions: usize,
+    },
+    Step {
+        observation: Vec<f32>,
+        reward: f32,
+        done: bool,
+        info: Info,
+    },
+    Error {
+        error: String,
+    },
+}
+
+/// The command line arguments
+#[derive(Default)]
+struct Args {
+    /// The settings file for the rules, if not the default settings
+    config: Option<PathBuf>,
+    /// The size of the board, if not the one of the settings
+    board: Option<Board>,
+    /// The observations, if not `Encoding::Grid`
+    encoding: Option<String>,
+    /// The bots playing against the agent
+    bots: Vec<Difficulty>,
+    /// The longest game, if not `arena::DEFAULT_MAX_TICKS`
+    max_ticks: Option<u64>,
+}
+
+impl Args {
+    const USAGE: &'static str =
+        "Usage: snake-gym [--config <file>] [--grid <width>x<height>] [--encoding grid | features] [--bot <difficulty>]... [--max-ticks <count>]";
+
+    /// Parse the command line arguments
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let value = iter.next().ok_or("--config needs a file")?;
+                    args.config = Some(PathBuf::from(value));
+                }
+                "--grid" => {
+                    let value = iter.next().ok_or("--grid needs a size")?;
+                    args.board = Some(value.parse()?);
+                }
+                "--encoding" => {
+                    let value = iter.next().ok_or("--encoding needs a name")?;
+                    args.encoding = Some(value);
+                }
+                "--bot" => {
+                    let value = iter.next().ok_or("--bot needs a difficulty")?;
+                    args.bots.push(value.parse()?);
+                }
+                "--max-ticks" => {
+                    let value = iter.next().ok_or("--max-ticks needs a count")?;
+                    let ticks = value
+                        .parse()
+                        .ok()
+                        .filter(|t| *t > 0)
+                        .ok_or_else(|| format!("Invalid tick count '{}'", value))?;
+                    args.max_ticks = Some(ticks);
+                }
+                "-h" | "--help" => {
+                    println!("{}", Args::USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Set up the environment from the arguments and the settings
+fn env(args: &Args) -> Result<Env, String> {
+    let overrides = Overrides {
+        board: args.board,
+        ..Overrides::default()
+    };
+    let settings =
+        Settings::load_with(args.config.as_deref(), &overrides).map_err(|e| e.to_string())?;
+
+    let mut config = EnvConfig {
+        rules: settings.rules().map_err(|e| e.to_string())?,
+        bots: args.bots.clone(),
+        ..EnvConfig::default()
+    };
+    config.rules.fruits = settings.game.fruits.parse()?;
+    if let Some(encoding) = &args.encoding {
+        config.encoding = encoding.parse()?;
+    }
+    if let Some(ticks) = args.max_ticks {
+        config.max_ticks = ticks;
+    }
+    Env::new(config)
+}
+
+/// Answer a line of the trainer
+fn answer(env: &mut Env, line: &str) -> Response {
+    let request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Response::Error {
+                error: format!("invalid request: {}", e),
+            }
+        }
+    };
+    match request {
+        Request::Reset { seed } => Response::Reset {
+            observation: env.reset(seed),
+            shape: env.shape(),
+            actions: Action::ALL.len(),
+        },
+        Request::Step { action } => match Action::from_index(action) {
+            Some(action) => {
+                let (observation, reward, done, info) = env.step(action);
+                Response::Step {
+                    observation,
+                    reward,
+                    done,
+                    info,
+                }
+            }
+            None => Response::Error {
+                error: format!("unknown action {}", action),
+            },
+        },
+    }
+}
+
+/// The main function
+fn main() {
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, Args::USAGE);
+        std::process::exit(2);
+    });
+
+    let mut env = env(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let mut output = io::BufWriter::new(io::stdout().lock());
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = answer(&mut env, &line);
+        let written = serde_json::to_writer(&mut output, &response)
+            .map_err(io::Error::other)
+            .and_then(|_| writeln!(output))
+            .and_then(|_| output.flush());
+        if written.is_err() {
+            break;
+        }
+    }
+}
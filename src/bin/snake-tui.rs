@@ -0,0 +1,511 @@
+//! Play in a terminal, such as over SSH on a machine without a display.
+//!
+//! The game is the same simulation as in the window, drawn with text by
+//! `snake_ggez::tui`. It plays alone or against the bots, watches the replays
+//! of the headless runs, or watches a game broadcast by another process.
+
+use crossterm::cursor::Hide;
+use crossterm::cursor::MoveTo;
+use crossterm::cursor::Show;
+use crossterm::event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::style::Color;
+use crossterm::style::Print;
+use crossterm::style::PrintStyledContent;
+use crossterm::style::Stylize;
+use crossterm::terminal;
+use crossterm::terminal::Clear;
+use crossterm::terminal::ClearType;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::LeaveAlternateScreen;
+use snake_ggez::bot::bot_starts;
+use snake_ggez::bot::Bot;
+use snake_ggez::broadcast::Spectator;
+use snake_ggez::broadcast::BROADCAST_PORT;
+use snake_ggez::level::find_level;
+use snake_ggez::level::Level;
+use snake_ggez::net::with_default_port;
+use snake_ggez::net::CONNECT_TIMEOUT;
+use snake_ggez::replay::Replay;
+use snake_ggez::replay::ReplayPlayer;
+use snake_ggez::settings::Overrides;
+use snake_ggez::settings::Settings;
+use snake_ggez::simulation::random_seed;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Event;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::tui;
+use snake_ggez::tui::Charset;
+use snake_ggez::tui::Tile;
+use snake_ggez::turns::TurnQueue;
+use std::io;
+use std::io::Stdout;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The colors of the snakes, in turn
+const SNAKE_COLORS: [Color; 4] = [Color::Green, Color::Cyan, Color::Yellow, Color::Magenta];
+
+/// How often a spectator looks for the next tick of the game watched
+const WATCH_POLL: Duration = Duration::from_millis(20);
+
+/// The command line arguments
+#[derive(Default)]
+struct Args {
+    /// The settings file, if not the default settings
+    config: Option<PathBuf>,
+    /// The board and the bots, if not as the settings say
+    overrides: Overrides,
+    /// The level to play, if not the one of the settings
+    level: Option<String>,
+    /// The seed of every game instead of a random one
+    seed: Option<u64>,
+    /// The replay file to watch instead of playing
+    replay: Option<PathBuf>,
+    /// The address of a broadcast to watch instead of playing
+    watch: Option<String>,
+    /// Draw with ASCII only
+    ascii: bool,
+}
+
+impl Args {
+    const USAGE: &'static str =
+        "Usage: snake-tui [--config <file>] [--grid <width>x<height> | --level <name>] [--bots <count>] [--difficulty <name>] [--seed <number>] [--ascii]\n       snake-tui [--config <file>] [--ascii] --replay <file>\n       snake-tui [--ascii] --watch <address>[:<port>]";
+
+    /// Parse the command line arguments
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let value = iter.next().ok_or("--config needs a file")?;
+                    args.config = Some(PathBuf::from(value));
+                }
+                "--grid" => {
+                    let value = iter.next().ok_or("--grid needs a size")?;
+                    args.overrides.board = Some(value.parse()?);
+                }
+                "--level" => {
+                    let value = iter.next().ok_or("--level needs a name or a file")?;
+                    args.level = Some(value);
+                }
+                "--bots" => {
+                    let value = iter.next().ok_or("--bots needs a count")?;
+                    let count = value
+                        .parse()
+                        .map_err(|_| format!("Invalid bot count '{}'", value))?;
+                    args.overrides.bots = Some(count);
+                }
+                "--difficulty" => {
+                    let value = iter.next().ok_or("--difficulty needs a name")?;
+                    args.overrides.difficulty = Some(value);
+                }
+                "--seed" => {
+                    let value = iter.next().ok_or("--seed needs a value")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed '{}'", value))?;
+                    args.seed = Some(seed);
+                }
+                "--replay" => {
+                    let value = iter.next().ok_or("--replay needs a file")?;
+                    args.replay = Some(PathBuf::from(value));
+                }
+                "--watch" => {
+                    let value = iter.next().ok_or("--watch needs an address")?;
+                    args.watch = Some(value);
+                }
+                "--ascii" => args.ascii = true,
+                "-h" | "--help" => {
+                    println!("{}", Args::USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+
+        if args.overrides.board.is_some() && args.level.is_some() {
+            return Err("--grid and --level can't be used together".to_string());
+        }
+        let playing = args.overrides.board.is_some()
+            || args.level.is_some()
+            || args.overrides.bots.is_some()
+            || args.overrides.difficulty.is_some()
+            || args.seed.is_some();
+        if args.replay.is_some() && (playing || args.watch.is_some()) {
+            return Err("--replay can only be used with --config and --ascii".to_string());
+        }
+        if args.watch.is_some() && (playing || args.config.is_some()) {
+            return Err("--watch can only be used with --ascii".to_string());
+        }
+
+        Ok(args)
+    }
+}
+
+/// Who moves the snakes
+enum Mode {
+    /// The player moves the first snake, and the bots the others
+    Play { turns: TurnQueue, bots: Vec<Bot> },
+    /// A replay moves them
+    Replay(ReplayPlayer),
+    /// They move in the game of another process
+    Watch(Spectator),
+}
+
+/// The game in the terminal
+struct Game {
+    sim: Simulation,
+    mode: Mode,
+    /// The seed of every game instead of a random one
+    fixed_seed: Option<u64>,
+    move_time: Duration,
+    charset: Charset,
+}
+
+impl Game {
+    /// Set up the game from the arguments and the settings
+    fn new(args: &Args) -> Result<Game, String> {
+        let settings = Settings::load_with(args.config.as_deref(), &args.overrides)
+            .map_err(|e| e.to_string())?;
+
+        let charset = if args.ascii {
+            Charset::Ascii
+        } else {
+            Charset::Unicode
+        };
+        let mut game = Game {
+            sim: Simulation::new(Rules::default(), 0),
+            mode: Mode::Play {
+                turns: TurnQueue::new(),
+                bots: Vec::new(),
+            },
+            fixed_seed: args.seed,
+            move_time: settings.move_time(),
+            charset,
+        };
+
+        // A spectator shows the game of another process, on its board
+        if let Some(address) = &args.watch {
+            let address = with_default_port(address, "127.0.0.1", BROADCAST_PORT);
+            let spectator = Spectator::watch(&address, CONNECT_TIMEOUT)
+                .map_err(|e| format!("Could not watch {}: {}", address, e))?;
+            if let Some(sim) = spectator.game() {
+                game.sim = sim.clone();
+            }
+            game.mode = Mode::Watch(spectator);
+            return Ok(game);
+        }
+
+        // Watch a replay instead of playing
+        if let Some(path) = &args.replay {
+            let replay = Replay::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            game.sim = Simulation::new(replay.rules.clone(), replay.seed);
+            game.mode = Mode::Replay(ReplayPlayer::new(replay));
+            return Ok(game);
+        }
+
+        let mut rules = match args.level.as_ref().or(settings.game.level.as_ref()) {
+            Some(name) => {
+                let path = find_level(name)?;
+                let level = Level::load(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                level.rules
+            }
+            None => settings.rules().map_err(|e| e.to_string())?,
+        };
+        rules.fruit_points = settings.game.fruit_points;
+
+        // The bots start in the other corners
+        let mut bots = Vec::new();
+        if settings.bots.count > 0 {
+            rules.rivals = bot_starts(&rules, settings.bots.count);
+            rules.fruits = settings.game.fruits.parse()?;
+            rules
+                .validate()
+                .map_err(|e| format!("No room for the bots: {}", e))?;
+            let difficulty = settings.difficulty().map_err(|e| e.to_string())?;
+            bots = vec![Bot::new(difficulty); settings.bots.count];
+        }
+        game.sim = Simulation::new(rules, args.seed.unwrap_or_else(random_seed));
+        game.mode = Mode::Play {
+            turns: TurnQueue::new(),
+            bots,
+        };
+        Ok(game)
+    }
+
+    /// Play until the player quits, drawing after every key and every tick
+    fn run(&mut self, out: &mut Stdout) -> io::Result<()> {
+        let mut next_tick = Instant::now() + self.move_time;
+        loop {
+            self.draw(out)?;
+
+            let mut timeout = next_tick.saturating_duration_since(Instant::now());
+            if let Mode::Watch(_) = self.mode {
+                timeout = timeout.min(WATCH_POLL);
+            }
+            if event::poll(timeout)? {
+                if let event::Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.key_press(key) {
+                        return Ok(());
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Watch(spectator) = &mut self.mode {
+                let watched = spectator
+                    .poll()
+                    .map_err(|e| io::Error::other(format!("Could not watch: {}", e)))?;
+                if let Some(game) = spectator.game().filter(|_| watched) {
+                    self.sim = game.clone();
+                }
+            }
+            if Instant::now() >= next_tick {
+                self.tick();
+                next_tick = Instant::now() + self.move_time;
+            }
+        }
+    }
+
+    /// Act on a key, and return whether to go on
+    fn key_press(&mut self, key: KeyEvent) -> bool {
+        let ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl_c || matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+            return false;
+        }
+        if let Mode::Watch(_) = self.mode {
+            return true;
+        }
+
+        let dir = match key.code {
+            KeyCode::Up | KeyCode::Char('w') => Some(Direction::Up),
+            KeyCode::Down | KeyCode::Char('s') => Some(Direction::Down),
+            KeyCode::Left | KeyCode::Char('a') => Some(Direction::Left),
+            KeyCode::Right | KeyCode::Char('d') => Some(Direction::Right),
+            _ => None,
+        };
+        let pause = matches!(key.code, KeyCode::Char('p') | KeyCode::Char(' '));
+        match self.sim.state() {
+            GameState::Running if pause => self.sim.set_state(GameState::Paused),
+            GameState::Paused if pause => self.sim.set_state(GameState::Running),
+            GameState::Running => {
+                if let (Some(dir), Mode::Play { turns, .. }) = (dir, &mut self.mode) {
+                    turns.push(&self.sim, dir);
+                }
+            }
+            _ if matches!(key.code, KeyCode::Enter | KeyCode::Char('r')) => self.restart(),
+            _ => {}
+        }
+        true
+    }
+
+    fn restart(&mut self) {
+        let seed = match &mut self.mode {
+            Mode::Play { turns, .. } => {
+                turns.clear();
+                self.fixed_seed.unwrap_or_else(random_seed)
+            }
+            Mode::Replay(player) => {
+                player.rewind();
+                player.replay().seed
+            }
+            Mode::Watch(_) => return,
+        };
+        self.sim.restart(seed);
+    }
+
+    /// Move the snakes by one tick, with the turns of the player and the
+    /// bots, or of the replay
+    fn tick(&mut self) {
+        if self.sim.state() != GameState::Running {
+            return;
+        }
+        let inputs = match &mut self.mode {
+            Mode::Play { turns, bots } => {
+                let mut inputs = vec![turns.pop()];
+                for (i, bot) in bots.iter().enumerate() {
+                    inputs.push(bot.choose(&self.sim, i + 1));
+                }
+                inputs
+            }
+            Mode::Replay(player) => player.inputs(self.sim.tick()),
+            Mode::Watch(_) => return,
+        };
+        let events = self.sim.step_all(&inputs);
+
+        match &self.mode {
+            // Against the bots the game is over when the player dies
+            Mode::Play { bots, .. } => {
+                let player_died = events
+                    .iter()
+                    .any(|e| matches!(e, Event::Died { snake: 0, .. }));
+                if !bots.is_empty() && player_died {
+                    self.sim.set_state(GameState::GameOver);
+                }
+            }
+            // The replay ended before the snake died: the player quit
+            Mode::Replay(player) => {
+                if self.sim.state() == GameState::Running && player.is_finished(self.sim.tick()) {
+                    self.sim.set_state(GameState::GameOver);
+                }
+            }
+            Mode::Watch(_) => {}
+        }
+    }
+
+    /// The names of the snakes on the status line
+    fn names(&self) -> Vec<String> {
+        match &self.mode {
+            Mode::Play { .. } => (0..self.sim.snakes().len())
+                .map(|snake| match snake {
+                    0 => "You".to_string(),
+                    _ => format!("Bot {}", snake),
+                })
+                .collect(),
+            Mode::Replay(_) => Vec::new(),
+            Mode::Watch(spectator) => spectator.players().to_vec(),
+        }
+    }
+
+    /// The line under the status line: what happens, and the keys
+    fn message(&self) -> String {
+        let keys = match (&self.mode, self.sim.state()) {
+            (Mode::Watch(_), _) => "Q to quit",
+            (Mode::Play { .. }, GameState::Running) => "Arrows to turn, P to pause, Q to quit",
+            (Mode::Replay(_), GameState::Running) => "P to pause, Q to quit",
+            (_, GameState::Paused) => "P to resume, Q to quit",
+            (Mode::Play { .. }, _) => "Enter to start, Q to quit",
+            (Mode::Replay(_), _) => "Enter to play the replay, Q to quit",
+        };
+        let state = match self.sim.state() {
+            GameState::Starting | GameState::Running | GameState::Quitting => "",
+            GameState::Paused => "PAUSED",
+            GameState::GameOver => "GAME OVER",
+            GameState::LevelComplete => "LEVEL COMPLETE",
+            GameState::Victory => "VICTORY",
+        };
+        let mode = match self.mode {
+            Mode::Play { .. } => "",
+            Mode::Replay(_) => "REPLAY",
+            Mode::Watch(_) => "WATCHING",
+        };
+        [mode, state, keys]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("   ")
+    }
+
+    /// Draw the board, the status line and the message from the top left
+    fn draw(&self, out: &mut Stdout) -> io::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        let (width, height) = tui::size(&self.sim);
+        queue!(out, MoveTo(0, 0))?;
+        if columns < width || rows < height + 2 {
+            queue!(
+                out,
+                Clear(ClearType::All),
+                Print(format!(
+                    "The terminal is too small: {}x{} needed, Q to quit",
+                    width,
+                    height + 2
+                ))
+            )?;
+            return out.flush();
+        }
+
+        for (y, row) in tui::tiles(&self.sim).into_iter().enumerate() {
+            queue!(out, MoveTo(0, y as u16))?;
+            for tile in row {
+                let glyph = self.charset.glyph(tile);
+                queue!(out, PrintStyledContent(glyph.with(color(tile))))?;
+            }
+            queue!(out, Clear(ClearType::UntilNewLine))?;
+        }
+        queue!(
+            out,
+            MoveTo(0, height),
+            Print(tui::status(&self.sim, &self.names())),
+            Clear(ClearType::UntilNewLine),
+            MoveTo(0, height + 1),
+            Print(self.message()),
+            Clear(ClearType::FromCursorDown)
+        )?;
+        out.flush()
+    }
+}
+
+/// The color of a tile; the dead snakes are grey
+fn color(tile: Tile) -> Color {
+    let snake_color = |snake: usize| SNAKE_COLORS[snake % SNAKE_COLORS.len()];
+    match tile {
+        Tile::Empty => Color::Reset,
+        Tile::Wall | Tile::Edge(_) => Color::DarkGrey,
+        Tile::Fruit { owner: None } => Color::Red,
+        Tile::Fruit { owner: Some(snake) } => snake_color(snake),
+        Tile::Head { alive: false, .. } | Tile::Body { alive: false, .. } => Color::DarkGrey,
+        Tile::Head { snake, .. } | Tile::Body { snake, .. } => snake_color(snake),
+    }
+}
+
+/// The terminal in raw mode on its alternate screen, given back as it was
+/// when dropped, even on a panic
+struct Terminal {
+    out: Stdout,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        if let Err(e) = execute!(out, EnterAlternateScreen, Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(e);
+        }
+        Ok(Terminal { out })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn main() {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, Args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut game = match Game::new(&args) {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let result = Terminal::open().and_then(|mut terminal| game.run(&mut terminal.out));
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,288 @@
+//! Computer-controlled snakes.
+//!
+//! A `Bot` looks at the simulation and chooses the turn of its snake for the
+//! next tick, like a player pressing a key. It sees nothing but the board and
+//! always gives the same answer for the same game, so games with bots stay
+//! deterministic.
+
+use crate::simulation::Board;
+use crate::simulation::Boundary;
+use crate::simulation::Direction;
+use crate::simulation::GridPosition;
+use crate::simulation::Rules;
+use crate::simulation::Simulation;
+use crate::simulation::Snake;
+use crate::simulation::Start;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// The most bots on a board, one in each corner the player doesn't take
+pub const MAX_BOTS: usize = 3;
+
+pub(crate) const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// How well a bot plays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Heads straight for the fruit, only avoiding to die on the next move
+    Greedy,
+    /// Follows the shortest path to the fruit around the obstacles
+    #[default]
+    Pathfinding,
+    /// Follows the shortest path too, but never into a space too small for
+    /// its body nor next to the head of another snake
+    Cautious,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [
+        Difficulty::Greedy,
+        Difficulty::Pathfinding,
+        Difficulty::Cautious,
+    ];
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Greedy => "greedy",
+            Difficulty::Pathfinding => "pathfinding",
+            Difficulty::Cautious => "cautious",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|d| d.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown difficulty '{}', expected greedy, pathfinding or cautious",
+                    s
+                )
+            })
+    }
+}
+
+/// Where the bots start: the player's start mirrored through the center
+/// of the board, then across each axis
+pub fn bot_starts(rules: &Rules, count: usize) -> Vec<Start> {
+    let board = rules.board;
+    let start = rules.start();
+    let flip_x = |s: Start| Start {
+        pos: GridPosition::new(board.width - 1 - s.pos.x, s.pos.y),
+        dir: match s.dir {
+            Direction::Left | Direction::Right => s.dir.reverse(),
+            _ => s.dir,
+        },
+    };
+    let flip_y = |s: Start| Start {
+        pos: GridPosition::new(s.pos.x, board.height - 1 - s.pos.y),
+        dir: match s.dir {
+            Direction::Up | Direction::Down => s.dir.reverse(),
+            _ => s.dir,
+        },
+    };
+    [start.mirrored(board), flip_x(start), flip_y(start)]
+        .into_iter()
+        .take(count)
+        .collect()
+}
+
+/// A computer-controlled snake
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bot {
+    pub difficulty: Difficulty,
+}
+
+impl Bot {
+    pub fn new(difficulty: Difficulty) -> Bot {
+        Bot { difficulty }
+    }
+
+    /// The turn of a snake of the simulation for the next tick, by its index
+    /// in `Simulation::snakes`; `None` to go straight on
+    pub fn choose(&self, sim: &Simulation, snake: usize) -> Option<Direction> {
+        let me = sim.snakes().get(snake)?;
+        if !me.is_alive() {
+            return None;
+        }
+        let map = Map::new(sim);
+        let fruit = sim.fruit_of(snake);
+
+        let mut moves = map.moves(me);
+        if moves.is_empty() {
+            return None;
+        }
+
+        let chosen = match self.difficulty {
+            Difficulty::Greedy => moves
+                .iter()
+                .min_by_key(|(_, pos)| map.distance(*pos, fruit))
+                .copied(),
+            Difficulty::Pathfinding => map.toward(&moves, fruit),
+            Difficulty::Cautious => {
+                // The cells where another snake may put its head
+                let danger: Vec<GridPosition> = sim
+                    .snakes()
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, s)| *i != snake && s.is_alive())
+                    .flat_map(|(_, s)| DIRECTIONS.map(|d| map.next(s.head_pos(), d)))
+                    .flatten()
+                    .collect();
+                let safe: Vec<(Direction, GridPosition)> = moves
+                    .iter()
+                    .copied()
+                    .filter(|(_, pos)| !danger.contains(pos))
+                    .filter(|(_, pos)| map.room(*pos) >= me.length() as usize)
+                    .collect();
+                if !safe.is_empty() {
+                    moves = safe;
+                }
+                map.toward(&moves, fruit)
+            }
+        };
+
+        chosen.map(|(dir, _)| dir).filter(|dir| *dir != me.dir())
+    }
+}
+
+/// The board as a bot sees it: where a snake can go this tick
+#[derive(Clone)]
+pub(crate) struct Map {
+    pub board: Board,
+    pub boundary: Boundary,
+    pub walls: Vec<bool>,
+    /// The walls and the snakes, dead or alive, tails included:
+    /// a tail moves away only after the heads moved
+    pub blocked: Vec<bool>,
+}
+
+impl Map {
+    pub fn new(sim: &Simulation) -> Map {
+        let board = sim.board();
+        let mut walls = vec![false; board.cells()];
+        for wall in sim.rules().walls.iter() {
+            walls[wall.index(board)] = true;
+        }
+        let mut blocked = walls.clone();
+        for snake in sim.snakes() {
+            blocked[snake.head_pos().index(board)] = true;
+            for seg in snake.body().iter() {
+                blocked[seg.index(board)] = true;
+            }
+        }
+        Map {
+            board,
+            boundary: sim.rules().boundary,
+            walls,
+            blocked,
+        }
+    }
+
+    pub fn index(&self, pos: GridPosition) -> usize {
+        pos.index(self.board)
+    }
+
+    pub fn is_blocked(&self, pos: GridPosition) -> bool {
+        self.blocked[self.index(pos)]
+    }
+
+    /// The cells a snake can move to without dying now, going straight first
+    pub fn moves(&self, snake: &Snake) -> Vec<(Direction, GridPosition)> {
+        std::iter::once(snake.dir())
+            .chain(DIRECTIONS.into_iter().filter(|d| *d != snake.dir()))
+            .filter(|d| snake.can_turn(*d))
+            .filter_map(|d| self.next(snake.head_pos(), d).map(|pos| (d, pos)))
+            .filter(|(_, pos)| !self.is_blocked(*pos))
+            .collect()
+    }
+
+    /// The next cell in a direction, unless it's a wall or beyond a walled edge
+    pub fn next(&self, pos: GridPosition, dir: Direction) -> Option<GridPosition> {
+        let mut next = pos;
+        (next.try_move_to_direction(dir, self.board, self.boundary)
+            && !self.walls[self.index(next)])
+        .then_some(next)
+    }
+
+    /// The distance between two cells, as if there were no obstacles
+    pub fn distance(&self, a: GridPosition, b: GridPosition) -> u32 {
+        let mut dx = a.x.abs_diff(b.x);
+        let mut dy = a.y.abs_diff(b.y);
+        if self.boundary.wraps_x() {
+            dx = dx.min(self.board.width - dx);
+        }
+        if self.boundary.wraps_y() {
+            dy = dy.min(self.board.height - dy);
+        }
+        dx + dy
+    }
+
+    /// The length of the shortest free path from a cell to every cell,
+    /// `None` where there is none
+    pub fn distances_from(&self, from: GridPosition) -> Vec<Option<u32>> {
+        let mut distances = vec![None; self.board.cells()];
+        distances[self.index(from)] = Some(0);
+        let mut queue = VecDeque::from([(from, 0)]);
+        while let Some((pos, d)) = queue.pop_front() {
+            for dir in DIRECTIONS {
+                if let Some(next) = self.next(pos, dir) {
+                    let i = self.index(next);
+                    if !self.blocked[i] && distances[i].is_none() {
+                        distances[i] = Some(d + 1);
+                        queue.push_back((next, d + 1));
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// The number of free cells reachable from a free cell, itself included
+    pub fn room(&self, from: GridPosition) -> usize {
+        self.distances_from(from)
+            .iter()
+            .filter(|d| d.is_some())
+            .count()
+    }
+
+    /// The move on the shortest path to a cell, or the one with the most room
+    /// after it if there is no path
+    pub fn toward(
+        &self,
+        moves: &[(Direction, GridPosition)],
+        to: GridPosition,
+    ) -> Option<(Direction, GridPosition)> {
+        let distances = self.distances_from(to);
+        moves
+            .iter()
+            .filter_map(|m| distances[self.index(m.1)].map(|d| (*m, d)))
+            .min_by_key(|(_, d)| *d)
+            .map(|(m, _)| m)
+            .or_else(|| self.roomiest(moves))
+    }
+
+    /// The move with the most room after it, the first one on a tie
+    pub fn roomiest(
+        &self,
+        moves: &[(Direction, GridPosition)],
+    ) -> Option<(Direction, GridPosition)> {
+        moves
+            .iter()
+            .copied()
+            .rev()
+            .max_by_key(|(_, pos)| self.room(*pos))
+    }
+}
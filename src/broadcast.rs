@@ -0,0 +1,213 @@
+//! Live broadcast of a game to spectators.
+//!
+//! A game publishes itself with a `Broadcaster`, and other processes watch it
+//! with a `Spectator`, such as a second screen in the office. Spectators only
+//! listen: they connect, get a snapshot of the whole game, then the game
+//! after every tick, as JSON over TCP, one message per line:
+//!
+//! ```text
+//! < {"type":"snapshot","protocol":1,"rules":{...},"players":["Ada","Bot 1"],"frame":{"tick":12,...}}
+//! < {"type":"frame","tick":13,"state":"Running","snakes":[...],"fruits":[...]}
+//! ```
+//!
+//! A new snapshot comes whenever the rules or the players change, such as at
+//! the next level of the campaign or the next round over the network.
+
+use crate::net::Connection;
+use crate::net::NetError;
+use crate::simulation::Frame;
+use crate::simulation::GameState;
+use crate::simulation::Rules;
+use crate::simulation::Simulation;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::ToSocketAddrs;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The port of a broadcast when the address doesn't have one, next to the
+/// one of the games over the network
+pub const BROADCAST_PORT: u16 = 7358;
+
+/// The version of the messages; a spectator of another version stops watching
+pub const PROTOCOL: u32 = 1;
+
+/// A spectator with this many bytes not sent yet can't keep up, and is dropped
+const MAX_BACKLOG: usize = 1 << 20;
+
+/// A message from the game to its spectators
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum BroadcastMessage {
+    /// The whole game, with the names of the players by snake: the first
+    /// message, and again when the rules or the players change
+    Snapshot {
+        protocol: u32,
+        rules: Rules,
+        players: Vec<String>,
+        frame: Frame,
+    },
+    /// The game after a tick
+    Frame(Frame),
+}
+
+/// Publishes a game to the spectators who connect
+pub struct Broadcaster {
+    listener: TcpListener,
+    spectators: Vec<Connection>,
+    /// The rules published last, if any
+    rules: Option<Rules>,
+    /// The players published last
+    players: Vec<String>,
+    /// The frame published last, if any
+    frame: Option<Frame>,
+}
+
+impl Broadcaster {
+    /// Listen for spectators on an address, such as `0.0.0.0:7358`
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Broadcaster> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcaster {
+            listener,
+            spectators: Vec::new(),
+            rules: None,
+            players: Vec::new(),
+            frame: None,
+        })
+    }
+
+    /// The address the spectators connect to, with the port chosen by the
+    /// system if bound to port 0
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// How many spectators are watching
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// Take the new spectators, and send the game if it changed: the frame,
+    /// or a snapshot when the rules or the players changed. The new
+    /// spectators get a snapshot. A spectator whose connection fails, or who
+    /// can't keep up, is dropped.
+    pub fn publish(&mut self, rules: &Rules, players: &[String], mut frame: Frame) {
+        // The spectators see the quit screen as a pause
+        if frame.state == GameState::Quitting {
+            frame.state = GameState::Paused;
+        }
+        let snapshot = || BroadcastMessage::Snapshot {
+            protocol: PROTOCOL,
+            rules: rules.clone(),
+            players: players.to_vec(),
+            frame: frame.clone(),
+        };
+        let message = if self.rules.as_ref() != Some(rules) || self.players != players {
+            self.rules = Some(rules.clone());
+            self.players = players.to_vec();
+            Some(snapshot())
+        } else if self.frame.as_ref() != Some(&frame) {
+            Some(BroadcastMessage::Frame(frame.clone()))
+        } else {
+            None
+        };
+        if let Some(message) = &message {
+            self.spectators
+                .retain_mut(|conn| conn.send(message).is_ok() && conn.backlog() < MAX_BACKLOG);
+        }
+
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(mut conn) = Connection::new(stream) {
+                if conn.send(&snapshot()).is_ok() {
+                    self.spectators.push(conn);
+                }
+            }
+        }
+        self.frame = Some(frame);
+    }
+}
+
+/// Watches the game published by another process
+pub struct Spectator {
+    conn: Connection,
+    /// The game as last received, once the first snapshot came
+    game: Option<Simulation>,
+    /// The names of the players by snake
+    players: Vec<String>,
+}
+
+impl Spectator {
+    /// Watch the game published at an address, such as `192.168.1.20:7358`
+    pub fn connect(address: impl ToSocketAddrs) -> Result<Spectator, NetError> {
+        Ok(Spectator {
+            conn: Connection::connect(address)?,
+            game: None,
+            players: Vec::new(),
+        })
+    }
+
+    /// Watch the game published at an address, waiting up to `timeout` for
+    /// the first snapshot
+    pub fn watch(address: impl ToSocketAddrs, timeout: Duration) -> Result<Spectator, NetError> {
+        let mut spectator = Spectator::connect(address)?;
+        let deadline = Instant::now() + timeout;
+        while spectator.game.is_none() {
+            if Instant::now() >= deadline {
+                let e = io::Error::new(io::ErrorKind::TimedOut, "no game came");
+                return Err(e.into());
+            }
+            spectator.poll()?;
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(spectator)
+    }
+
+    /// Follow the game with the messages received since the last call,
+    /// without waiting. Return whether the game changed.
+    pub fn poll(&mut self) -> Result<bool, NetError> {
+        let mut changed = false;
+        for message in self.conn.receive()? {
+            match message {
+                BroadcastMessage::Snapshot {
+                    protocol,
+                    rules,
+                    players,
+                    frame,
+                } => {
+                    if protocol != PROTOCOL {
+                        return Err(NetError::Invalid(format!(
+                            "the game is broadcast in version {}, not {}",
+                            protocol, PROTOCOL
+                        )));
+                    }
+                    rules.validate().map_err(NetError::Invalid)?;
+                    let mut game = Simulation::new(rules, 0);
+                    game.apply_frame(frame).map_err(NetError::Invalid)?;
+                    self.game = Some(game);
+                    self.players = players;
+                }
+                BroadcastMessage::Frame(frame) => match &mut self.game {
+                    Some(game) => game.apply_frame(frame).map_err(NetError::Invalid)?,
+                    None => continue,
+                },
+            }
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /// The game watched, once the first snapshot came
+    pub fn game(&self) -> Option<&Simulation> {
+        self.game.as_ref()
+    }
+
+    /// The names of the players by snake
+    pub fn players(&self) -> &[String] {
+        &self.players
+    }
+}
@@ -0,0 +1,159 @@
+//! A campaign: a list of levels played one after the other.
+//!
+//! The campaign is described by `campaign.list` in the levels directory,
+//! with the names of its stages in order, one per line:
+//!
+//! ```text
+//! ; Lines starting with ';' are comments
+//! box
+//! cross
+//! rooms
+//! ```
+//!
+//! Every stage must have a goal: reaching it unlocks the next stage.
+//! Which stages are unlocked and the best score of each one are kept
+//! in a `Progress`, saved between two runs of the game.
+
+use crate::level::Level;
+use crate::level::LevelError;
+use crate::level::LEVEL_EXTENSION;
+use crate::toml_file;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The name of the campaign file in the levels directory
+pub const CAMPAIGN_FILE: &str = "campaign.list";
+
+/// The name of the progress file in the user data directory
+pub const PROGRESS_FILE: &str = "campaign.toml";
+
+/// Everything that can go wrong loading a campaign or its progress
+#[derive(Debug)]
+pub enum CampaignError {
+    Io(PathBuf, io::Error),
+    /// A stage is not a valid level
+    Level(PathBuf, LevelError),
+    /// A stage has no goal, so it can't be completed
+    NoGoal(String),
+    /// The campaign file lists no stages
+    Empty(PathBuf),
+    /// The progress file is corrupted
+    Progress(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for CampaignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CampaignError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            CampaignError::Level(path, e) => write!(f, "{}: {}", path.display(), e),
+            CampaignError::NoGoal(id) => write!(f, "the stage '{}' has no goal", id),
+            CampaignError::Empty(path) => write!(f, "{}: no stages", path.display()),
+            CampaignError::Progress(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for CampaignError {}
+
+/// A stage of the campaign
+#[derive(Clone, Debug)]
+pub struct Stage {
+    /// The name of the level file, which identifies the stage
+    pub id: String,
+    pub level: Level,
+}
+
+/// The stages of a campaign, in order
+#[derive(Clone, Debug)]
+pub struct Campaign {
+    pub stages: Vec<Stage>,
+}
+
+impl Campaign {
+    /// Load the campaign of a levels directory, with all its stages
+    pub fn load(dir: &Path) -> Result<Campaign, CampaignError> {
+        let path = dir.join(CAMPAIGN_FILE);
+        let text = fs::read_to_string(&path).map_err(|e| CampaignError::Io(path.clone(), e))?;
+
+        let mut stages = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let level_path = dir.join(line).with_extension(LEVEL_EXTENSION);
+            let level =
+                Level::load(&level_path).map_err(|e| CampaignError::Level(level_path, e))?;
+            if level.rules.goal.is_none() {
+                return Err(CampaignError::NoGoal(line.to_string()));
+            }
+            stages.push(Stage {
+                id: line.to_string(),
+                level,
+            });
+        }
+
+        if stages.is_empty() {
+            return Err(CampaignError::Empty(path));
+        }
+        Ok(Campaign { stages })
+    }
+}
+
+/// What the player achieved in the campaign
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    /// The number of stages completed so far; all of them plus the next one are unlocked
+    pub completed: usize,
+    /// The best score of each stage, by stage id
+    pub best: BTreeMap<String, u32>,
+}
+
+impl Progress {
+    /// Load the progress from a file, starting from scratch if it doesn't exist
+    pub fn load(path: &Path) -> Result<Progress, CampaignError> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|e| CampaignError::Progress(path.to_path_buf(), e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Progress::default()),
+            Err(e) => Err(CampaignError::Io(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Save the progress to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        toml_file::save(path, self)
+    }
+
+    /// Check if the stage with the given index can be played
+    pub fn is_unlocked(&self, stage: usize) -> bool {
+        stage <= self.completed
+    }
+
+    /// The best score of a stage, 0 if never played
+    pub fn best(&self, id: &str) -> u32 {
+        self.best.get(id).copied().unwrap_or(0)
+    }
+
+    /// Record the score of a game of a stage; return true if it's a new best
+    pub fn record_score(&mut self, id: &str, score: u32) -> bool {
+        if score > self.best(id) {
+            self.best.insert(id.to_string(), score);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Record that the stage with the given index was completed, unlocking the next one
+    pub fn complete(&mut self, stage: usize) {
+        self.completed = self.completed.max(stage + 1);
+    }
+}
@@ -0,0 +1,575 @@
+//! The keys that drive the game.
+//!
+//! Every command of the game is an `Action`, bound to one or more keys.
+//! The bindings start from a `Preset` and can be changed one action at a time,
+//! in the settings or on the controls screen of the game, which saves them
+//! in `controls.toml` in the user config directory.
+//!
+//! Keys are named after the ggez `KeyCode` variants, e.g. `"Up"`, `"W"`,
+//! `"Space"` or `"Key1"`.
+
+use crate::settings::invalid;
+use crate::settings::KeySettings;
+use crate::settings::SettingsError;
+use crate::simulation::Direction;
+use crate::toml_file;
+use ggez::input::keyboard::KeyCode;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Build the list of the keys that can be named in the settings
+macro_rules! key_codes {
+    ($($key:ident),* $(,)?) => {
+        &[$(KeyCode::$key),*]
+    };
+}
+
+/// The keys that can be bound
+const KEY_CODES: &[KeyCode] = key_codes![
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Escape,
+    Return,
+    Tab,
+    Back,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Pause,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    Comma,
+    Period,
+    Slash,
+    Semicolon,
+    Apostrophe,
+    Minus,
+    Equals,
+    LBracket,
+    RBracket,
+    Backslash,
+    Grave,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadEnter,
+];
+
+/// The name of the bindings file in the user config directory
+pub const CONTROLS_FILE: &str = "controls.toml";
+
+/// Find a key by its name
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_CODES
+        .iter()
+        .copied()
+        .find(|k| key_name(*k).eq_ignore_ascii_case(name))
+}
+
+/// The name of a key, as `key_from_name` finds it
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// A command of the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    TurnUp,
+    TurnDown,
+    TurnLeft,
+    TurnRight,
+    Pause,
+    ToggleGrid,
+    ToggleFps,
+    Quit,
+    /// Answer yes: play again, or really quit
+    Confirm,
+    /// Answer no
+    Cancel,
+    SaveAndQuit,
+    /// Continue the saved game
+    Continue,
+    HighScores,
+    /// Open the controls screen
+    Bindings,
+    /// Let the autopilot drive the snake, or take it back
+    Assist,
+}
+
+impl Action {
+    /// All the actions, in the order of the controls screen
+    pub const ALL: [Action; 15] = [
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Pause,
+        Action::ToggleGrid,
+        Action::ToggleFps,
+        Action::Quit,
+        Action::Confirm,
+        Action::Cancel,
+        Action::SaveAndQuit,
+        Action::Continue,
+        Action::HighScores,
+        Action::Bindings,
+        Action::Assist,
+    ];
+
+    /// The name of the action in the settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::TurnUp => "up",
+            Action::TurnDown => "down",
+            Action::TurnLeft => "left",
+            Action::TurnRight => "right",
+            Action::Pause => "pause",
+            Action::ToggleGrid => "toggle_grid",
+            Action::ToggleFps => "toggle_fps",
+            Action::Quit => "quit",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::SaveAndQuit => "save",
+            Action::Continue => "continue",
+            Action::HighScores => "high_scores",
+            Action::Bindings => "bindings",
+            Action::Assist => "assist",
+        }
+    }
+
+    /// Find an action by its name in the settings
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// The action that turns the snake in a direction
+    pub fn turning(dir: Direction) -> Action {
+        match dir {
+            Direction::Up => Action::TurnUp,
+            Direction::Down => Action::TurnDown,
+            Direction::Left => Action::TurnLeft,
+            Direction::Right => Action::TurnRight,
+        }
+    }
+
+    /// The direction the action turns the snake to, if it's a turn
+    pub fn turn(&self) -> Option<Direction> {
+        match self {
+            Action::TurnUp => Some(Direction::Up),
+            Action::TurnDown => Some(Direction::Down),
+            Action::TurnLeft => Some(Direction::Left),
+            Action::TurnRight => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    /// The description of the action on the controls screen
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Action::TurnUp => "Turn up",
+            Action::TurnDown => "Turn down",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::Pause => "Pause",
+            Action::ToggleGrid => "Show / hide the grid",
+            Action::ToggleFps => "Show / hide the FPS",
+            Action::Quit => "Quit",
+            Action::Confirm => "Yes",
+            Action::Cancel => "No",
+            Action::SaveAndQuit => "Save and quit",
+            Action::Continue => "Continue the saved game",
+            Action::HighScores => "High scores",
+            Action::Bindings => "Controls",
+            Action::Assist => "Autopilot on / off",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// The actions used on the same screen, which can't share a key
+const SCREENS: [&[Action]; 4] = [
+    // Playing
+    &[
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Pause,
+        Action::ToggleGrid,
+        Action::ToggleFps,
+        Action::Quit,
+        Action::Assist,
+    ],
+    // The start screen, where left and right choose the stage of the campaign
+    &[
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Continue,
+        Action::HighScores,
+        Action::Bindings,
+        Action::Quit,
+    ],
+    // Watching a replay: up and down change the speed, right steps
+    &[
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::TurnRight,
+        Action::Pause,
+        Action::ToggleGrid,
+        Action::ToggleFps,
+        Action::Quit,
+    ],
+    // The questions: quit, play again
+    &[
+        Action::Confirm,
+        Action::Cancel,
+        Action::SaveAndQuit,
+        Action::Quit,
+    ],
+];
+
+/// A set of bindings to start from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Preset {
+    /// The arrow keys
+    #[default]
+    Arrows,
+    /// W, A, S and D
+    Wasd,
+    /// H, J, K and L, like in vi
+    Vim,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Arrows, Preset::Wasd, Preset::Vim];
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Preset::Arrows => "arrows",
+            Preset::Wasd => "wasd",
+            Preset::Vim => "vim",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arrows" => Ok(Preset::Arrows),
+            "wasd" => Ok(Preset::Wasd),
+            "vim" => Ok(Preset::Vim),
+            _ => Err(format!(
+                "unknown preset '{}', expected arrows, wasd or vim",
+                s
+            )),
+        }
+    }
+}
+
+/// Two actions of the same screen bound to the same key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: KeyCode,
+    pub actions: (Action, Action),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is bound to both '{}' and '{}'",
+            key_name(self.key),
+            self.actions.0,
+            self.actions.1
+        )
+    }
+}
+
+/// A key or a gamepad button pressed, with the actions it is bound to
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Press {
+    /// The key pressed, `None` for a gamepad
+    pub key: Option<KeyCode>,
+    pub actions: Vec<Action>,
+}
+
+impl Press {
+    /// Check if the press is bound to an action
+    pub fn is(&self, action: Action) -> bool {
+        self.actions.contains(&action)
+    }
+
+    /// The direction the press turns the snake to, if it's bound to a turn
+    pub fn turn(&self) -> Option<Direction> {
+        self.actions.iter().find_map(Action::turn)
+    }
+}
+
+/// The keys bound to each action
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Controls {
+    keys: BTreeMap<Action, Vec<KeyCode>>,
+}
+
+impl Controls {
+    /// The bindings of a preset
+    pub fn preset(preset: Preset) -> Controls {
+        let (up, down, left, right) = match preset {
+            Preset::Arrows => (KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right),
+            Preset::Wasd => (KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D),
+            Preset::Vim => (KeyCode::K, KeyCode::J, KeyCode::H, KeyCode::L),
+        };
+        // H turns left in vi, on the start screen too
+        let high_scores = match preset {
+            Preset::Vim => KeyCode::T,
+            _ => KeyCode::H,
+        };
+
+        let keys = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = match action {
+                    Action::TurnUp => vec![up],
+                    Action::TurnDown => vec![down],
+                    Action::TurnLeft => vec![left],
+                    Action::TurnRight => vec![right],
+                    Action::Pause => vec![KeyCode::P, KeyCode::Space],
+                    Action::ToggleGrid => vec![KeyCode::G],
+                    Action::ToggleFps => vec![KeyCode::F],
+                    Action::Quit => vec![KeyCode::Q, KeyCode::Escape],
+                    Action::Confirm => vec![KeyCode::Y],
+                    Action::Cancel => vec![KeyCode::N],
+                    Action::SaveAndQuit => vec![KeyCode::S],
+                    Action::Continue => vec![KeyCode::C],
+                    Action::HighScores => vec![high_scores],
+                    Action::Bindings => vec![KeyCode::B],
+                    Action::Assist => vec![KeyCode::Tab],
+                };
+                (action, keys)
+            })
+            .collect();
+        Controls { keys }
+    }
+
+    /// Resolve the key names of the settings, over the bindings of their preset
+    pub fn from_settings(settings: &KeySettings) -> Result<Controls, SettingsError> {
+        let preset = settings
+            .preset
+            .parse()
+            .map_err(|e: String| invalid("keys.preset", e))?;
+        let mut controls = Controls::preset(preset);
+
+        for (name, names) in settings.bindings.iter() {
+            let key = format!("keys.{}", name);
+            let action = Action::from_name(name)
+                .ok_or_else(|| invalid(&key, format!("unknown action '{}'", name)))?;
+            if names.is_empty() {
+                return Err(invalid(&key, "at least one key is needed"));
+            }
+            let keys = names
+                .iter()
+                .map(|n| {
+                    key_from_name(n).ok_or_else(|| invalid(&key, format!("unknown key '{}'", n)))
+                })
+                .collect::<Result<_, _>>()?;
+            controls.keys.insert(action, keys);
+        }
+
+        if let Some(conflict) = controls.conflicts().first() {
+            let key = format!("keys.{}", conflict.actions.1.name());
+            return Err(invalid(&key, conflict.to_string()));
+        }
+        Ok(controls)
+    }
+
+    /// The settings that give these bindings, with every action listed
+    pub fn to_settings(&self) -> KeySettings {
+        let bindings = self
+            .keys
+            .iter()
+            .map(|(action, keys)| {
+                let names = keys.iter().map(|k| key_name(*k)).collect();
+                (action.name().to_string(), names)
+            })
+            .collect();
+        KeySettings {
+            preset: Preset::default().to_string(),
+            bindings,
+        }
+    }
+
+    /// Load the bindings saved by `save`, or `None` if there are none
+    pub fn load(path: &Path) -> Result<Option<Controls>, SettingsError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(SettingsError::Io(path.to_path_buf(), e)),
+        };
+        let settings: KeySettings =
+            toml::from_str(&text).map_err(|e| SettingsError::Parse(path.to_path_buf(), e))?;
+        Controls::from_settings(&settings).map(Some)
+    }
+
+    /// Save the bindings to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        toml_file::save(path, &self.to_settings())
+    }
+
+    /// The keys bound to an action
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Check if a key is bound to an action
+    pub fn is(&self, action: Action, key: KeyCode) -> bool {
+        self.keys(action).contains(&key)
+    }
+
+    /// The name of the first key bound to an action, to show it in the texts
+    pub fn key_name(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map_or_else(|| "?".to_string(), |k| key_name(*k))
+    }
+
+    /// A key pressed, with all the actions it's bound to
+    pub fn press(&self, key: KeyCode) -> Press {
+        Press {
+            key: Some(key),
+            actions: Action::ALL
+                .into_iter()
+                .filter(|a| self.is(*a, key))
+                .collect(),
+        }
+    }
+
+    /// The direction a key turns the snake to, if it's bound to a turn
+    pub fn turn(&self, key: KeyCode) -> Option<Direction> {
+        self.press(key).turn()
+    }
+
+    /// Bind a single key to an action, unless it conflicts with another action:
+    /// then the bindings don't change and the conflict is returned.
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> Result<(), Conflict> {
+        let old = self.keys.insert(action, vec![key]);
+        let conflict = self
+            .conflicts()
+            .into_iter()
+            .find(|c| c.actions.0 == action || c.actions.1 == action);
+        match conflict {
+            Some(conflict) => {
+                if let Some(old) = old {
+                    self.keys.insert(action, old);
+                }
+                Err(conflict)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The keys bound to two actions of the same screen
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for screen in SCREENS {
+            for (i, a) in screen.iter().enumerate() {
+                for b in &screen[i + 1..] {
+                    for key in self.keys(*a).iter().filter(|k| self.is(*b, **k)) {
+                        let conflict = Conflict {
+                            key: *key,
+                            actions: (*a, *b),
+                        };
+                        if !conflicts.contains(&conflict) {
+                            conflicts.push(conflict);
+                        }
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls::preset(Preset::default())
+    }
+}
@@ -0,0 +1,390 @@
+//! A reinforcement-learning environment, in the style of OpenAI Gym.
+//!
+//! An `Env` wraps a `Simulation` where the learning agent drives the first
+//! snake, and bots may drive the others. `Env::reset` starts a game and
+//! returns the first observation; `Env::step` plays one tick with an `Action`
+//! and returns the next observation, the reward, whether the game is over,
+//! and some `Info` about it.
+//!
+//! The observation is a flat vector of numbers, encoded as the `Encoding` of
+//! the `EnvConfig` says. The reward comes from a `Reward`: `Rewards` by
+//! default, or any function of the simulation and of the events of the tick.
+
+use crate::arena::DEFAULT_MAX_TICKS;
+use crate::bot::bot_starts;
+use crate::bot::Bot;
+use crate::bot::Difficulty;
+use crate::bot::Map;
+use crate::bot::MAX_BOTS;
+use crate::simulation::Death;
+use crate::simulation::Direction;
+use crate::simulation::Event;
+use crate::simulation::GameState;
+use crate::simulation::GridPosition;
+use crate::simulation::Rules;
+use crate::simulation::Simulation;
+use crate::simulation::FRUIT_POINTS;
+use serde::Serialize;
+use std::fmt;
+
+/// The channels of `Encoding::Grid`: the head and the body of the snake, its
+/// fruit, the walls, and the other snakes
+pub const GRID_CHANNELS: usize = 5;
+
+/// The length of `Encoding::Features`
+pub const FEATURES: usize = 12;
+
+/// What the snake does at the next tick, relative to where it's going, so
+/// that every action is a valid move
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Straight,
+    /// Turn to the left of the head
+    Left,
+    /// Turn to the right of the head
+    Right,
+}
+
+impl Action {
+    /// All the actions, by their index
+    pub const ALL: [Action; 3] = [Action::Straight, Action::Left, Action::Right];
+
+    /// The action with the given index in `ALL`, for discrete action spaces
+    pub fn from_index(index: usize) -> Option<Action> {
+        Action::ALL.get(index).copied()
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Action::Straight => 0,
+            Action::Left => 1,
+            Action::Right => 2,
+        }
+    }
+
+    /// The direction of a snake going `dir` after the action
+    pub fn direction(&self, dir: Direction) -> Direction {
+        match self {
+            Action::Straight => dir,
+            Action::Left => left_of(dir),
+            Action::Right => left_of(dir).reverse(),
+        }
+    }
+}
+
+/// The direction to the left of a snake going `dir`
+fn left_of(dir: Direction) -> Direction {
+    match dir {
+        Direction::Up => Direction::Left,
+        Direction::Left => Direction::Down,
+        Direction::Down => Direction::Right,
+        Direction::Right => Direction::Up,
+    }
+}
+
+/// How the board is turned into an observation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// A tensor of `GRID_CHANNELS` channels of the size of the board, channel
+    /// first then row by row: 1 where the channel has something, else 0
+    #[default]
+    Grid,
+    /// `FEATURES` numbers seen from the head: whether going straight, left or
+    /// right is deadly, the direction of the snake (up, down, left, right),
+    /// where its fruit is (up, down, left, right, the shortest way round) and
+    /// the share of the board its snake fills
+    Features,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 2] = [Encoding::Grid, Encoding::Features];
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Grid => "grid",
+            Encoding::Features => "features",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::ALL
+            .into_iter()
+            .find(|e| e.to_string() == s)
+            .ok_or_else(|| format!("unknown encoding '{}', expected grid or features", s))
+    }
+}
+
+/// Reward shaping: the reward of a tick, from the simulation after the tick
+/// and what happened during it. The learning agent drives snake 0.
+pub trait Reward {
+    fn reward(&mut self, sim: &Simulation, events: &[Event]) -> f32;
+}
+
+impl<F: FnMut(&Simulation, &[Event]) -> f32> Reward for F {
+    fn reward(&mut self, sim: &Simulation, events: &[Event]) -> f32 {
+        self(sim, events)
+    }
+}
+
+/// The default rewards, added up over the events of a tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rewards {
+    /// For `FRUIT_POINTS` points: a fruit, or a part of the victory bonus
+    pub fruit: f32,
+    /// When the snake dies
+    pub death: f32,
+    /// At every tick, negative to hurry the snake
+    pub step: f32,
+    /// When the snake completes the level, or wins against the bots
+    pub win: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Rewards {
+            fruit: 1.0,
+            death: -1.0,
+            step: 0.0,
+            win: 1.0,
+        }
+    }
+}
+
+impl Reward for Rewards {
+    fn reward(&mut self, _sim: &Simulation, events: &[Event]) -> f32 {
+        let fruits = |points: u32| self.fruit * points as f32 / FRUIT_POINTS as f32;
+        self.step
+            + events
+                .iter()
+                .map(|event| match event {
+                    Event::FruitEaten { snake: 0, points } => fruits(*points),
+                    Event::Victory { bonus } => fruits(*bonus),
+                    Event::GameOver(_) | Event::Died { snake: 0, .. } => self.death,
+                    Event::LevelComplete | Event::RoundOver { winner: Some(0) } => self.win,
+                    _ => 0.0,
+                })
+                .sum::<f32>()
+    }
+}
+
+/// How the environment plays
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvConfig {
+    /// The rules of the games; the starts of the rivals are replaced by the
+    /// ones of the bots
+    pub rules: Rules,
+    pub encoding: Encoding,
+    /// The bots playing against the agent, if any
+    pub bots: Vec<Difficulty>,
+    /// A game is cut short after this many ticks
+    pub max_ticks: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            rules: Rules::default(),
+            encoding: Encoding::default(),
+            bots: Vec::new(),
+            max_ticks: DEFAULT_MAX_TICKS,
+        }
+    }
+}
+
+/// What happened at a step, besides the reward
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Info {
+    pub tick: u64,
+    pub score: u32,
+    pub length: u32,
+    /// How the snake died, if it did
+    pub death: Option<Death>,
+    /// The game was cut short by `EnvConfig::max_ticks`
+    pub truncated: bool,
+}
+
+/// A game to train agents on
+pub struct Env {
+    config: EnvConfig,
+    sim: Simulation,
+    bots: Vec<Bot>,
+    reward: Box<dyn Reward>,
+    /// The game is over, until the next reset
+    done: bool,
+}
+
+impl Env {
+    /// A new environment, ready to play with the seed 0
+    pub fn new(config: EnvConfig) -> Result<Env, String> {
+        if config.bots.len() > MAX_BOTS {
+            return Err(format!("at most {} bots", MAX_BOTS));
+        }
+        let mut rules = config.rules.clone();
+        rules.rivals = bot_starts(&rules, config.bots.len());
+        rules.validate()?;
+
+        let mut sim = Simulation::new(rules, 0);
+        sim.restart(0);
+        let bots = config.bots.iter().map(|d| Bot::new(*d)).collect();
+        Ok(Env {
+            config,
+            sim,
+            bots,
+            reward: Box::new(Rewards::default()),
+            done: false,
+        })
+    }
+
+    /// Shape the rewards with another `Reward`
+    pub fn with_reward(mut self, reward: impl Reward + 'static) -> Env {
+        self.reward = Box::new(reward);
+        self
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// The game being played
+    pub fn sim(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// The shape of the observations: channels, height and width for
+    /// `Encoding::Grid`, the number of features for `Encoding::Features`
+    pub fn shape(&self) -> Vec<usize> {
+        let board = self.sim.board();
+        match self.config.encoding {
+            Encoding::Grid => vec![GRID_CHANNELS, board.height as usize, board.width as usize],
+            Encoding::Features => vec![FEATURES],
+        }
+    }
+
+    /// Start a new game, and return its first observation
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.sim.restart(seed);
+        self.done = false;
+        self.observe()
+    }
+
+    /// Play one tick, and return the next observation, the reward, whether
+    /// the game is over and what happened. Once the game is over, nothing
+    /// happens until the next reset.
+    pub fn step(&mut self, action: Action) -> (Vec<f32>, f32, bool, Info) {
+        if self.done {
+            return (self.observe(), 0.0, true, self.info());
+        }
+
+        let me = self.sim.player();
+        let dir = action.direction(me.dir());
+        let mut inputs = vec![Some(dir).filter(|d| *d != me.dir())];
+        for (i, bot) in self.bots.iter().enumerate() {
+            inputs.push(bot.choose(&self.sim, i + 1));
+        }
+        let events = self.sim.step_all(&inputs);
+
+        // Against the bots the game is over when the agent dies
+        if !self.sim.player().is_alive() {
+            self.sim.set_state(GameState::GameOver);
+        }
+        let reward = self.reward.reward(&self.sim, &events);
+        self.done = self.sim.state() != GameState::Running || self.truncated();
+        (self.observe(), reward, self.done, self.info())
+    }
+
+    /// The observation of the current game
+    pub fn observe(&self) -> Vec<f32> {
+        match self.config.encoding {
+            Encoding::Grid => self.grid(),
+            Encoding::Features => self.features(),
+        }
+    }
+
+    fn truncated(&self) -> bool {
+        self.sim.state() == GameState::Running && self.sim.tick() >= self.config.max_ticks
+    }
+
+    fn info(&self) -> Info {
+        let me = self.sim.player();
+        Info {
+            tick: self.sim.tick(),
+            score: me.score(),
+            length: me.length(),
+            death: me.death(),
+            truncated: self.truncated(),
+        }
+    }
+
+    fn grid(&self) -> Vec<f32> {
+        let board = self.sim.board();
+        let cells = board.cells();
+        let mut grid = vec![0.0; GRID_CHANNELS * cells];
+        let mut set = |channel: usize, pos: GridPosition| {
+            grid[channel * cells + pos.index(board)] = 1.0;
+        };
+
+        for (i, snake) in self.sim.snakes().iter().enumerate() {
+            let (head, body) = if i == 0 { (0, 1) } else { (4, 4) };
+            set(head, snake.head_pos());
+            for seg in snake.body().iter() {
+                set(body, *seg);
+            }
+        }
+        set(2, self.sim.fruit_of(0));
+        for wall in self.sim.rules().walls.iter() {
+            set(3, *wall);
+        }
+        grid
+    }
+
+    fn features(&self) -> Vec<f32> {
+        let me = self.sim.player();
+        let map = Map::new(&self.sim);
+        let head = me.head_pos();
+        let dir = me.dir();
+        let deadly = |action: Action| {
+            map.next(head, action.direction(dir))
+                .is_none_or(|pos| map.is_blocked(pos))
+        };
+
+        let board = self.sim.board();
+        let boundary = self.sim.rules().boundary;
+        let fruit = self.sim.fruit_of(0);
+        let dx = offset(head.x, fruit.x, board.width, boundary.wraps_x());
+        let dy = offset(head.y, fruit.y, board.height, boundary.wraps_y());
+
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        vec![
+            flag(deadly(Action::Straight)),
+            flag(deadly(Action::Left)),
+            flag(deadly(Action::Right)),
+            flag(dir == Direction::Up),
+            flag(dir == Direction::Down),
+            flag(dir == Direction::Left),
+            flag(dir == Direction::Right),
+            flag(dy < 0),
+            flag(dy > 0),
+            flag(dx < 0),
+            flag(dx > 0),
+            me.length() as f32 / board.cells() as f32,
+        ]
+    }
+}
+
+/// The shortest way from `from` to `to` along an axis of `size` cells
+fn offset(from: u32, to: u32, size: u32, wraps: bool) -> i64 {
+    let d = i64::from(to) - i64::from(from);
+    let size = i64::from(size);
+    if wraps && d.abs() * 2 > size {
+        d - d.signum() * size
+    } else {
+        d
+    }
+}
@@ -0,0 +1,99 @@
+//! Playing with a gamepad, through the gamepad events of ggez.
+//!
+//! The buttons have fixed actions, as in most console games: the D-pad and
+//! the left stick turn, Start pauses and Select quits. In the questions South
+//! (A on an Xbox pad) answers yes and East (B) answers no; West (X) saves and
+//! quits, North (Y) continues the saved game.
+//!
+//! A stick moves continuously, so it turns the snake only when it's pushed
+//! out of the dead zone, or from one direction to another.
+
+use crate::controls::Action;
+use crate::controls::Press;
+use crate::simulation::Direction;
+use ggez::input::gamepad::gilrs::Axis;
+use ggez::input::gamepad::gilrs::Button;
+
+/// How far a stick must be pushed to turn, from 0 (centered) to 1
+pub const DEAD_ZONE: f32 = 0.5;
+
+/// The action of a button, if it has one
+pub fn button_action(button: Button) -> Option<Action> {
+    match button {
+        Button::DPadUp => Some(Action::TurnUp),
+        Button::DPadDown => Some(Action::TurnDown),
+        Button::DPadLeft => Some(Action::TurnLeft),
+        Button::DPadRight => Some(Action::TurnRight),
+        Button::Start => Some(Action::Pause),
+        Button::Select => Some(Action::Quit),
+        Button::South => Some(Action::Confirm),
+        Button::East => Some(Action::Cancel),
+        Button::West => Some(Action::SaveAndQuit),
+        Button::North => Some(Action::Continue),
+        _ => None,
+    }
+}
+
+/// The state of a gamepad, which turns its events into presses
+#[derive(Clone, Debug, Default)]
+pub struct Pad {
+    /// The position of the stick, right and up are positive
+    x: f32,
+    y: f32,
+    /// The direction the stick points to, if out of the dead zone
+    dir: Option<Direction>,
+}
+
+impl Pad {
+    pub fn new() -> Self {
+        Pad::default()
+    }
+
+    /// A button was pressed. Every button counts, as any key does on the start screen.
+    pub fn button_down(&mut self, button: Button) -> Press {
+        Press {
+            key: None,
+            actions: button_action(button).into_iter().collect(),
+        }
+    }
+
+    /// An axis moved: return a turn when the stick is pushed to a new direction.
+    /// The D-pads reported as axes work like the left stick.
+    pub fn axis(&mut self, axis: Axis, value: f32) -> Option<Press> {
+        match axis {
+            Axis::LeftStickX | Axis::DPadX => self.x = value,
+            Axis::LeftStickY | Axis::DPadY => self.y = value,
+            _ => return None,
+        }
+
+        let dir = self.direction();
+        if dir == self.dir {
+            return None;
+        }
+        self.dir = dir;
+        dir.map(|dir| Press {
+            key: None,
+            actions: vec![Action::turning(dir)],
+        })
+    }
+
+    /// The direction the stick points to, along its larger axis,
+    /// or `None` in the dead zone
+    pub fn direction(&self) -> Option<Direction> {
+        if self.x.abs().max(self.y.abs()) < DEAD_ZONE {
+            None
+        } else if self.x.abs() > self.y.abs() {
+            Some(if self.x > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            })
+        } else {
+            Some(if self.y > 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            })
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! The high-score table.
+//!
+//! Scores are only comparable between games played with the same settings,
+//! so there is a table for each combination of board, boundary, speed,
+//! fruit points and level, named by `table_name`.
+//! The tables are saved in a TOML file in the user data directory.
+
+use crate::simulation::Rules;
+use crate::toml_file;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The name of the high-score file in the user data directory
+pub const HIGHSCORES_FILE: &str = "highscores.toml";
+
+/// The number of entries kept in each table
+pub const MAX_ENTRIES: usize = 10;
+
+/// The longest name of a player
+pub const MAX_NAME_LEN: usize = 12;
+
+/// Everything that can go wrong loading the high scores
+#[derive(Debug)]
+pub enum HighScoresError {
+    Io(PathBuf, io::Error),
+    /// The file is corrupted
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for HighScoresError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HighScoresError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            HighScoresError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for HighScoresError {}
+
+/// A line of a high-score table
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    /// The seed of the game, to play it again
+    #[serde(with = "crate::u64_string")]
+    pub seed: u64,
+    /// The autopilot drove the snake for a while
+    #[serde(default)]
+    pub assisted: bool,
+}
+
+/// All the high-score tables
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    /// The name entered last time, proposed for the next entry
+    pub last_name: String,
+    /// The tables, by `table_name`, best score first
+    pub tables: BTreeMap<String, Vec<Entry>>,
+}
+
+/// The name of the table for games played with these settings
+pub fn table_name(rules: &Rules, move_time: Duration, level: Option<&str>) -> String {
+    let mut name = format!(
+        "{}x{} {} {}ms {}pts length {}",
+        rules.board.width,
+        rules.board.height,
+        rules.boundary,
+        move_time.as_millis(),
+        rules.fruit_points,
+        rules.start_length
+    );
+    if let Some(level) = level {
+        name.push_str(" level ");
+        name.push_str(level);
+    }
+    name
+}
+
+impl HighScores {
+    /// Load the high scores from a file, starting with empty tables if it doesn't exist
+    pub fn load(path: &Path) -> Result<HighScores, HighScoresError> {
+        let mut scores: HighScores = match fs::read_to_string(path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|e| HighScoresError::Parse(path.to_path_buf(), e))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HighScores::default(),
+            Err(e) => return Err(HighScoresError::Io(path.to_path_buf(), e)),
+        };
+
+        // Don't trust a file edited by hand
+        for table in scores.tables.values_mut() {
+            table.sort_by_key(|e| std::cmp::Reverse(e.score));
+            table.truncate(MAX_ENTRIES);
+            for entry in table.iter_mut() {
+                entry.name = clean_name(&entry.name);
+            }
+        }
+        scores.last_name = clean_name(&scores.last_name);
+
+        Ok(scores)
+    }
+
+    /// Save the high scores to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        toml_file::save(path, self)
+    }
+
+    /// The entries of a table, best score first
+    pub fn table(&self, name: &str) -> &[Entry] {
+        self.tables.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Check if a score enters a table
+    pub fn qualifies(&self, name: &str, score: u32) -> bool {
+        let table = self.table(name);
+        score > 0 && (table.len() < MAX_ENTRIES || table.iter().any(|e| score > e.score))
+    }
+
+    /// Add an entry to a table, returning its rank (0 for the best),
+    /// or `None` if the score doesn't enter the table.
+    ///
+    /// An entry goes after the older ones with the same score.
+    pub fn insert(&mut self, name: &str, mut entry: Entry) -> Option<usize> {
+        if !self.qualifies(name, entry.score) {
+            return None;
+        }
+        entry.name = clean_name(&entry.name);
+        self.last_name = entry.name.clone();
+
+        let table = self.tables.entry(name.to_string()).or_default();
+        let rank = table.iter().take_while(|e| e.score >= entry.score).count();
+        table.insert(rank, entry);
+        table.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+/// Keep only the printable characters of a name, up to `MAX_NAME_LEN`
+pub fn clean_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LEN)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
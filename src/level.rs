@@ -0,0 +1,224 @@
+//! Levels with walls, loaded from plain text files.
+//!
+//! A level file starts with some optional `key value` lines, followed by
+//! a `map` line and by the map itself, one line per row of the board:
+//!
+//! ```text
+//! name The Box
+//! boundary walls
+//! length 3
+//! goal length 10
+//! map
+//! ##########
+//! #........#
+//! #..>.....#
+//! #......*.#
+//! #.*......#
+//! ##########
+//! ```
+//!
+//! In the map `#` is a wall, `.` an empty cell, `*` a cell where the fruits appear
+//! and `^`, `v`, `<` or `>` the head of the snake, pointing where it moves when
+//! the game starts. When there are no `*` the fruits appear anywhere.
+//!
+//! The keys are `name`, `boundary` (see `Boundary`, `wrap` by default),
+//! `length`, the length of the snake when the game starts (1 by default) and
+//! `goal`, `length N` or `score N`, what completes the level (endless by default).
+//! Lines starting with `;` are comments.
+
+use crate::simulation::Board;
+use crate::simulation::Boundary;
+use crate::simulation::Direction;
+use crate::simulation::GridPosition;
+use crate::simulation::Rules;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The extension of the level files
+pub const LEVEL_EXTENSION: &str = "txt";
+
+/// Everything that can go wrong reading a level
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    /// The file is not a valid level
+    Parse {
+        line: usize,
+        msg: String,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(e) => write!(f, "{}", e),
+            LevelError::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(e: io::Error) -> Self {
+        LevelError::Io(e)
+    }
+}
+
+/// A level: its name and the rules it is played with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    pub name: String,
+    /// The rules of the level, with the default fruit points
+    pub rules: Rules,
+}
+
+impl Level {
+    /// Load a level from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Level, LevelError> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+impl std::str::FromStr for Level {
+    type Err = LevelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, l)| (i + 1, l.trim_end()));
+
+        let mut name = String::new();
+        let mut boundary = Boundary::default();
+        let mut length = 1;
+        let mut goal = None;
+
+        // The keys, up to the map
+        let mut map_found = false;
+        for (n, line) in lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "name" => name = value.to_string(),
+                "boundary" => boundary = value.parse().map_err(|e: String| parse_error(n, &e))?,
+                "length" => {
+                    length = value
+                        .parse()
+                        .map_err(|_| parse_error(n, &format!("invalid length '{}'", value)))?
+                }
+                "goal" => goal = Some(value.parse().map_err(|e: String| parse_error(n, &e))?),
+                "map" => {
+                    map_found = true;
+                    break;
+                }
+                _ => return Err(parse_error(n, &format!("unexpected key '{}'", key))),
+            }
+        }
+        if !map_found {
+            return Err(parse_error(s.lines().count(), "missing 'map'"));
+        }
+
+        // The map, one line per row
+        let rows: Vec<(usize, &str)> = lines.filter(|(_, l)| !l.is_empty()).collect();
+        let (first, width) = match rows.first() {
+            Some((n, row)) => (*n, row.chars().count()),
+            None => return Err(parse_error(s.lines().count(), "the map is empty")),
+        };
+        let board =
+            Board::new(width as u32, rows.len() as u32).map_err(|e| parse_error(first, &e))?;
+
+        let mut walls = Vec::new();
+        let mut fruit_spawns = Vec::new();
+        let mut start = None;
+        for (y, (n, row)) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(parse_error(
+                    *n,
+                    "all the rows of the map must have the same length",
+                ));
+            }
+            for (x, c) in row.chars().enumerate() {
+                let pos = GridPosition::new(x as u32, y as u32);
+                let dir = match c {
+                    '.' => None,
+                    '#' => {
+                        walls.push(pos);
+                        None
+                    }
+                    '*' => {
+                        fruit_spawns.push(pos);
+                        None
+                    }
+                    '^' => Some(Direction::Up),
+                    'v' => Some(Direction::Down),
+                    '<' => Some(Direction::Left),
+                    '>' => Some(Direction::Right),
+                    _ => return Err(parse_error(*n, &format!("unexpected cell '{}'", c))),
+                };
+                if let Some(dir) = dir {
+                    if start.is_some() {
+                        return Err(parse_error(*n, "the map has more than one snake head"));
+                    }
+                    start = Some((pos, dir));
+                }
+            }
+        }
+        let (start_pos, start_dir) =
+            start.ok_or_else(|| parse_error(first, "the map has no snake head"))?;
+
+        let rules = Rules {
+            board,
+            boundary,
+            start_pos,
+            start_dir,
+            start_length: length,
+            walls,
+            fruit_spawns,
+            goal,
+            ..Rules::default()
+        };
+        rules.validate().map_err(|e| parse_error(first, &e))?;
+
+        Ok(Level { name, rules })
+    }
+}
+
+fn parse_error(line: usize, msg: &str) -> LevelError {
+    LevelError::Parse {
+        line,
+        msg: msg.to_string(),
+    }
+}
+
+/// Find a level by path, or by name in the `levels` directory of the resources
+pub fn find_level(name: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let file = Path::new("levels")
+        .join(name)
+        .with_extension(LEVEL_EXTENSION);
+    find_resource(&file).ok_or_else(|| format!("Level '{}' not found", name))
+}
+
+/// Find a file in the resources directory, next to the executable or,
+/// when run with cargo, in the project directory
+pub fn find_resource(file: &Path) -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+
+    exe_dir
+        .into_iter()
+        .chain(manifest_dir)
+        .map(|dir| dir.join("resources").join(file))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,29 @@
+//! A small Snake game written with ggez.
+//!
+//! The game rules live in `simulation` and don't depend on ggez,
+//! so they can be run and tested without opening a window.
+//! Only `controls`, `gamepad` and `pointer`, which turn the input into actions, use ggez types.
+
+pub mod agent;
+pub mod arena;
+pub mod autopilot;
+pub mod bot;
+pub mod broadcast;
+pub mod campaign;
+pub mod controls;
+pub mod env;
+pub mod gamepad;
+pub mod highscores;
+pub mod level;
+pub mod lockstep;
+pub mod net;
+pub mod pointer;
+pub mod replay;
+pub mod savegame;
+pub mod settings;
+pub mod simulation;
+pub mod tui;
+pub mod turns;
+
+mod toml_file;
+mod u64_string;
@@ -0,0 +1,721 @@
+//! Versus matches between two machines, peer to peer.
+//!
+//! Both sides play the whole match, and send each other only the turns of
+//! their own snake, tick by tick: the seeded RNG of the simulation makes the
+//! same turns give the same game on both sides. Waiting for the turns of the
+//! other side would make every tick as slow as the network, so a side plays
+//! on without them, predicting that the other snake goes straight on, and
+//! rolls back to play the ticks again when a turn arrives that it didn't
+//! predict. A side waits only when it gets `MAX_PREDICTION` ticks ahead of
+//! what it knows.
+//!
+//! Each side sends the checksum of its match after the ticks it knows from
+//! both sides; a checksum that differs from its own means the games went out
+//! of sync, which the `Session` reports.
+//!
+//! The messages go over a `Transport`, such as `Udp`, and may be lost or come
+//! late: each message repeats the turns the other side didn't acknowledge.
+//!
+//! A `PeerMatch` is the match as a front end plays it: the turns of the
+//! player, the pace of the ticks, and the link with the other side.
+//!
+//! ```text
+//! > {"type":"hello","protocol":1,"nonce":"8812...","proposal":{"rules":{...},"seed":"42","move_time_ms":150,"rounds_to_win":3}}
+//! > {"type":"turns","start":12,"turns":[null,"Up",null],"ack":13,"checksum":[11,5123...]}
+//! ```
+
+use crate::simulation::Direction;
+use crate::simulation::Event;
+use crate::simulation::Fnv;
+use crate::simulation::GameState;
+use crate::simulation::Rules;
+use crate::simulation::Simulation;
+use crate::turns::TurnQueue;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::Hasher;
+use std::io;
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The version of the messages; a side of another version is turned away
+pub const PROTOCOL: u32 = 1;
+
+/// How many ticks a side plays ahead of the turns it knows from the other
+pub const MAX_PREDICTION: u64 = 8;
+
+/// The ticks between the end of a round and the start of the next one
+pub const ROUND_PAUSE: u64 = 20;
+
+/// How long `connect` waits for the other side, by default
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The other side of a match is lost after this long without a word
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often `connect` says hello
+const HELLO_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The most turns repeated in one message
+const MAX_TURNS_PER_MESSAGE: usize = 64;
+
+/// The checksums kept to compare with the ones of the other side
+const CHECKSUM_HISTORY: usize = 256;
+
+/// The largest UDP datagram
+const MAX_DATAGRAM: usize = 65_507;
+
+/// What a side proposes to play. The side with the larger nonce chooses.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    /// The rules of the rounds, with a start for each of the two snakes
+    pub rules: Rules,
+    /// The seed of the first round; each round adds one
+    #[serde(with = "crate::u64_string")]
+    pub seed: u64,
+    /// The time between two ticks
+    pub move_time_ms: u64,
+    /// The rounds a player must win to win the match
+    pub rounds_to_win: u32,
+}
+
+impl Proposal {
+    /// Check that the match can be played: the proposal of the other side
+    /// comes from the network
+    pub fn validate(&self) -> Result<(), String> {
+        self.rules.validate()?;
+        let snakes = self.rules.starts().len();
+        if snakes != 2 {
+            return Err(format!("a match is for 2 snakes, not {}", snakes));
+        }
+        Ok(())
+    }
+}
+
+/// A message from one side to the other
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum PeerMessage {
+    /// Sent until the other side answers. The side with the larger nonce
+    /// plays the first snake, and its proposal is played.
+    Hello {
+        protocol: u32,
+        #[serde(with = "crate::u64_string")]
+        nonce: u64,
+        proposal: Proposal,
+    },
+    /// The turns of the sender's snake
+    Turns {
+        /// The tick of the first turn
+        start: u64,
+        /// A turn for each tick from `start` on, `None` to go straight on
+        turns: Vec<Option<Direction>>,
+        /// The sender knows the turns of the receiver before this tick
+        ack: u64,
+        /// The tick and the checksum of the sender's match, after the last
+        /// tick it knows from both sides
+        checksum: Option<(u64, u64)>,
+    },
+}
+
+/// Everything that can go wrong when meeting the other side
+#[derive(Debug)]
+pub enum LockstepError {
+    Io(io::Error),
+    /// The other side speaks another version
+    Protocol(u32),
+    /// Both sides drew the same nonce, so neither can choose
+    SameNonce,
+    /// The other side didn't answer in time
+    Timeout(Duration),
+    /// The match chosen can't be played
+    Invalid(String),
+    /// The match of the other side differs from ours since this tick
+    Desync(u64),
+}
+
+impl fmt::Display for LockstepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockstepError::Io(e) => write!(f, "{}", e),
+            LockstepError::Protocol(protocol) => write!(
+                f,
+                "the other side speaks version {}, not {}",
+                protocol, PROTOCOL
+            ),
+            LockstepError::SameNonce => write!(f, "both sides drew the same nonce, try again"),
+            LockstepError::Timeout(timeout) => {
+                write!(f, "no answer within {}s", timeout.as_secs())
+            }
+            LockstepError::Invalid(e) => write!(f, "invalid match: {}", e),
+            LockstepError::Desync(tick) => {
+                write!(f, "the games went out of sync at tick {}", tick)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LockstepError {}
+
+impl From<io::Error> for LockstepError {
+    fn from(e: io::Error) -> Self {
+        LockstepError::Io(e)
+    }
+}
+
+/// A match of rounds decided by the turns alone: the next round starts
+/// `ROUND_PAUSE` ticks after the end of the last one, with the next seed,
+/// until a player has won enough rounds.
+#[derive(Clone)]
+pub struct Rounds {
+    sim: Simulation,
+    seed: u64,
+    rounds_to_win: u32,
+    /// The rounds played before the current one
+    round: u64,
+    /// The rounds won by each player
+    wins: Vec<u32>,
+    /// The winner of the last round, `None` for a draw
+    last_winner: Option<usize>,
+    /// The ticks left before the next round
+    pause: u64,
+    /// The ticks of the whole match
+    tick: u64,
+}
+
+impl Rounds {
+    /// The match of a proposal, in its first round
+    pub fn new(proposal: &Proposal) -> Rounds {
+        let mut sim = Simulation::new(proposal.rules.clone(), proposal.seed);
+        sim.restart(proposal.seed);
+        let wins = vec![0; sim.snakes().len()];
+        Rounds {
+            sim,
+            seed: proposal.seed,
+            rounds_to_win: proposal.rounds_to_win,
+            round: 0,
+            wins,
+            last_winner: None,
+            pause: 0,
+            tick: 0,
+        }
+    }
+
+    /// The current round
+    pub fn sim(&self) -> &Simulation {
+        &self.sim
+    }
+
+    pub fn wins(&self) -> &[u32] {
+        &self.wins
+    }
+
+    pub fn last_winner(&self) -> Option<usize> {
+        self.last_winner
+    }
+
+    /// The winner of the match, once a player won enough rounds
+    pub fn winner(&self) -> Option<usize> {
+        self.wins.iter().position(|w| *w >= self.rounds_to_win)
+    }
+
+    /// The ticks played since the match started, pauses included
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Play a tick with a turn for each snake
+    pub fn step(&mut self, turns: &[Option<Direction>]) {
+        self.tick += 1;
+        if self.sim.state() == GameState::Running {
+            for event in self.sim.step_all(turns) {
+                if let Event::RoundOver { winner } = event {
+                    if let Some(winner) = winner {
+                        self.wins[winner] += 1;
+                    }
+                    self.last_winner = winner;
+                    self.pause = ROUND_PAUSE;
+                }
+            }
+        } else if self.winner().is_none() {
+            self.pause = self.pause.saturating_sub(1);
+            if self.pause == 0 {
+                self.round += 1;
+                self.sim.restart(self.seed.wrapping_add(self.round));
+            }
+        }
+    }
+
+    /// A checksum of the whole match
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Fnv::default();
+        for n in [self.sim.checksum(), self.round, self.pause, self.tick] {
+            hasher.write(&n.to_le_bytes());
+        }
+        for wins in self.wins.iter() {
+            hasher.write(&wins.to_le_bytes());
+        }
+        hasher.finish()
+    }
+}
+
+/// The start of a session: says hello until the other side does
+pub struct Handshake {
+    hello: PeerMessage,
+}
+
+impl Handshake {
+    /// Propose a match, with a random nonce to decide who chooses
+    pub fn new(proposal: Proposal, nonce: u64) -> Handshake {
+        Handshake {
+            hello: PeerMessage::Hello {
+                protocol: PROTOCOL,
+                nonce,
+                proposal,
+            },
+        }
+    }
+
+    /// The message to send until the other side answers
+    pub fn hello(&self) -> &PeerMessage {
+        &self.hello
+    }
+
+    /// Start the session when the message is the hello of the other side
+    pub fn receive(&self, message: &PeerMessage) -> Result<Option<Session>, LockstepError> {
+        let PeerMessage::Hello {
+            protocol,
+            nonce,
+            proposal,
+        } = message
+        else {
+            return Ok(None);
+        };
+        let PeerMessage::Hello {
+            nonce: our_nonce,
+            proposal: our_proposal,
+            ..
+        } = &self.hello
+        else {
+            unreachable!("the handshake says hello");
+        };
+        if *protocol != PROTOCOL {
+            return Err(LockstepError::Protocol(*protocol));
+        }
+        let (local, proposal) = match nonce.cmp(our_nonce) {
+            std::cmp::Ordering::Less => (0, our_proposal),
+            std::cmp::Ordering::Greater => (1, proposal),
+            std::cmp::Ordering::Equal => return Err(LockstepError::SameNonce),
+        };
+        proposal.validate().map_err(LockstepError::Invalid)?;
+        Ok(Some(Session::new(
+            local,
+            proposal.clone(),
+            self.hello.clone(),
+        )))
+    }
+}
+
+/// One side of a match played in lockstep, with rollback
+pub struct Session {
+    /// The snake of this side
+    local: usize,
+    proposal: Proposal,
+    /// Our hello, sent again when the other side says hello: it didn't hear ours
+    hello: PeerMessage,
+    reply_hello: bool,
+    /// The match after the last tick known from both sides
+    confirmed: Rounds,
+    /// The match shown: the confirmed one, played on with the predicted turns
+    predicted: Rounds,
+    /// The turns of each snake, by tick
+    turns: [Vec<Option<Direction>>; 2],
+    /// The other side knows our turns before this tick
+    acked: u64,
+    /// Our checksums of the last confirmed ticks
+    checksums: BTreeMap<u64, u64>,
+    /// The checksums of the other side for ticks not confirmed here yet
+    pending: BTreeMap<u64, u64>,
+    /// The first tick where the checksums differ, if any
+    desync: Option<u64>,
+    /// How many times the predicted match was played again
+    rollbacks: u64,
+}
+
+impl Session {
+    fn new(local: usize, proposal: Proposal, hello: PeerMessage) -> Session {
+        let rounds = Rounds::new(&proposal);
+        Session {
+            local,
+            proposal,
+            hello,
+            reply_hello: false,
+            confirmed: rounds.clone(),
+            predicted: rounds,
+            turns: [Vec::new(), Vec::new()],
+            acked: 0,
+            checksums: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            desync: None,
+            rollbacks: 0,
+        }
+    }
+
+    /// The snake of this side
+    pub fn local(&self) -> usize {
+        self.local
+    }
+
+    fn remote(&self) -> usize {
+        1 - self.local
+    }
+
+    /// The match both sides agreed on
+    pub fn proposal(&self) -> &Proposal {
+        &self.proposal
+    }
+
+    /// The match to show, predicted where the turns of the other side are not known yet
+    pub fn game(&self) -> &Rounds {
+        &self.predicted
+    }
+
+    /// The match as far as both sides know it
+    pub fn confirmed(&self) -> &Rounds {
+        &self.confirmed
+    }
+
+    /// The first tick where the match differs from the other side's, if any
+    pub fn desync(&self) -> Option<u64> {
+        self.desync
+    }
+
+    /// How many times a turn of the other side came that wasn't predicted
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks
+    }
+
+    /// Whether the next tick can be played without waiting for the other side
+    pub fn can_advance(&self) -> bool {
+        self.predicted.tick() < self.confirmed.tick() + MAX_PREDICTION
+    }
+
+    /// Play the next tick with the turn of our snake. Return `false` without
+    /// playing it when too far ahead of the other side.
+    pub fn advance(&mut self, turn: Option<Direction>) -> bool {
+        if !self.can_advance() {
+            return false;
+        }
+        self.turns[self.local].push(turn);
+        let turns = self.turns_at(self.predicted.tick());
+        self.predicted.step(&turns);
+        self.confirm();
+        true
+    }
+
+    /// Take a message of the other side
+    pub fn receive(&mut self, message: PeerMessage) {
+        let PeerMessage::Turns {
+            start,
+            turns,
+            ack,
+            checksum,
+        } = message
+        else {
+            self.reply_hello = true;
+            return;
+        };
+
+        // Only the turns right after the known ones count: the others come again
+        let remote = self.remote();
+        let known = self.turns[remote].len() as u64;
+        let mut mispredicted = false;
+        if start <= known {
+            for (tick, turn) in (start..).zip(turns) {
+                if tick < known {
+                    continue;
+                }
+                mispredicted |= tick < self.predicted.tick() && turn.is_some();
+                self.turns[remote].push(turn);
+            }
+        }
+        let sent = self.turns[self.local].len() as u64;
+        self.acked = self.acked.max(ack).min(sent);
+
+        if let Some((tick, theirs)) = checksum {
+            match self.checksums.get(&tick) {
+                Some(ours) if *ours != theirs => {
+                    self.desync.get_or_insert(tick);
+                }
+                None if tick > self.confirmed.tick() => {
+                    self.pending.insert(tick, theirs);
+                }
+                _ => {}
+            }
+        }
+
+        let target = self.predicted.tick();
+        self.confirm();
+
+        // Play the ticks again from the last one known from both sides
+        if mispredicted {
+            self.predicted = self.confirmed.clone();
+            while self.predicted.tick() < target {
+                let turns = self.turns_at(self.predicted.tick());
+                self.predicted.step(&turns);
+            }
+            self.rollbacks += 1;
+        }
+    }
+
+    /// The messages to send to the other side, as often as possible
+    pub fn outgoing(&mut self) -> Vec<PeerMessage> {
+        let mut messages = Vec::new();
+        if std::mem::take(&mut self.reply_hello) {
+            messages.push(self.hello.clone());
+        }
+        let local = &self.turns[self.local];
+        let start = self.acked as usize;
+        let end = local.len().min(start + MAX_TURNS_PER_MESSAGE);
+        messages.push(PeerMessage::Turns {
+            start: self.acked,
+            turns: local[start..end].to_vec(),
+            ack: self.turns[self.remote()].len() as u64,
+            checksum: self.checksums.last_key_value().map(|(t, c)| (*t, *c)),
+        });
+        messages
+    }
+
+    /// The turns of the snakes at a tick, going straight on where unknown
+    fn turns_at(&self, tick: u64) -> Vec<Option<Direction>> {
+        self.turns
+            .iter()
+            .map(|turns| turns.get(tick as usize).copied().flatten())
+            .collect()
+    }
+
+    /// Play the confirmed match up to the last tick known from both sides
+    fn confirm(&mut self) {
+        let known = self.turns.iter().map(Vec::len).min().unwrap_or(0) as u64;
+        while self.confirmed.tick() < known {
+            let turns = self.turns_at(self.confirmed.tick());
+            self.confirmed.step(&turns);
+
+            let tick = self.confirmed.tick();
+            let ours = self.confirmed.checksum();
+            self.checksums.insert(tick, ours);
+            if self
+                .pending
+                .remove(&tick)
+                .is_some_and(|theirs| theirs != ours)
+            {
+                self.desync.get_or_insert(tick);
+            }
+        }
+        while self.checksums.len() > CHECKSUM_HISTORY {
+            self.checksums.pop_first();
+        }
+    }
+}
+
+/// A match against another machine as a front end plays it: the player's
+/// turns are played at the pace of the match, and the messages go to the
+/// other side and back over the transport
+pub struct PeerMatch<T: Transport = Udp> {
+    session: Session,
+    transport: T,
+    /// The turns of our snake, waiting for the next ticks
+    turns: TurnQueue,
+    /// The time since the last tick
+    timer: Duration,
+    /// When the other side was last heard from
+    last_heard: Instant,
+}
+
+impl<T: Transport> PeerMatch<T> {
+    /// Play the match of a session, with the other side at the end of the transport
+    pub fn new(session: Session, transport: T) -> PeerMatch<T> {
+        PeerMatch {
+            session,
+            transport,
+            turns: TurnQueue::new(),
+            timer: Duration::from_millis(0),
+            last_heard: Instant::now(),
+        }
+    }
+
+    /// The session of the match
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// The snake of this side
+    pub fn local(&self) -> usize {
+        self.session.local()
+    }
+
+    /// The match to show, as this side predicts it
+    pub fn game(&self) -> &Rounds {
+        self.session.game()
+    }
+
+    /// The time between two ticks, as both sides agreed
+    pub fn move_time(&self) -> Duration {
+        Duration::from_millis(self.session.proposal().move_time_ms)
+    }
+
+    /// Queue a turn of our snake, during a round. Return `false` if it's ignored.
+    pub fn input(&mut self, dir: Direction) -> bool {
+        let sim = self.session.game().sim();
+        if sim.state() != GameState::Running {
+            return false;
+        }
+        self.turns.push_for(&sim.snakes()[self.local()], dir)
+    }
+
+    /// Take the turns of the other side, and play our snake's at the pace of
+    /// the match, `elapsed` after the last call. The other side is lost when
+    /// the transport fails, when it's not heard from for `PEER_TIMEOUT`, or
+    /// when its match differs from ours.
+    pub fn tick(&mut self, elapsed: Duration) -> Result<(), LockstepError> {
+        let messages = self.transport.receive()?;
+        if !messages.is_empty() {
+            self.last_heard = Instant::now();
+        }
+        for message in messages {
+            self.session.receive(message);
+        }
+        if let Some(tick) = self.session.desync() {
+            return Err(LockstepError::Desync(tick));
+        }
+        if self.last_heard.elapsed() >= PEER_TIMEOUT {
+            return Err(LockstepError::Timeout(PEER_TIMEOUT));
+        }
+
+        // Play on until the match is won, unless too far ahead of the other side
+        self.timer += elapsed;
+        if self.timer >= self.move_time()
+            && self.session.game().winner().is_none()
+            && self.session.can_advance()
+        {
+            let turn = self.turns.pop();
+            self.session.advance(turn);
+            self.timer = Duration::from_millis(0);
+        }
+
+        for message in self.session.outgoing() {
+            self.transport.send(&message)?;
+        }
+        if self.session.game().sim().state() != GameState::Running {
+            self.turns.clear();
+        }
+        Ok(())
+    }
+}
+
+/// How the messages go from one side to the other. They may be lost, or come
+/// late or out of order: the session makes up for it.
+pub trait Transport {
+    fn send(&mut self, message: &PeerMessage) -> io::Result<()>;
+
+    /// The messages that came since the last call, without waiting
+    fn receive(&mut self) -> io::Result<Vec<PeerMessage>>;
+}
+
+/// Messages in UDP datagrams, one JSON message each
+pub struct Udp {
+    socket: UdpSocket,
+    peer: SocketAddr,
+}
+
+impl Udp {
+    /// Listen on a local address, such as `0.0.0.0:7357`, and talk to the
+    /// other side at its address
+    pub fn bind(local: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<Udp> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        let ipv4 = socket.local_addr()?.is_ipv4();
+        let peer = peer
+            .to_socket_addrs()?
+            .find(|addr| addr.is_ipv4() == ipv4)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to talk to"))?;
+        Ok(Udp { socket, peer })
+    }
+
+    /// The address the other side talks to, with the port chosen by the
+    /// system if bound to port 0
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl Transport for Udp {
+    fn send(&mut self, message: &PeerMessage) -> io::Result<()> {
+        let datagram = serde_json::to_vec(message).map_err(io::Error::other)?;
+        match self.socket.send_to(&datagram, self.peer) {
+            Ok(_) => Ok(()),
+            // Nobody listens on the other side yet, or the socket is full: like a lost message
+            Err(e)
+                if e.kind() == io::ErrorKind::ConnectionRefused
+                    || e.kind() == io::ErrorKind::WouldBlock =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<PeerMessage>> {
+        let mut messages = Vec::new();
+        let mut buffer = vec![0; MAX_DATAGRAM];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, from)) => {
+                    // Anything else than a message of the other side is noise
+                    if from != self.peer {
+                        continue;
+                    }
+                    if let Ok(message) = serde_json::from_slice(&buffer[..size]) {
+                        messages.push(message);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // A message sent before the other side listened came back
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(messages)
+    }
+}
+
+/// Say hello to the other side until it answers, for at most `timeout`
+pub fn connect(
+    transport: &mut dyn Transport,
+    handshake: &Handshake,
+    timeout: Duration,
+) -> Result<Session, LockstepError> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        transport.send(handshake.hello())?;
+        let next_hello = Instant::now() + HELLO_INTERVAL;
+        while Instant::now() < next_hello {
+            for message in transport.receive()? {
+                if let Some(mut session) = handshake.receive(&message)? {
+                    // The other side may not have heard us yet
+                    session.receive(message);
+                    return Ok(session);
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+    Err(LockstepError::Timeout(timeout))
+}
@@ -1,3 +1,5 @@
+mod modes;
+
 use ggez::conf::WindowMode;
 use ggez::conf::WindowSetup;
 use ggez::event;
@@ -9,156 +11,590 @@ use ggez::graphics::DrawParam;
 use ggez::graphics::Image;
 use ggez::graphics::Mesh;
 use ggez::graphics::PxScale;
+use ggez::graphics::Quad;
+use ggez::graphics::Rect;
 use ggez::graphics::Text;
+use ggez::input::gamepad::gilrs::Axis;
+use ggez::input::gamepad::gilrs::Button;
+use ggez::input::gamepad::GamepadId;
 use ggez::input::keyboard::KeyCode;
 use ggez::input::keyboard::KeyInput;
+use ggez::input::mouse::MouseButton;
 use ggez::mint::Point2;
 use ggez::Context;
 use ggez::ContextBuilder;
 use ggez::GameResult;
-use oorandom::Rand32;
-use std::collections::LinkedList;
+use modes::CampaignRun;
+use modes::Match;
+use modes::Mode;
+use modes::Play;
+use modes::Playback;
+use modes::ROUNDS_TO_WIN;
+use snake_ggez::agent::AgentPilot;
+use snake_ggez::agent::Subprocess;
+use snake_ggez::agent::DEFAULT_TIMEOUT;
+use snake_ggez::autopilot::Autopilot;
+use snake_ggez::bot::bot_starts;
+use snake_ggez::bot::Bot;
+use snake_ggez::broadcast::Broadcaster;
+use snake_ggez::broadcast::Spectator;
+use snake_ggez::broadcast::BROADCAST_PORT;
+use snake_ggez::controls::key_name;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Conflict;
+use snake_ggez::controls::Controls;
+use snake_ggez::controls::Preset;
+use snake_ggez::controls::Press;
+use snake_ggez::controls::CONTROLS_FILE;
+use snake_ggez::gamepad::Pad;
+use snake_ggez::highscores::table_name;
+use snake_ggez::highscores::Entry;
+use snake_ggez::highscores::HighScores;
+use snake_ggez::highscores::HIGHSCORES_FILE;
+use snake_ggez::highscores::MAX_NAME_LEN;
+use snake_ggez::level::find_level;
+use snake_ggez::level::Level;
+use snake_ggez::lockstep::connect;
+use snake_ggez::lockstep::Handshake;
+use snake_ggez::lockstep::PeerMatch;
+use snake_ggez::lockstep::Proposal;
+use snake_ggez::lockstep::Udp;
+use snake_ggez::lockstep::DEFAULT_HANDSHAKE_TIMEOUT;
+use snake_ggez::net::with_default_port;
+use snake_ggez::net::with_port;
+use snake_ggez::net::Client;
+use snake_ggez::net::LanGame;
+use snake_ggez::net::Server;
+use snake_ggez::net::CONNECT_TIMEOUT;
+use snake_ggez::net::DEFAULT_PORT;
+use snake_ggez::net::MAX_PLAYERS;
+use snake_ggez::pointer::Drag;
+use snake_ggez::replay::Replay;
+use snake_ggez::savegame::SaveGame;
+use snake_ggez::savegame::SAVEGAME_FILE;
+use snake_ggez::settings::parse_color;
+use snake_ggez::settings::Overrides;
+use snake_ggez::settings::Settings;
+use snake_ggez::settings::SETTINGS_FILE;
+use snake_ggez::simulation::random_seed;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Event;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Goal;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::simulation::Snake;
+use snake_ggez::simulation::VICTORY_BONUS_FRUITS;
+use snake_ggez::turns::TurnQueue;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
 use std::time::Duration;
 
-const GRID_WIDTH: u32 = 25;
-const GRID_HEIGHT: u32 = 20;
-const CELL_SIZE: u32 = 32;
-const WINDOW_WIDTH: f32 = (GRID_WIDTH * CELL_SIZE) as f32;
-const WINDOW_HEIGHT: f32 = (GRID_HEIGHT * CELL_SIZE) as f32;
-const FRUIT_POINTS: u32 = 10;
+/// The size of a cell when the board fits in the window
+const DEFAULT_CELL_SIZE: u32 = 32;
+/// The window is never smaller than this, to leave room for the texts
+const MIN_WINDOW_WIDTH: f32 = 480.0;
+const MIN_WINDOW_HEIGHT: f32 = 360.0;
+/// Large boards get smaller cells to fit in this size
+const MAX_WINDOW_WIDTH: u32 = 1280;
+const MAX_WINDOW_HEIGHT: u32 = 960;
+/// The distance between the texts of the HUD and the window borders
+const HUD_MARGIN_X: f32 = 16.0;
+const HUD_MARGIN_Y: f32 = 10.0;
 
-const COLOR_BACKGROUND: Color = Color::new(0.075, 0.098, 0.149, 1.0);
 const COLOR_GRAY: Color = Color::new(0.2, 0.2, 0.2, 1.0);
+const COLOR_WALL: Color = Color::new(0.7, 0.7, 0.7, 1.0);
+
+/// The width of the lines drawn for the walls at the edges of the board
+const WALL_WIDTH: f32 = 4.0;
+
+/// The size of the buttons shown with `[mouse] buttons`, in pixels
+const BUTTON_WIDTH: f32 = 120.0;
+const BUTTON_HEIGHT: f32 = 36.0;
+const BUTTON_GAP: f32 = 12.0;
+
+const COLOR_BUTTON: Color = Color::new(0.25, 0.3, 0.4, 0.9);
+const COLOR_BUTTON_HOVER: Color = Color::new(0.35, 0.45, 0.6, 0.9);
+
+/// The tint of each snake, the player's untinted
+const SNAKE_COLORS: [Color; 4] = [
+    Color::WHITE,
+    Color::new(0.5, 0.7, 1.0, 1.0),
+    Color::new(1.0, 0.6, 0.3, 1.0),
+    Color::new(1.0, 0.5, 0.8, 1.0),
+];
+
+/// The longest game played without a window, as a snake may go round forever
+const HEADLESS_MAX_TICKS: u64 = 100_000;
+
+/// Where the board is drawn in the window
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    /// The size of a cell, in pixels
+    cell_size: f32,
+    /// The top-left corner of the board
+    origin: Vec2,
+    /// The size of the board, in pixels
+    board_width: f32,
+    board_height: f32,
+    /// The size of the window, in pixels
+    width: f32,
+    height: f32,
+}
 
-const MOVE_TIME: Duration = Duration::from_millis(300);
+impl Layout {
+    /// Compute the layout of the board, centered in a window large enough for the texts
+    fn new(board: Board, cell_size: u32) -> Layout {
+        let board_width = (board.width * cell_size) as f32;
+        let board_height = (board.height * cell_size) as f32;
+        // Leave room for the walls around the board
+        let width = (board_width + 2.0 * WALL_WIDTH).max(MIN_WINDOW_WIDTH);
+        let height = (board_height + 2.0 * WALL_WIDTH).max(MIN_WINDOW_HEIGHT);
+        Layout::centered(board, cell_size as f32, width, height)
+    }
 
-/// An enum with all the possible game states
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum GameState {
-    Starting,
-    Running,
-    Paused,
-    GameOver,
-    Quitting,
-}
+    /// Whether another board fits in the window, with cells of the same size
+    fn fits(&self, board: Board) -> bool {
+        board.width as f32 * self.cell_size + 2.0 * WALL_WIDTH <= self.width
+            && board.height as f32 * self.cell_size + 2.0 * WALL_WIDTH <= self.height
+    }
 
-/// An enum that will represent all the possible
-/// directions that our snake could move.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
+    /// The layout of another board, no larger than this one, in the same window
+    fn resized(&self, board: Board) -> Layout {
+        Layout::centered(board, self.cell_size, self.width, self.height)
+    }
 
-impl Direction {
-    /// An helper function that will allow us to easily check the opposite
-    /// of a `Direction` which we can use later to check if the player should be
-    /// able to move the snake in a certain direction.
-    pub fn opposite(&self, op: Direction) -> bool {
-        match *self {
-            Direction::Up => op == Direction::Down,
-            Direction::Down => op == Direction::Up,
-            Direction::Left => op == Direction::Right,
-            Direction::Right => op == Direction::Left,
+    fn centered(board: Board, cell_size: f32, width: f32, height: f32) -> Layout {
+        let board_width = board.width as f32 * cell_size;
+        let board_height = board.height as f32 * cell_size;
+        Layout {
+            cell_size,
+            origin: Vec2::new((width - board_width) / 2.0, (height - board_height) / 2.0),
+            board_width,
+            board_height,
+            width,
+            height,
         }
     }
+
+    /// Convert a position to a Vec2 used by ggez.
+    /// Note that a position is in grid coordinates, the Vec2 is in pixels
+    fn cell(&self, pos: GridPosition) -> Vec2 {
+        self.origin + Vec2::new(pos.x as f32, pos.y as f32) * self.cell_size
+    }
 }
 
-/// A struct that will hold an entity's position on our game board
-/// or grid which we defined above.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct GridPosition {
-    x: u32,
-    y: u32,
+/// The largest cell size (up to `DEFAULT_CELL_SIZE`) that fits the board on the screen
+fn default_cell_size(board: Board) -> u32 {
+    DEFAULT_CELL_SIZE
+        .min(MAX_WINDOW_WIDTH / board.width)
+        .min(MAX_WINDOW_HEIGHT / board.height)
+        .max(1)
 }
 
-impl GridPosition {
-    /// We make a standard helper function so that we can create a new `GridPosition`
-    /// more easily.
-    pub fn new(x: u32, y: u32) -> Self {
-        GridPosition { x, y }
-    }
+/// Everything decided before opening the window
+struct Setup {
+    settings: Settings,
+    /// The keys, from the controls screen or else from the settings
+    controls: Controls,
+    /// Where the controls screen saves the keys, if there is a config directory
+    controls_path: Option<PathBuf>,
+    /// The rules of the game, from the settings, the level or the replay
+    rules: Rules,
+    layout: Layout,
+    /// The replay to watch, if any
+    replay: Option<Replay>,
+    /// The campaign to play, if any
+    campaign: Option<CampaignRun>,
+    /// The name of the level played, outside of the campaign
+    level_id: Option<String>,
+    highscores: HighScores,
+    /// Where the high scores are saved, if there is a data directory
+    highscores_path: Option<PathBuf>,
+    /// The game saved last time, if any
+    savegame: Option<SaveGame>,
+    /// Where the game is saved, if there is a data directory
+    savegame_path: Option<PathBuf>,
+    /// The bots driving the other snakes, if any
+    bots: Vec<Bot>,
+    /// The program driving the player's snake, if any
+    agent: Option<AgentPilot<Subprocess>>,
+    /// The game over the network, if any
+    net: Option<LanGame>,
+    /// The match against another machine, if any
+    peer: Option<PeerMatch>,
+    /// Where the game is broadcast to the spectators, if it is
+    broadcaster: Option<Broadcaster>,
+    /// The game watched, if any
+    spectator: Option<Spectator>,
+}
 
-    /*
-    /// An helper function that will give us a random `GridPosition` in the grid
-    pub fn random(rng: &mut Rand32) -> Self {
-        let x = rng.rand_range(0..GRID_WIDTH);
-        let y = rng.rand_range(0..GRID_HEIGHT);
-        GridPosition { x, y }
-    }
-    */
+impl Setup {
+    /// Load the settings and the replay, letting the command line override the settings
+    fn new(args: &Args) -> Result<Setup, String> {
+        let mut settings = match &args.config {
+            Some(path) => Settings::load(path),
+            None => match config_dir().map(|dir| dir.join(SETTINGS_FILE)) {
+                Some(path) => Settings::load_or_default(&path),
+                None => Ok(Settings::default()),
+            },
+        }
+        .map_err(|e| e.to_string())?;
+        settings.apply(&args.overrides).map_err(|e| e.to_string())?;
+
+        let mut controls = Controls::from_settings(&settings.keys).map_err(|e| e.to_string())?;
+        let controls_path = config_dir().map(|dir| dir.join(CONTROLS_FILE));
+        if let Some(path) = &controls_path {
+            match Controls::load(path) {
+                Ok(Some(saved)) => controls = saved,
+                Ok(None) => {}
+                Err(e) => eprintln!("Could not load the controls, using the settings: {}", e),
+            }
+        }
 
-    /// Move the position in the given direction.
-    /// The grid is toroidal
-    pub fn move_to_direction(&mut self, dir: Direction) {
-        match dir {
-            Direction::Down => {
-                self.y += 1;
-                if self.y >= GRID_HEIGHT {
-                    self.y = 0;
-                }
+        let replay = match &args.replay {
+            Some(path) => {
+                Some(Replay::load(path).map_err(|e| format!("{}: {}", path.display(), e))?)
             }
-            Direction::Up => {
-                if self.y == 0 {
-                    self.y = GRID_HEIGHT;
-                }
-                self.y -= 1;
+            None => None,
+        };
+
+        let level_name = args.level.as_ref().or(settings.game.level.as_ref());
+        let level = match level_name {
+            Some(name) => {
+                let path = find_level(name)?;
+                Some(Level::load(&path).map_err(|e| format!("{}: {}", path.display(), e))?)
             }
-            Direction::Right => {
-                self.x += 1;
-                if self.x >= GRID_WIDTH {
-                    self.x = 0;
-                }
+            None => None,
+        };
+        // A level given as a path is known by its file name
+        let level_id = level_name.map(|name| {
+            Path::new(name)
+                .file_stem()
+                .map_or(name.clone(), |stem| stem.to_string_lossy().to_string())
+        });
+
+        let highscores_path = data_dir().map(|dir| dir.join(HIGHSCORES_FILE));
+        let highscores = match &highscores_path {
+            Some(path) => load_or_set_aside(path, "the high scores", HighScores::load),
+            None => HighScores::default(),
+        };
+
+        let savegame_path = data_dir().map(|dir| dir.join(SAVEGAME_FILE));
+        let savegame = match &savegame_path {
+            Some(path)
+                if args.replay.is_none()
+                    && !args.versus
+                    && !args.online()
+                    && args.watch.is_none() =>
+            {
+                load_or_set_aside(path, "the saved game", SaveGame::load)
+            }
+            _ => None,
+        };
+
+        let campaign = if args.campaign {
+            Some(CampaignRun::load(settings.game.fruit_points)?)
+        } else {
+            None
+        };
+
+        let mut rules = match (&replay, level, &campaign) {
+            (Some(replay), _, _) => replay.rules.clone(),
+            (None, _, Some(campaign)) => campaign.rules(),
+            (None, Some(level), None) => Rules {
+                fruit_points: settings.game.fruit_points,
+                ..level.rules
+            },
+            (None, None, None) => settings.rules().map_err(|e| e.to_string())?,
+        };
+
+        // The second player starts on the other side of the board
+        if args.versus {
+            rules.rivals = vec![rules.start().mirrored(rules.board)];
+            rules.fruits = settings.game.fruits.parse()?;
+            rules
+                .validate()
+                .map_err(|e| format!("No room for two players: {}", e))?;
+        }
+
+        // Against another machine, the side that chooses the match may change the rules
+        let peer = match &args.peer {
+            Some(address) => {
+                let address = with_port(address, "127.0.0.1");
+                let local = format!("0.0.0.0:{}", args.port.unwrap_or(DEFAULT_PORT));
+                let mut udp = Udp::bind(&local, &address)
+                    .map_err(|e| format!("Could not play with {}: {}", address, e))?;
+                let proposal = Proposal {
+                    rules: rules.clone(),
+                    seed: args.seed.unwrap_or_else(random_seed),
+                    move_time_ms: settings.move_time().as_millis() as u64,
+                    rounds_to_win: ROUNDS_TO_WIN,
+                };
+                let handshake = Handshake::new(proposal, random_seed());
+                println!("Waiting for {}...", address);
+                let session = connect(&mut udp, &handshake, DEFAULT_HANDSHAKE_TIMEOUT)
+                    .map_err(|e| format!("Could not play with {}: {}", address, e))?;
+                rules = session.proposal().rules.clone();
+                Some(PeerMatch::new(session, udp))
             }
-            Direction::Left => {
-                if self.x == 0 {
-                    self.x = GRID_WIDTH;
+            None => None,
+        };
+
+        // A spectator shows the game of another process, on its board
+        let spectator = match &args.watch {
+            Some(address) => {
+                let address = with_default_port(address, "127.0.0.1", BROADCAST_PORT);
+                let spectator = Spectator::watch(&address, CONNECT_TIMEOUT)
+                    .map_err(|e| format!("Could not watch {}: {}", address, e))?;
+                if let Some(game) = spectator.game() {
+                    rules = game.rules().clone();
                 }
-                self.x -= 1;
+                Some(spectator)
+            }
+            None => None,
+        };
+
+        // The players who join start in the other corners, like the bots
+        let name = args
+            .name
+            .clone()
+            .unwrap_or_else(|| highscores.last_name.clone());
+        let net = match (&args.host, &args.join) {
+            (Some(address), _) => {
+                rules.fruits = settings.game.fruits.parse()?;
+                let mut full = rules.clone();
+                full.rivals = bot_starts(&rules, MAX_PLAYERS - 1);
+                full.validate()
+                    .map_err(|e| format!("No room for {} players: {}", MAX_PLAYERS, e))?;
+                let address = with_port(address, "0.0.0.0");
+                let server = Server::bind(&address, &name)
+                    .map_err(|e| format!("Could not host on {}: {}", address, e))?;
+                Some(LanGame::host(server, rules.clone()))
+            }
+            (None, Some(address)) => {
+                let address = with_port(address, "127.0.0.1");
+                let client = Client::connect(&address, &name)
+                    .map_err(|e| format!("Could not join {}: {}", address, e))?;
+                Some(LanGame::join(client))
             }
+            (None, None) => None,
+        };
+
+        // The bots start in the other corners, in the games alone outside of the campaign
+        let mut bots = Vec::new();
+        let alone = !args.versus
+            && replay.is_none()
+            && campaign.is_none()
+            && net.is_none()
+            && spectator.is_none();
+        if alone && settings.bots.count > 0 {
+            rules.rivals = bot_starts(&rules, settings.bots.count);
+            rules.fruits = settings.game.fruits.parse()?;
+            rules
+                .validate()
+                .map_err(|e| format!("No room for the bots: {}", e))?;
+            let difficulty = settings.difficulty().map_err(|e| e.to_string())?;
+            bots = vec![Bot::new(difficulty); settings.bots.count];
         }
-    }
 
-    /// Convert the position to a Vec2 used by ggez.
-    /// Note that a position is in grid coordinates, the Vec2 is in pixels
-    fn as_vec2(&self) -> Vec2 {
-        Vec2::new((CELL_SIZE * self.x) as f32, (CELL_SIZE * self.y) as f32)
+        let agent = match &args.agent {
+            Some(command) => {
+                let timeout = args.agent_timeout.unwrap_or(DEFAULT_TIMEOUT);
+                let agent = Subprocess::spawn(command, timeout).map_err(|e| e.to_string())?;
+                Some(AgentPilot(agent))
+            }
+            None => None,
+        };
+
+        let broadcaster = match &args.broadcast {
+            Some(address) => {
+                let address = with_default_port(address, "0.0.0.0", BROADCAST_PORT);
+                let broadcaster = Broadcaster::bind(&address)
+                    .map_err(|e| format!("Could not broadcast on {}: {}", address, e))?;
+                Some(broadcaster)
+            }
+            None => None,
+        };
+
+        // The window must fit all the stages of the campaign, and the saved game
+        let mut window_board = campaign.as_ref().map_or(rules.board, |c| c.largest_board());
+        if let Some(save) = &savegame {
+            window_board.width = window_board.width.max(save.game.rules.board.width);
+            window_board.height = window_board.height.max(save.game.rules.board.height);
+        }
+        let cell_size = settings
+            .board
+            .cell_size
+            .unwrap_or_else(|| default_cell_size(window_board));
+        let layout = Layout::new(window_board, cell_size).resized(rules.board);
+
+        Ok(Setup {
+            settings,
+            controls,
+            controls_path,
+            rules,
+            layout,
+            replay,
+            campaign,
+            level_id,
+            highscores,
+            highscores_path,
+            savegame,
+            savegame_path,
+            bots,
+            agent,
+            net,
+            peer,
+            broadcaster,
+            spectator,
+        })
     }
 }
 
-/// A struct that contains all the information needed to describe the state of the game.
-struct MyGame {
-    /// The current head position on the grid
-    head_pos: GridPosition,
-    /// The head velocity (gap between single head movement)
-    head_timer: Duration,
-    /// The current head moving direction
-    dir: Direction,
-    /// The new direction the snake will turn
-    dir_new: Option<Direction>,
+/// Load a file of the game, such as the high scores. A corrupted file is put
+/// aside, not to lose it when the file is saved again, and the game goes on
+/// without it.
+fn load_or_set_aside<T: Default, E: fmt::Display>(
+    path: &Path,
+    what: &str,
+    load: impl FnOnce(&Path) -> Result<T, E>,
+) -> T {
+    load(path).unwrap_or_else(|e| {
+        let backup = path.with_extension("toml.bak");
+        eprintln!(
+            "Could not load {}, moving the file to {}: {}",
+            what,
+            backup.display(),
+            e
+        );
+        if let Err(e) = std::fs::rename(path, &backup) {
+            eprintln!("Could not move {}: {}", path.display(), e);
+        }
+        T::default()
+    })
+}
+
+/// The ggez user directories, if the system has a home for them
+fn project_dirs() -> Option<directories::ProjectDirs> {
+    directories::ProjectDirs::from("", "author", "snake-ggez")
+}
+
+/// The ggez user data directory, for the high scores, the saved game and the campaign
+fn data_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_dir().into())
+}
+
+/// The ggez user config directory, for the settings and the controls
+fn config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().into())
+}
+
+/// Convert a color of the settings, already checked when loading them
+fn settings_color(s: &str) -> Color {
+    let (r, g, b) = parse_color(s).expect("Invalid color");
+    Color::from_rgb(r, g, b)
+}
+
+/// A screen shown over the game, apart from the states of the simulation
+enum Overlay {
+    /// The score enters the high scores: the player types a name
+    NameEntry(String),
+    /// The high scores of the current settings, maybe with an entry highlighted
+    HighScores(Option<usize>),
+    /// The keys bound to each action, to change them
+    Bindings(BindingsScreen),
+}
 
-    /// The current fruit position on the grid
-    fruit_pos: GridPosition,
+/// The state of the controls screen
+struct BindingsScreen {
+    /// The index of the chosen action in `Action::ALL`
+    selected: usize,
+    /// The next key pressed is bound to the chosen action
+    waiting: bool,
+    /// Why the last key could not be bound, if it couldn't
+    conflict: Option<Conflict>,
+}
 
-    /// The list of body segments
-    body: LinkedList<GridPosition>,
+/// A button on the screen, clicked like pressing the keys of its actions
+struct ScreenButton {
+    label: &'static str,
+    rect: Rect,
+    /// The press of a key bound to these actions; none for "any key"
+    press: Press,
+}
 
-    /// Our RNG state
-    rng: Rand32,
+/// A struct that contains all the information needed to draw and drive the game.
+struct MyGame {
+    /// The game rules and state
+    sim: Simulation,
+    /// Where the board is drawn
+    layout: Layout,
+    /// The head velocity (gap between single head movement)
+    head_timer: Duration,
+    /// The time between two movements of the head
+    move_time: Duration,
+    /// The turns each snake will take at the next ticks, the player's first
+    turns: Vec<TurnQueue>,
+
+    /// The seed given on the command line, if any.
+    /// When missing every game gets a new random seed.
+    fixed_seed: Option<u64>,
+
+    /// The inputs of the current game
+    recording: Replay,
+    /// Where to save the recording, if any
+    record_path: Option<PathBuf>,
+    /// Who drives the snakes: the player, a replay, the network...
+    mode: Mode,
+    /// The name of the level played, outside of the campaign
+    level_id: Option<String>,
+    /// Plays the demo of the start screen, and the player's snake with the assist
+    autopilot: Box<dyn Autopilot>,
+    /// The autopilot drives the player's snake
+    assist: bool,
+    /// The autopilot drove the player's snake in this game, which marks its score
+    assisted: bool,
+    /// Where the game is broadcast to the spectators, if it is
+    broadcaster: Option<Broadcaster>,
+
+    /// The high scores of all the settings
+    highscores: HighScores,
+    /// Where to save the high scores, if anywhere
+    highscores_path: Option<PathBuf>,
+    /// The screen shown over the game, if any
+    overlay: Option<Overlay>,
+
+    /// The game saved last time, until it's continued
+    savegame: Option<SaveGame>,
+    /// Where to save the game, if anywhere
+    savegame_path: Option<PathBuf>,
+
+    /// The keys bound to each action
+    controls: Controls,
+    /// Where to save the keys changed on the controls screen, if anywhere
+    controls_path: Option<PathBuf>,
+    /// The state of each gamepad, for its sticks
+    pads: HashMap<GamepadId, Pad>,
+    /// Turn the snake toward the clicked point
+    mouse_steering: bool,
+    /// Show buttons to click on the screens that wait for an answer
+    mouse_buttons: bool,
+    /// The state of the mouse or of the finger steering the snake
+    drag: Drag,
 
-    /// The score
-    score: u32,
     /// Hide / show the grid
     show_grid: bool,
     /// Hide / show the FPS
     show_fps: bool,
-    /// Game state
-    state: GameState,
-
-    /// Counter used by the choose_random_direction function
-    choose_random_direction_counter: u32,
+    /// The background color
+    background_color: Color,
+    /// The color of the grid lines
+    grid_color: Color,
 
     /// Head image
     head_image: Image,
@@ -169,99 +605,434 @@ struct MyGame {
 }
 
 impl MyGame {
-    /// Create a new game
-    pub fn new(ctx: &mut Context) -> GameResult<MyGame> {
+    /// Create a new game, or watch the replay of the setup if given
+    pub fn new(ctx: &mut Context, args: &Args, setup: Setup) -> GameResult<MyGame> {
         // Load images from filesystem
         let head_image = Image::from_path(ctx, "/snakehead.png")?;
         let body_image = Image::from_path(ctx, "/snakebody.png")?;
         let fruit_image = Image::from_path(ctx, "/apple.png")?;
 
-        // We seed our RNG with the system RNG.
-        let mut seed: [u8; 8] = [0; 8];
-        getrandom::getrandom(&mut seed[..]).expect("Could not create RNG seed");
-        let rng = Rand32::new(u64::from_ne_bytes(seed));
-
-        let head_pos = GridPosition::new(4, 4);
-        let fruit_pos = GridPosition::new(4, 4);
-        let mut body = LinkedList::new();
-        body.push_back(GridPosition::new(3, 4));
-        body.push_back(GridPosition::new(2, 4));
-        body.push_back(GridPosition::new(1, 4));
-        body.push_back(GridPosition::new(0, 4));
+        let Setup {
+            settings,
+            controls,
+            controls_path,
+            rules,
+            layout,
+            replay,
+            campaign,
+            level_id,
+            highscores,
+            highscores_path,
+            savegame,
+            savegame_path,
+            bots,
+            agent,
+            net,
+            peer,
+            broadcaster,
+            spectator,
+        } = setup;
+        let fixed_seed = args.seed;
+        let seed = fixed_seed.unwrap_or_else(random_seed);
+
+        // Someone else drives the snakes, or else the players on this keyboard
+        let mode = match (spectator, peer, replay, net) {
+            (Some(spectator), ..) => Mode::Watch(spectator),
+            (None, Some(peer), ..) => Mode::Peer(Box::new(peer)),
+            (None, None, Some(replay), _) => Mode::Replay(Playback::new(replay)),
+            (None, None, None, Some(net)) => Mode::Lan(net),
+            (None, None, None, None) if args.versus => Mode::Versus(Match::new()),
+            (None, None, None, None) => Mode::Play(Play {
+                campaign,
+                bots,
+                agent,
+            }),
+        };
 
         let mut g = MyGame {
+            sim: Simulation::new(rules.clone(), seed),
+            layout,
+            controls,
+            controls_path,
+            pads: HashMap::new(),
+            mouse_steering: settings.mouse.steering,
+            mouse_buttons: settings.mouse.buttons,
+            drag: Drag::new(),
             head_image,
             body_image,
             fruit_image,
-            head_pos,
-            fruit_pos,
-            body,
-            rng,
-            dir: Direction::Right,
-            dir_new: None,
-            score: 0,
-            show_grid: true,
-            show_fps: true,
-            state: GameState::Starting,
+            turns: vec![TurnQueue::new()],
+            fixed_seed,
+            recording: Replay::new(seed, rules),
+            record_path: args.record.clone(),
+            mode,
+            level_id,
+            autopilot: settings.strategy().unwrap_or_default().autopilot(),
+            assist: false,
+            assisted: false,
+            broadcaster,
+            highscores,
+            highscores_path,
+            overlay: None,
+            savegame,
+            savegame_path,
+            show_grid: settings.display.show_grid,
+            show_fps: settings.display.show_fps,
+            background_color: settings_color(&settings.display.background),
+            grid_color: settings_color(&settings.display.grid),
             head_timer: Duration::from_millis(0),
-            choose_random_direction_counter: 0,
+            move_time: settings.move_time(),
         };
 
-        g.fruit_pos = g.random_free_pos();
+        match &g.mode {
+            // The game watched is the one of the broadcast
+            Mode::Watch(spectator) => {
+                if let Some(game) = spectator.game() {
+                    g.sim = game.clone();
+                }
+            }
+            // The match against another machine goes at the pace chosen by the handshake
+            Mode::Peer(peer) => {
+                g.sim = peer.game().sim().clone();
+                g.turns = vec![TurnQueue::new(); g.sim.snakes().len()];
+                g.move_time = peer.move_time();
+            }
+            _ => {}
+        }
+
+        // Watch a replay instead of playing
+        if matches!(g.mode, Mode::Replay(_)) {
+            g.restart();
+        }
 
         Ok(g)
     }
 
     fn restart(&mut self) {
-        self.head_pos = GridPosition::new(4, 4);
-        self.body.clear();
-        self.fruit_pos = self.random_free_pos();
-        self.dir = Direction::Right;
-        self.dir_new = None;
-        self.score = 0;
-        self.state = GameState::Running;
+        let seed = match &mut self.mode {
+            Mode::Replay(playback) => {
+                playback.player.rewind();
+                playback.player.replay().seed
+            }
+            _ => self.fixed_seed.unwrap_or_else(random_seed),
+        };
+        self.sim.restart(seed);
+        self.recording = Replay::new(seed, self.sim.rules().clone());
+        self.turns = vec![TurnQueue::new(); self.sim.snakes().len()];
+        self.assisted = self.assist;
+
+        // A new match after the last one
+        if let Mode::Versus(versus) = &mut self.mode {
+            if versus.winner().is_some() {
+                *versus = Match::new();
+            }
+        }
         self.head_timer = Duration::from_millis(0);
     }
 
+    /// Move the snake by one tick, with the input of the player or of the replay
+    fn tick(&mut self) {
+        // The players who joined turn their snakes from afar
+        if let Mode::Lan(net) = &mut self.mode {
+            net.tick(&self.sim, &mut self.turns);
+        }
+
+        let mut inputs: Vec<Option<Direction>> = (0..self.sim.snakes().len())
+            .map(|i| self.turns.get_mut(i).and_then(TurnQueue::pop))
+            .collect();
+        if let Mode::Replay(playback) = &mut self.mode {
+            inputs = playback.player.inputs(self.sim.tick());
+        }
+
+        // The autopilot plays the demo, and the player's snake with the assist
+        let demo = self.sim.state() == GameState::Starting;
+        if demo || self.assist {
+            inputs[0] = self.autopilot.choose(&self.sim, 0);
+        } else if let Mode::Play(Play {
+            agent: Some(agent), ..
+        }) = &mut self.mode
+        {
+            inputs[0] = agent.choose(&self.sim, 0);
+        }
+
+        // The bots turn like players pressing keys, in the demo of a match too
+        for (i, input) in inputs.iter_mut().enumerate().skip(1) {
+            match self.bots().get(i - 1) {
+                Some(bot) => *input = bot.choose(&self.sim, i),
+                None if demo => *input = Bot::default().choose(&self.sim, i),
+                None => {}
+            }
+        }
+
+        if self.sim.state() == GameState::Running {
+            self.recording.record_all(self.sim.tick(), &inputs);
+        }
+
+        let events = self.sim.step_all(&inputs);
+
+        // The demo starts again when it ends
+        if demo {
+            if self.sim.state() != GameState::Starting {
+                let seed = self.fixed_seed.unwrap_or_else(random_seed);
+                self.sim = Simulation::new(self.sim.rules().clone(), seed);
+            }
+            return;
+        }
+
+        for event in events.iter() {
+            match (event, &mut self.mode) {
+                (Event::RoundOver { winner }, Mode::Versus(versus)) => versus.record(*winner),
+                // The players see the end of the round, then who won it
+                (Event::RoundOver { winner }, Mode::Lan(net)) => net.round_over(&self.sim, *winner),
+                _ => {}
+            }
+        }
+
+        // Against the bots the game is over when the player dies
+        let player_died = !self.bots().is_empty()
+            && events
+                .iter()
+                .any(|e| matches!(e, Event::Died { snake: 0, .. }));
+        if player_died {
+            self.sim.set_state(GameState::GameOver);
+        }
+
+        // Filling the board completes the level too
+        let completed = events
+            .iter()
+            .any(|e| matches!(e, Event::LevelComplete | Event::Victory { .. }));
+        let over = events
+            .iter()
+            .any(|e| matches!(e, Event::GameOver(_) | Event::RoundOver { .. }));
+        if completed || over || player_died {
+            self.save_recording();
+            if let Mode::Play(play) = &mut self.mode {
+                if let Some(agent) = &mut play.agent {
+                    agent.end(&self.sim, 0);
+                    if let Some(e) = agent.0.error() {
+                        eprintln!("The agent lost control of the snake: {}", e);
+                    }
+                }
+                if let Some(campaign) = &mut play.campaign {
+                    campaign.record(self.sim.score(), completed);
+                }
+            }
+            // Only the players enter the high scores
+            if matches!(&self.mode, Mode::Play(play) if play.agent.is_none())
+                && self.highscores.qualifies(&self.table(), self.sim.score())
+            {
+                self.overlay = Some(Overlay::NameEntry(self.highscores.last_name.clone()));
+            }
+        }
+
+        // The replay ended before the snake died: the player quit
+        if let Mode::Replay(playback) = &self.mode {
+            if self.sim.state() == GameState::Running
+                && playback.player.is_finished(self.sim.tick())
+            {
+                self.sim.set_state(GameState::GameOver);
+            }
+        }
+    }
+
+    /// Show a board, growing the window for a board larger than its own
+    fn fit_board(&mut self, ctx: &mut Context, board: Board) {
+        if self.layout.fits(board) {
+            self.layout = self.layout.resized(board);
+        } else {
+            self.layout = Layout::new(board, default_cell_size(board));
+            if let Err(e) = ctx
+                .gfx
+                .set_drawable_size(self.layout.width, self.layout.height)
+            {
+                eprintln!("Could not resize the window: {}", e);
+            }
+        }
+    }
+
+    /// Send the game to the spectators, if it's broadcast
+    fn publish(&mut self) {
+        if self.broadcaster.is_none() {
+            return;
+        }
+        let players = self.player_names();
+        if let Some(broadcaster) = &mut self.broadcaster {
+            broadcaster.publish(self.sim.rules(), &players, self.sim.frame());
+        }
+    }
+
+    /// The name of the level played, the current stage in the campaign
+    fn level(&self) -> Option<&str> {
+        match self.campaign() {
+            Some(campaign) => Some(campaign.campaign.stages[campaign.stage].id.as_str()),
+            None => self.level_id.as_deref(),
+        }
+    }
+
+    /// The name of the high-score table of the current game
+    fn table(&self) -> String {
+        let name = table_name(self.sim.rules(), self.move_time, self.level());
+        match self.bots().first() {
+            Some(bot) => format!(
+                "{} {} {} bots {} fruits",
+                name,
+                self.bots().len(),
+                bot.difficulty,
+                self.sim.rules().fruits
+            ),
+            None => name,
+        }
+    }
+
+    /// The player is alone on the board, without other players nor bots
+    fn alone(&self) -> bool {
+        self.sim.rules().rivals.is_empty()
+    }
+
+    /// Add the score of the game to the high scores, then show them
+    fn enter_highscore(&mut self, name: &str) {
+        let entry = Entry {
+            name: name.to_string(),
+            score: self.sim.score(),
+            seed: self.sim.seed(),
+            assisted: self.assisted,
+        };
+        let rank = self.highscores.insert(&self.table(), entry);
+        if let Some(path) = &self.highscores_path {
+            if let Err(e) = self.highscores.save(path) {
+                eprintln!(
+                    "Could not save the high scores to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        self.overlay = Some(Overlay::HighScores(rank));
+    }
+
+    /// Save the current game, if it has to be recorded
+    fn save_recording(&self) {
+        if let Some(path) = &self.record_path {
+            if let Err(e) = self.recording.save(path) {
+                eprintln!("Could not save the replay to {}: {}", path.display(), e);
+            }
+        }
+    }
+
     /// Draw the grid
     fn draw_grid(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        let board = self.sim.board();
+        let Layout {
+            origin,
+            board_width,
+            board_height,
+            cell_size,
+            ..
+        } = self.layout;
+
         // First and last point of an horizzontal line
-        let mut point1 = Point2::from_slice(&[0.0, 0.0]);
-        let mut point2 = Point2::from_slice(&[WINDOW_WIDTH, 0.0]);
+        let mut point1 = Point2::from_slice(&[origin.x, 0.0]);
+        let mut point2 = Point2::from_slice(&[origin.x + board_width, 0.0]);
 
         // Draw the horizzontal lines
-        let mut y: f32 = 0.0;
-        while y < WINDOW_HEIGHT {
+        for row in 0..=board.height {
+            let y = origin.y + row as f32 * cell_size;
             point1.y = y;
             point2.y = y;
-            let hline = Mesh::new_line(ctx, &[point1, point2], 1.0, COLOR_GRAY)?;
+            let hline = Mesh::new_line(ctx, &[point1, point2], 1.0, self.grid_color)?;
             // Draw an horizzontal line
             canvas.draw(&hline, DrawParam::default());
-            y += CELL_SIZE as f32;
         }
 
         // First and last point of a vertical line
-        let mut point1 = Point2::from_slice(&[0.0, 0.0]);
-        let mut point2 = Point2::from_slice(&[0.0, WINDOW_HEIGHT]);
+        let mut point1 = Point2::from_slice(&[0.0, origin.y]);
+        let mut point2 = Point2::from_slice(&[0.0, origin.y + board_height]);
 
         // Draw the vertical lines
-        let mut x: f32 = 0.0;
-        while x < WINDOW_WIDTH {
+        for col in 0..=board.width {
+            let x = origin.x + col as f32 * cell_size;
             point1.x = x;
             point2.x = x;
-            let vline = Mesh::new_line(ctx, &[point1, point2], 1.0, COLOR_GRAY).unwrap();
+            let vline = Mesh::new_line(ctx, &[point1, point2], 1.0, self.grid_color)?;
             // Draw a vertical line
             canvas.draw(&vline, DrawParam::default());
-            x += CELL_SIZE as f32;
         }
 
         Ok(())
     }
 
+    /// Draw an image in a cell of the board, scaled to the cell size and tinted with a color
+    fn draw_tinted_cell(
+        &self,
+        canvas: &mut Canvas,
+        image: &Image,
+        pos: GridPosition,
+        color: Color,
+    ) {
+        let scale = self.layout.cell_size / image.width() as f32;
+        let params = DrawParam::default()
+            .dest(self.layout.cell(pos))
+            .scale(Vec2::splat(scale))
+            .color(color);
+        canvas.draw(image, params);
+    }
+
+    /// Draw a snake, tinted with a color
+    fn draw_snake(&self, canvas: &mut Canvas, snake: &Snake, color: Color) {
+        // Draw the body
+        for seg in snake.body().iter() {
+            self.draw_tinted_cell(canvas, &self.body_image, *seg, color);
+        }
+
+        // Draw the snake head
+        self.draw_tinted_cell(canvas, &self.head_image, snake.head_pos(), color);
+    }
+
+    /// The short name of a snake on the screen
+    fn snake_name(&self, snake: usize) -> String {
+        let name = match &self.mode {
+            Mode::Watch(spectator) => spectator.players().get(snake),
+            Mode::Lan(net) => net.players().get(snake),
+            _ => None,
+        };
+        if let Some(name) = name {
+            return name.clone();
+        }
+        match snake {
+            _ if matches!(&self.mode, Mode::Peer(peer) if peer.local() == snake) => {
+                "You".to_string()
+            }
+            _ if matches!(self.mode, Mode::Versus(_) | Mode::Peer(_)) => format!("P{}", snake + 1),
+            0 => "You".to_string(),
+            _ => format!("Bot {}", snake),
+        }
+    }
+
+    /// The names of the snakes for the spectators, who aren't "You"
+    fn player_names(&self) -> Vec<String> {
+        (0..self.sim.snakes().len())
+            .map(|i| match self.snake_name(i) {
+                name if name != "You" => name,
+                _ if !self.highscores.last_name.is_empty() => self.highscores.last_name.clone(),
+                _ => format!("P{}", i + 1),
+            })
+            .collect()
+    }
+
     /// Draw the score
     fn draw_score(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
         // Create a new text
-        let s = format!("Points: {}", self.score);
+        let s = match self.sim.snakes() {
+            [player] if !matches!(self.mode, Mode::Watch(_)) => {
+                format!("Points: {}", player.score())
+            }
+            snakes => snakes
+                .iter()
+                .enumerate()
+                .map(|(i, snake)| format!("{}: {}", self.snake_name(i), snake.score()))
+                .collect::<Vec<_>>()
+                .join("  "),
+        };
         let mut text = Text::new(s);
 
         // Set font size
@@ -270,8 +1041,8 @@ impl MyGame {
         // Set text position to the bottom-right of the screen
         let m = text.measure(ctx)?;
         let coords = [
-            WINDOW_WIDTH - m.x - CELL_SIZE as f32 / 2.0,
-            WINDOW_HEIGHT - m.y - CELL_SIZE as f32 / 3.0,
+            self.layout.width - m.x - HUD_MARGIN_X,
+            self.layout.height - m.y - HUD_MARGIN_Y,
         ];
 
         // Set params
@@ -297,10 +1068,7 @@ impl MyGame {
 
         // Set text position to the bottom-left of the screen
         let m = text.measure(ctx)?;
-        let coords = [
-            CELL_SIZE as f32 / 2.0,
-            WINDOW_HEIGHT - m.y - CELL_SIZE as f32 / 3.0,
-        ];
+        let coords = [HUD_MARGIN_X, self.layout.height - m.y - HUD_MARGIN_Y];
 
         // Set params
         let params = DrawParam::default().dest(coords).color(COLOR_GRAY);
@@ -311,331 +1079,1609 @@ impl MyGame {
         Ok(())
     }
 
-    /// Draw the arena of a running game
-    fn draw_running(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
-        // Draw the grid
-        if self.show_grid {
-            self.draw_grid(ctx, canvas)?;
-        }
-
-        // Draw the fruit
-        canvas.draw(
-            &self.fruit_image,
-            DrawParam::default().dest(self.fruit_pos.as_vec2()),
-        );
-
-        // Draw the body
-        for seg in self.body.iter() {
-            canvas.draw(&self.body_image, DrawParam::default().dest(seg.as_vec2()));
-        }
+    /// Draw the seed of the current game
+    fn draw_seed(&self, canvas: &mut Canvas) {
+        // Create a new text
+        let s = format!("Seed: {}", self.sim.seed());
+        let mut text = Text::new(s);
 
-        // Draw the snake head
-        canvas.draw(
-            &self.head_image,
-            DrawParam::default().dest(self.head_pos.as_vec2()),
-        );
+        // Set font size
+        text.set_scale(PxScale::from(20.0));
 
-        // Draw FPS
-        if self.show_fps {
-            self.draw_fps(ctx, canvas)?;
-        }
+        // Set text position to the top-left of the screen
+        let coords = [HUD_MARGIN_X, HUD_MARGIN_Y];
 
-        // Draw the score
-        self.draw_score(ctx, canvas)?;
+        // Set params
+        let params = DrawParam::default().dest(coords).color(COLOR_GRAY);
 
-        Ok(())
+        // Draw the text
+        canvas.draw(&text, params);
     }
 
-    fn draw_starting(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
-        // Draw the grid
-        if self.show_grid {
-            self.draw_grid(ctx, canvas)?;
-        }
-
-        // Draw the body
-        for seg in self.body.iter() {
-            canvas.draw(&self.body_image, DrawParam::default().dest(seg.as_vec2()));
-        }
-
-        // Draw the snake head
-        canvas.draw(
-            &self.head_image,
-            DrawParam::default().dest(self.head_pos.as_vec2()),
-        );
+    /// Draw the goal of the level, and how far the snake is from it
+    fn draw_goal(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        let Some(goal) = self.sim.rules().goal else {
+            return Ok(());
+        };
 
         // Create a new text
-        let mut text1 = Text::new("SNAKE");
-        let mut text2 = Text::new("Press any key to start");
+        let s = match goal {
+            Goal::Length(l) => format!("Length: {} / {}", self.sim.length(), l),
+            Goal::Score(s) => format!("Goal: {} / {}", self.sim.score(), s),
+        };
+        let mut text = Text::new(s);
 
         // Set font size
-        text1.set_scale(PxScale::from(200.0));
-        text2.set_scale(PxScale::from(20.0));
-
-        // Set text position to the center of the screen
-        let m1 = text1.measure(ctx)?;
-        let m2 = text2.measure(ctx)?;
+        text.set_scale(PxScale::from(20.0));
 
-        let y1 = (WINDOW_HEIGHT - m1.y) / 2.0;
-        let y2 = (WINDOW_HEIGHT - m2.y + m1.y) / 2.0;
-        let coords1 = [(WINDOW_WIDTH - m1.x) / 2.0, y1];
-        let coords2 = [(WINDOW_WIDTH - m2.x) / 2.0, y2];
+        // Set text position to the top-center of the screen
+        let m = text.measure(ctx)?;
+        let coords = [(self.layout.width - m.x) / 2.0, HUD_MARGIN_Y];
 
         // Set params
-        let params = DrawParam::default().dest(coords1).color(Color::GREEN);
-        // Draw the text
-        canvas.draw(&text1, params);
+        let params = DrawParam::default().dest(coords).color(Color::YELLOW);
 
-        // Set params
-        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
         // Draw the text
-        canvas.draw(&text2, params);
+        canvas.draw(&text, params);
 
         Ok(())
     }
 
-    fn draw_gameover(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
-        self.draw_running(ctx, canvas)?;
-
+    /// Draw that the autopilot drives the snake
+    fn draw_assist(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
         // Create a new text
-        let mut text1 = Text::new("GAME OVER");
-        let mut text2 = Text::new("Do you want to play again? (Y / N)");
+        let s = format!(
+            "AUTOPILOT ({} to take the snake back)",
+            self.controls.key_name(Action::Assist)
+        );
+        let mut text = Text::new(s);
 
         // Set font size
-        text1.set_scale(PxScale::from(100.0));
-        text2.set_scale(PxScale::from(20.0));
-
-        // Set text position to the center of the screen
-        let m1 = text1.measure(ctx)?;
-        let m2 = text2.measure(ctx)?;
+        text.set_scale(PxScale::from(20.0));
 
-        let y1 = (WINDOW_HEIGHT - m1.y) / 2.0;
-        let y2 = (WINDOW_HEIGHT - m2.y + m1.y) / 2.0;
-        let coords1 = [(WINDOW_WIDTH - m1.x) / 2.0, y1];
-        let coords2 = [(WINDOW_WIDTH - m2.x) / 2.0, y2];
+        // Set text position to the top-right of the screen
+        let m = text.measure(ctx)?;
+        let coords = [self.layout.width - m.x - HUD_MARGIN_X, HUD_MARGIN_Y];
 
         // Set params
-        let params = DrawParam::default().dest(coords1).color(Color::GREEN);
-        // Draw the text
-        canvas.draw(&text1, params);
+        let params = DrawParam::default().dest(coords).color(Color::CYAN);
 
-        // Set params
-        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
         // Draw the text
-        canvas.draw(&text2, params);
+        canvas.draw(&text, params);
 
         Ok(())
     }
 
-    fn draw_quitting(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
-        self.draw_running(ctx, canvas)?;
+    /// Draw the walls: the edges of the board that don't wrap around,
+    /// and the walls of the level as tiles
+    fn draw_walls(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        let boundary = self.sim.rules().boundary;
+        let Layout {
+            origin,
+            board_width,
+            board_height,
+            ..
+        } = self.layout;
+
+        // Keep the walls just outside the board
+        let d = WALL_WIDTH / 2.0;
+        let (left, right) = (origin.x - d, origin.x + board_width + d);
+        let (top, bottom) = (origin.y - d, origin.y + board_height + d);
+
+        let mut walls = Vec::new();
+        if !boundary.wraps_y() {
+            walls.push([[left, top], [right, top]]);
+            walls.push([[left, bottom], [right, bottom]]);
+        }
+        if !boundary.wraps_x() {
+            walls.push([[left, top], [left, bottom]]);
+            walls.push([[right, top], [right, bottom]]);
+        }
 
-        // Create a new text
-        let mut text2 = Text::new("Do you really want to quit? (Y / N)");
+        for [p1, p2] in walls {
+            let points = [Point2::from_slice(&p1), Point2::from_slice(&p2)];
+            let wall = Mesh::new_line(ctx, &points, WALL_WIDTH, COLOR_WALL)?;
+            canvas.draw(&wall, DrawParam::default());
+        }
 
-        // Set font size
-        text2.set_scale(PxScale::from(20.0));
+        for wall in self.sim.rules().walls.iter() {
+            let dest = self.layout.cell(*wall);
+            let rect = Rect::new(dest.x, dest.y, self.layout.cell_size, self.layout.cell_size);
+            canvas.draw(
+                &Quad,
+                DrawParam::default().dest_rect(rect).color(COLOR_WALL),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Draw the board: the grid, the walls, the fruits and the snakes
+    fn draw_board(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        // Draw the grid
+        if self.show_grid {
+            self.draw_grid(ctx, canvas)?;
+        }
+
+        // Draw the walls
+        self.draw_walls(ctx, canvas)?;
+
+        // Draw the fruits, each of the color of its snake if they are separate
+        for (i, fruit) in self.sim.fruits().iter().enumerate() {
+            let color = match self.sim.fruits().len() {
+                1 => Color::WHITE,
+                _ => SNAKE_COLORS[i % SNAKE_COLORS.len()],
+            };
+            self.draw_tinted_cell(canvas, &self.fruit_image, *fruit, color);
+        }
+
+        // Draw the snakes
+        for (i, snake) in self.sim.snakes().iter().enumerate() {
+            self.draw_snake(canvas, snake, SNAKE_COLORS[i % SNAKE_COLORS.len()]);
+        }
+
+        Ok(())
+    }
+
+    /// Draw the arena of a running game
+    fn draw_running(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_board(ctx, canvas)?;
+
+        // Draw FPS
+        if self.show_fps {
+            self.draw_fps(ctx, canvas)?;
+        }
+
+        // Draw the score
+        self.draw_score(ctx, canvas)?;
+
+        // Draw the seed, only known to the host in a game over the network
+        if !self.joined() {
+            self.draw_seed(canvas);
+        }
+
+        // Draw the goal
+        self.draw_goal(ctx, canvas)?;
+
+        // Draw the replay status
+        if let Mode::Replay(playback) = &self.mode {
+            self.draw_playback(ctx, canvas, playback)?;
+        }
+
+        // Draw the autopilot status
+        if self.assist {
+            self.draw_assist(ctx, canvas)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_starting(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        // Draw the demo
+        self.draw_board(ctx, canvas)?;
+
+        // Create a new text
+        let mut text1 = Text::new("SNAKE");
+        let mut text2 = match self.saved_game() {
+            Some(_) => Text::new(format!(
+                "Press any key to start, {} to continue\n{} for the high scores, {} for the controls",
+                self.controls.key_name(Action::Continue),
+                self.controls.key_name(Action::HighScores),
+                self.controls.key_name(Action::Bindings)
+            )),
+            None => Text::new(format!(
+                "Press any key to start\n{} for the high scores, {} for the controls",
+                self.controls.key_name(Action::HighScores),
+                self.controls.key_name(Action::Bindings)
+            )),
+        };
+        let mut text3 = match self.campaign() {
+            Some(campaign) => {
+                let stage = &campaign.campaign.stages[campaign.stage];
+                let goal = stage
+                    .level
+                    .rules
+                    .goal
+                    .map_or(String::new(), |g| g.to_string());
+                Text::new(format!(
+                    "Stage {} / {}: {} - goal: {} - best: {}\n({} / {} to choose)",
+                    campaign.stage + 1,
+                    campaign.campaign.stages.len(),
+                    stage.level.name,
+                    goal,
+                    campaign.progress.best(&stage.id),
+                    self.controls.key_name(Action::TurnLeft),
+                    self.controls.key_name(Action::TurnRight)
+                ))
+            }
+            None => Text::new(""),
+        };
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 4.0).min(200.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
 
         // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
         let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
 
-        let y2 = (WINDOW_HEIGHT - m2.y) / 2.0;
-        let coords2 = [(WINDOW_WIDTH - m2.x) / 2.0, y2];
+        let y1 = (self.layout.height - m1.y) / 2.0;
+        let y2 = (self.layout.height - m2.y + m1.y) / 2.0;
+        let y3 = y2 + m2.y * 1.5;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::GREEN);
+        // Draw the text
+        canvas.draw(&text1, params);
 
         // Set params
         let params = DrawParam::default().dest(coords2).color(Color::WHITE);
         // Draw the text
         canvas.draw(&text2, params);
 
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(Color::YELLOW);
+        // Draw the text
+        canvas.draw(&text3, params);
+
         Ok(())
     }
 
-    /// Change the direction when an arrow key is pressed
-    fn process_input(&mut self, ctx: &Context) {
-        // Turn the direction based on the key pressed,
-        // but avoid the opposite direction if the snake has a body
-        if ctx.keyboard.is_key_pressed(KeyCode::Down)
-            && (self.body.is_empty() || !self.dir.opposite(Direction::Down))
-        {
-            self.dir_new = Some(Direction::Down);
-        } else if ctx.keyboard.is_key_pressed(KeyCode::Up)
-            && (self.body.is_empty() || !self.dir.opposite(Direction::Up))
-        {
-            self.dir_new = Some(Direction::Up);
-        } else if ctx.keyboard.is_key_pressed(KeyCode::Right)
-            && (self.body.is_empty() || !self.dir.opposite(Direction::Right))
-        {
-            self.dir_new = Some(Direction::Right);
-        } else if ctx.keyboard.is_key_pressed(KeyCode::Left)
-            && (self.body.is_empty() || !self.dir.opposite(Direction::Left))
-        {
-            self.dir_new = Some(Direction::Left);
-        }
+    fn draw_gameover(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
+
+        // Create a new text
+        // The player outlived the bots
+        let won = !self.bots().is_empty() && self.sim.player().is_alive();
+        let mut text1 = if won {
+            Text::new("YOU WIN!")
+        } else {
+            Text::new("GAME OVER")
+        };
+        let mut text2 = if matches!(self.mode, Mode::Replay(_)) {
+            Text::new(format!("Do you want to watch again? {}", self.yes_no()))
+        } else {
+            Text::new(format!("Do you want to play again? {}", self.yes_no()))
+        };
+        let assisted = if self.assisted { " - Assisted" } else { "" };
+        let mut text3 = match self.sim.death() {
+            Some(death) => Text::new(format!("{} - Seed: {}{}", death, self.sim.seed(), assisted)),
+            None if won => Text::new(format!(
+                "The bots are out - Seed: {}{}",
+                self.sim.seed(),
+                assisted
+            )),
+            None => Text::new(format!("Seed: {}{}", self.sim.seed(), assisted)),
+        };
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 8.0).min(100.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y) / 2.0;
+        let y2 = (self.layout.height - m2.y + m1.y) / 2.0;
+        let y3 = y2 + m2.y * 1.5;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::GREEN);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(COLOR_GRAY);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        Ok(())
     }
 
-    /// Return a random free position on the grid
-    fn random_free_pos(&mut self) -> GridPosition {
-        let mut freepos: Vec<GridPosition> = Vec::new();
+    fn draw_level_complete(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
 
-        for y in 0..GRID_HEIGHT {
-            for x in 0..GRID_WIDTH {
-                let p = GridPosition::new(x, y);
-                if p != self.head_pos && !self.body.contains(&p) {
-                    freepos.push(p)
-                }
+        // Create a new text
+        let mut text1 = Text::new("LEVEL COMPLETE");
+        let (mut text2, mut text3) = match self.campaign() {
+            Some(campaign) => {
+                let stage = &campaign.campaign.stages[campaign.stage];
+                let best = campaign.progress.best(&stage.id);
+                let text2 = if campaign.stage + 1 < campaign.campaign.stages.len() {
+                    Text::new("Press any key for the next stage")
+                } else {
+                    Text::new("You completed the campaign! Press any key")
+                };
+                (text2, Text::new(format!("Best: {}", best)))
+            }
+            None if matches!(self.mode, Mode::Replay(_)) => (
+                Text::new(format!("Do you want to watch again? {}", self.yes_no())),
+                Text::new(format!("Seed: {}", self.sim.seed())),
+            ),
+            None => (
+                Text::new(format!("Do you want to play again? {}", self.yes_no())),
+                Text::new(format!("Seed: {}", self.sim.seed())),
+            ),
+        };
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 10.0).min(80.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y) / 2.0;
+        let y2 = (self.layout.height - m2.y + m1.y) / 2.0;
+        let y3 = y2 + m2.y * 1.5;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::GREEN);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(COLOR_GRAY);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        Ok(())
+    }
+
+    fn draw_victory(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
+
+        // Create a new text
+        let mut text1 = Text::new("YOU WIN!");
+        let mut text2 = match self.campaign() {
+            Some(campaign) if campaign.stage + 1 < campaign.campaign.stages.len() => {
+                Text::new("Press any key for the next stage")
+            }
+            Some(_) => Text::new("You completed the campaign! Press any key"),
+            None if matches!(self.mode, Mode::Replay(_)) => {
+                Text::new(format!("Do you want to watch again? {}", self.yes_no()))
             }
+            None => Text::new(format!("Do you want to play again? {}", self.yes_no())),
+        };
+        let bonus = VICTORY_BONUS_FRUITS * self.sim.rules().fruit_points;
+        let mut text3 = Text::new(format!(
+            "The snake filled the board! Bonus: {} - Seed: {}",
+            bonus,
+            self.sim.seed()
+        ));
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 8.0).min(100.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y) / 2.0;
+        let y2 = (self.layout.height - m2.y + m1.y) / 2.0;
+        let y3 = y2 + m2.y * 1.5;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::YELLOW);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(COLOR_GRAY);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        Ok(())
+    }
+
+    /// Draw the controls screen: the keys of each action
+    fn draw_bindings(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        screen: &BindingsScreen,
+    ) -> GameResult {
+        self.draw_shade(canvas);
+
+        // Create a new text
+        let mut title = Text::new("CONTROLS");
+        let mut footer = match &screen.conflict {
+            Some(conflict) => Text::new(format!("{}, not changed", conflict)),
+            None => Text::new(
+                "Up / Down to choose, Return to change\n1, 2, 3: arrows, WASD or vim keys, Escape to close",
+            ),
+        };
+
+        // Set font size, small enough for all the actions to fit
+        let line_scale =
+            ((self.layout.height - 160.0) / (Action::ALL.len() as f32 * 1.2)).min(20.0);
+        title.set_scale(PxScale::from((self.layout.width / 10.0).min(60.0)));
+        footer.set_scale(PxScale::from(16.0));
+
+        // Set text position to the top of the screen, the actions below
+        let mt = title.measure(ctx)?;
+        let mf = footer.measure(ctx)?;
+        let mut y = HUD_MARGIN_Y;
+        canvas.draw(
+            &title,
+            DrawParam::default()
+                .dest([(self.layout.width - mt.x) / 2.0, y])
+                .color(Color::GREEN),
+        );
+        y += mt.y * 1.2;
+
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let keys = if i == screen.selected && screen.waiting {
+                "press a key...".to_string()
+            } else {
+                let names: Vec<_> = self
+                    .controls
+                    .keys(*action)
+                    .iter()
+                    .map(|k| key_name(*k))
+                    .collect();
+                names.join(", ")
+            };
+            let mut text = Text::new(format!("{:<24} {}", action.to_string(), keys));
+            text.set_scale(PxScale::from(line_scale));
+            let m = text.measure(ctx)?;
+            let color = if i == screen.selected {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+            let params = DrawParam::default()
+                .dest([(self.layout.width - m.x) / 2.0, y])
+                .color(color);
+            canvas.draw(&text, params);
+            y += m.y * 1.2;
         }
 
-        // This should happens only when the snake body fills all the grid
-        if freepos.is_empty() {
-            self.body.clear();
-            return self.random_free_pos();
+        let color = match screen.conflict {
+            Some(_) => Color::RED,
+            None => COLOR_GRAY,
+        };
+        let coords = [
+            (self.layout.width - mf.x) / 2.0,
+            self.layout.height - mf.y - HUD_MARGIN_Y,
+        ];
+        canvas.draw(&footer, DrawParam::default().dest(coords).color(color));
+
+        Ok(())
+    }
+
+    /// Draw the buttons of the screen, lighter under the mouse
+    fn draw_buttons(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        let mouse = ctx.mouse.position();
+        for button in self.buttons() {
+            let color = if button.rect.contains(mouse) {
+                COLOR_BUTTON_HOVER
+            } else {
+                COLOR_BUTTON
+            };
+            canvas.draw(
+                &Quad,
+                DrawParam::default().dest_rect(button.rect).color(color),
+            );
+
+            // Create a new text
+            let mut text = Text::new(button.label);
+
+            // Set font size
+            text.set_scale(PxScale::from(18.0));
+
+            // Set text position to the center of the button
+            let m = text.measure(ctx)?;
+            let coords = [
+                button.rect.x + (button.rect.w - m.x) / 2.0,
+                button.rect.y + (button.rect.h - m.y) / 2.0,
+            ];
+
+            // Set params
+            let params = DrawParam::default().dest(coords).color(Color::WHITE);
+
+            // Draw the text
+            canvas.draw(&text, params);
         }
 
-        let i = self.rng.rand_range(0..freepos.len() as u32) as usize;
+        Ok(())
+    }
 
-        freepos[i]
+    /// Darken the game under an overlay
+    fn draw_shade(&self, canvas: &mut Canvas) {
+        let rect = Rect::new(0.0, 0.0, self.layout.width, self.layout.height);
+        let color = Color::new(0.0, 0.0, 0.0, 0.75);
+        canvas.draw(&Quad, DrawParam::default().dest_rect(rect).color(color));
     }
 
-    /// Choose a new random direction for the snake
-    fn choose_random_direction(&mut self) {
-        let d = self.rng.rand_range(0..4);
-        let somedir = match d {
-            0 => Some(Direction::Down),
-            1 => Some(Direction::Left),
-            2 => Some(Direction::Right),
-            4 => Some(Direction::Up),
-            _ => None,
+    fn draw_name_entry(&self, ctx: &mut Context, canvas: &mut Canvas, name: &str) -> GameResult {
+        self.draw_shade(canvas);
+
+        // Create a new text
+        let mut text1 = Text::new("NEW HIGH SCORE");
+        let mut text2 = Text::new(format!("Points: {}", self.sim.score()));
+        let mut text3 = Text::new(format!("Your name: {}_", name));
+        let mut text4 = Text::new("Return to confirm, Escape to skip");
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 10.0).min(80.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+        text4.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+        let m4 = text4.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y) / 2.0 - m2.y * 2.0;
+        let y2 = y1 + m1.y * 1.5;
+        let y3 = y2 + m2.y * 2.0;
+        let y4 = y3 + m3.y * 2.0;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+        let coords4 = [(self.layout.width - m4.x) / 2.0, y4];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::YELLOW);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::YELLOW);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords4).color(COLOR_GRAY);
+        // Draw the text
+        canvas.draw(&text4, params);
+
+        Ok(())
+    }
+
+    fn draw_highscores(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        highlight: Option<usize>,
+    ) -> GameResult {
+        self.draw_shade(canvas);
+
+        let table = self.table();
+        let entries = self.highscores.table(&table);
+
+        // Create a new text
+        let mut title = Text::new("HIGH SCORES");
+        let mut subtitle = Text::new(table.as_str());
+        let mut footer = if entries.iter().any(|e| e.assisted) {
+            Text::new("* with the autopilot - Press any key")
+        } else {
+            Text::new("Press any key")
         };
-        if let Some(dir_new) = somedir {
-            if !dir_new.opposite(self.dir) {
-                self.dir_new = somedir;
+
+        // Set font size
+        title.set_scale(PxScale::from((self.layout.width / 10.0).min(80.0)));
+        subtitle.set_scale(PxScale::from(16.0));
+        footer.set_scale(PxScale::from(20.0));
+
+        // Set text position to the top of the screen, the entries below
+        let mt = title.measure(ctx)?;
+        let ms = subtitle.measure(ctx)?;
+        let mf = footer.measure(ctx)?;
+        let mut y = HUD_MARGIN_Y * 2.0;
+        canvas.draw(
+            &title,
+            DrawParam::default()
+                .dest([(self.layout.width - mt.x) / 2.0, y])
+                .color(Color::GREEN),
+        );
+        y += mt.y;
+        canvas.draw(
+            &subtitle,
+            DrawParam::default()
+                .dest([(self.layout.width - ms.x) / 2.0, y])
+                .color(Color::WHITE),
+        );
+        y += ms.y * 2.0;
+
+        if entries.is_empty() {
+            let mut text = Text::new("No scores yet");
+            text.set_scale(PxScale::from(20.0));
+            let m = text.measure(ctx)?;
+            let params = DrawParam::default()
+                .dest([(self.layout.width - m.x) / 2.0, y])
+                .color(COLOR_GRAY);
+            canvas.draw(&text, params);
+        }
+
+        for (i, entry) in entries.iter().enumerate() {
+            let mut text = Text::new(format!(
+                "{:>2}. {:<width$} {:>6}{}",
+                i + 1,
+                entry.name,
+                entry.score,
+                if entry.assisted { "*" } else { " " },
+                width = MAX_NAME_LEN
+            ));
+            text.set_scale(PxScale::from(20.0));
+            let m = text.measure(ctx)?;
+            let color = if highlight == Some(i) {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+            let params = DrawParam::default()
+                .dest([(self.layout.width - m.x) / 2.0, y])
+                .color(color);
+            canvas.draw(&text, params);
+            y += m.y * 1.2;
+        }
+
+        let coords = [
+            (self.layout.width - mf.x) / 2.0,
+            self.layout.height - mf.y - HUD_MARGIN_Y * 2.0,
+        ];
+        canvas.draw(&footer, DrawParam::default().dest(coords).color(COLOR_GRAY));
+
+        Ok(())
+    }
+
+    fn draw_quitting(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
+
+        // Create a new text
+        let mut text2 = if self.alone() {
+            Text::new(format!(
+                "Do you really want to quit? {}\n{} to save and quit",
+                self.yes_no(),
+                self.controls.key_name(Action::SaveAndQuit)
+            ))
+        } else {
+            Text::new(format!("Do you really want to quit? {}", self.yes_no()))
+        };
+
+        // Set font size
+        text2.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m2 = text2.measure(ctx)?;
+
+        let y2 = (self.layout.height - m2.y) / 2.0;
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        Ok(())
+    }
+
+    /// The keys to answer a question, like "(Y / N)"
+    fn yes_no(&self) -> String {
+        format!(
+            "({} / {})",
+            self.controls.key_name(Action::Confirm),
+            self.controls.key_name(Action::Cancel)
+        )
+    }
+
+    /// Save the keys changed on the controls screen
+    fn save_controls(&self) {
+        if let Some(path) = &self.controls_path {
+            if let Err(e) = self.controls.save(path) {
+                eprintln!("Could not save the controls to {}: {}", path.display(), e);
             }
         }
     }
+
+    /// The buttons of the current screen, in a row at the bottom, if `[mouse] buttons` is set
+    fn buttons(&self) -> Vec<ScreenButton> {
+        if !self.mouse_buttons || matches!(self.mode, Mode::Watch(_)) {
+            return Vec::new();
+        }
+
+        let labels: Vec<(&'static str, Vec<Action>)> = match &self.overlay {
+            Some(Overlay::NameEntry(_)) => vec![
+                ("OK", vec![Action::Confirm]),
+                ("Skip", vec![Action::Cancel]),
+            ],
+            Some(_) => Vec::new(),
+            None => match self.sim.state() {
+                GameState::Starting => {
+                    let mut labels = vec![("Play", vec![])];
+                    if self.saved_game().is_some() {
+                        labels.push(("Continue", vec![Action::Continue]));
+                    }
+                    labels.push(("Scores", vec![Action::HighScores]));
+                    if self.campaign().is_some() {
+                        labels.insert(0, ("<", vec![Action::TurnLeft]));
+                        labels.push((">", vec![Action::TurnRight]));
+                    }
+                    labels
+                }
+                GameState::Paused => vec![
+                    ("Resume", vec![Action::Pause]),
+                    ("Quit", vec![Action::Quit]),
+                ],
+                GameState::Quitting if !self.alone() => {
+                    vec![("Yes", vec![Action::Confirm]), ("No", vec![Action::Cancel])]
+                }
+                GameState::Quitting => vec![
+                    ("Yes", vec![Action::Confirm]),
+                    ("No", vec![Action::Cancel]),
+                    ("Save", vec![Action::SaveAndQuit]),
+                ],
+                GameState::LevelComplete | GameState::Victory if self.campaign().is_some() => {
+                    vec![("Next", vec![]), ("Quit", vec![Action::Quit])]
+                }
+                GameState::GameOver | GameState::LevelComplete | GameState::Victory => vec![
+                    ("Again", vec![Action::Confirm]),
+                    ("Quit", vec![Action::Cancel]),
+                ],
+                _ => Vec::new(),
+            },
+        };
+
+        // As wide as possible up to BUTTON_WIDTH, above the score and the FPS
+        let n = labels.len() as f32;
+        let width =
+            BUTTON_WIDTH.min((self.layout.width - 2.0 * HUD_MARGIN_X - BUTTON_GAP * (n - 1.0)) / n);
+        let x = (self.layout.width - width * n - BUTTON_GAP * (n - 1.0)) / 2.0;
+        let y = self.layout.height - BUTTON_HEIGHT - 20.0 - HUD_MARGIN_Y * 2.0;
+
+        labels
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, actions))| ScreenButton {
+                label,
+                rect: Rect::new(x + i as f32 * (width + BUTTON_GAP), y, width, BUTTON_HEIGHT),
+                press: Press { key: None, actions },
+            })
+            .collect()
+    }
+
+    /// The pointer steers the snake: a game is being played
+    fn steering(&self) -> bool {
+        self.mouse_steering
+            && matches!(self.mode, Mode::Play(_) | Mode::Versus(_) | Mode::Lan(_))
+            && self.overlay.is_none()
+            && self.sim.state() == GameState::Running
+    }
+
+    /// The center of the head in pixels, and the distance around it where the pointer doesn't turn
+    fn head_center(&self) -> (Vec2, f32) {
+        let half = self.layout.cell_size / 2.0;
+        (
+            self.layout.cell(self.sim.head_pos()) + Vec2::splat(half),
+            half,
+        )
+    }
 }
 
 impl EventHandler for MyGame {
     /// The main update function for our snake which gets called every time
     /// we want to update the game state.
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        if self.state == GameState::Running {
-            // Check input
-            self.process_input(ctx);
+        match self.mode {
+            // A game watched moves in another process
+            Mode::Watch(_) => {
+                self.update_spectator(ctx);
+                return Ok(());
+            }
+            // A match against another machine is played by the session
+            Mode::Peer(_) => {
+                self.update_peer(ctx);
+                self.publish();
+                return Ok(());
+            }
+            // Take the players and their turns, or the game of the host
+            Mode::Lan(_) => self.update_net(ctx),
+            Mode::Play(_) | Mode::Replay(_) | Mode::Versus(_) => {}
         }
 
-        if self.state == GameState::Running || self.state == GameState::Starting {
+        // A game joined over the network moves on the host
+        let shown = matches!(&self.mode, Mode::Lan(net) if net.following());
+        if !shown
+            && (self.sim.state() == GameState::Running || self.sim.state() == GameState::Starting)
+        {
             // Time from the last snake movement
             self.head_timer += ctx.time.delta();
 
-            // If it's time move the snake
-            if self.head_timer >= MOVE_TIME {
-                // Choose a random direction
-                if self.state == GameState::Starting {
-                    self.choose_random_direction_counter += 1;
-                    if self.choose_random_direction_counter > 3 {
-                        self.choose_random_direction();
-                        self.choose_random_direction_counter = 0;
+            // If it's time move the snake, several times when fast-forwarding a replay
+            if self.head_timer >= self.move_time {
+                let speed = match &self.mode {
+                    Mode::Replay(playback) => playback.speed,
+                    _ => 1,
+                };
+                for _ in 0..speed {
+                    if self.sim.state() == GameState::Running
+                        || self.sim.state() == GameState::Starting
+                    {
+                        self.tick();
                     }
                 }
 
-                // Eventually change the direction
-                if let Some(dir_new) = self.dir_new {
-                    self.dir = dir_new;
-                    self.dir_new = None;
-                }
-
-                // Move the head
-                self.body.push_front(self.head_pos);
-                self.head_pos.move_to_direction(self.dir);
-
-                // If the snake eats itself is game over
-                for seg in self.body.iter() {
-                    if self.head_pos == *seg {
-                        self.state = GameState::GameOver;
-                    }
-                }
-
-                // If the snake eats a fruit increment the score and the body lenght
-                if self.head_pos == self.fruit_pos {
-                    self.score += FRUIT_POINTS;
-                    self.fruit_pos = self.random_free_pos();
-                } else {
-                    self.body.pop_back();
-                }
-
                 // Reset the timer
                 self.head_timer = Duration::from_millis(0);
             }
         }
 
+        // Send the game to the players who joined, and to the spectators
+        if let Mode::Lan(net) = &mut self.mode {
+            net.send_frame(&self.sim);
+        }
+        self.publish();
+
         Ok(())
     }
 
     /// The main drawing function
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         // Create a new Canvas that renders directly to the window surface.
-        let mut canvas = Canvas::from_frame(ctx, COLOR_BACKGROUND);
-
-        match self.state {
-            GameState::Starting => self.draw_starting(ctx, &mut canvas)?,
-            GameState::GameOver => self.draw_gameover(ctx, &mut canvas)?,
-            GameState::Quitting => self.draw_quitting(ctx, &mut canvas)?,
+        let mut canvas = Canvas::from_frame(ctx, self.background_color);
+
+        match (&self.mode, self.sim.state()) {
+            (Mode::Watch(_), _) => self.draw_watching(ctx, &mut canvas)?,
+            (Mode::Lan(_), GameState::Starting) => self.draw_lobby(ctx, &mut canvas)?,
+            (_, GameState::Starting) => self.draw_starting(ctx, &mut canvas)?,
+            (Mode::Lan(_), GameState::GameOver) => self.draw_net_over(ctx, &mut canvas)?,
+            (Mode::Versus(_) | Mode::Peer(_), GameState::GameOver) => {
+                self.draw_round_over(ctx, &mut canvas)?
+            }
+            (_, GameState::GameOver) => self.draw_gameover(ctx, &mut canvas)?,
+            (_, GameState::LevelComplete) => self.draw_level_complete(ctx, &mut canvas)?,
+            (_, GameState::Victory) => self.draw_victory(ctx, &mut canvas)?,
+            (_, GameState::Quitting) => self.draw_quitting(ctx, &mut canvas)?,
             _ => self.draw_running(ctx, &mut canvas)?,
         };
 
+        match &self.overlay {
+            Some(Overlay::NameEntry(name)) => self.draw_name_entry(ctx, &mut canvas, name)?,
+            Some(Overlay::HighScores(highlight)) => {
+                self.draw_highscores(ctx, &mut canvas, *highlight)?
+            }
+            Some(Overlay::Bindings(screen)) => self.draw_bindings(ctx, &mut canvas, screen)?,
+            None => {}
+        }
+
+        // Draw the buttons over everything
+        self.draw_buttons(ctx, &mut canvas)?;
+
         // Finish drawing with this canvas and submit all the draw calls.
         canvas.finish(ctx)
     }
 
     /// A keyboard button was pressed.
     fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repetd: bool) -> GameResult {
-        match self.state {
-            GameState::Starting => match input.keycode.unwrap() {
-                KeyCode::Q | KeyCode::Escape => ctx.request_quit(),
+        let Some(key) = input.keycode else {
+            return Ok(());
+        };
+
+        // In a match on one keyboard each player turns their snake with their own keys
+        if self.versus_key_event(key) {
+            return Ok(());
+        }
+
+        let press = self.controls.press(key);
+        self.press_event(ctx, &press)
+    }
+
+    /// A gamepad button was pressed.
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        btn: Button,
+        id: GamepadId,
+    ) -> GameResult {
+        let press = self.pads.entry(id).or_default().button_down(btn);
+        self.press_event(ctx, &press)
+    }
+
+    /// A gamepad stick moved: it may turn the snake like a D-pad.
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut Context,
+        axis: Axis,
+        value: f32,
+        id: GamepadId,
+    ) -> GameResult {
+        match self.pads.entry(id).or_default().axis(axis, value) {
+            Some(press) => self.press_event(ctx, &press),
+            None => Ok(()),
+        }
+    }
+
+    /// A mouse button was pressed, or the screen touched.
+    /// A click on a button presses its actions, elsewhere it may turn the snake.
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if button != MouseButton::Left {
+            return Ok(());
+        }
+        let point = Vec2::new(x, y);
+
+        if let Some(button) = self.buttons().into_iter().find(|b| b.rect.contains(point)) {
+            return self.press_event(ctx, &button.press);
+        }
+
+        if self.steering() {
+            let (head, dead_zone) = self.head_center();
+            if let Some(dir) = self.drag.press(head, point, dead_zone) {
+                self.turns[0].push(&self.sim, dir);
+            }
+        } else if self.mouse_buttons {
+            // Like a key bound to nothing: it starts a game, or closes the high scores
+            self.press_event(ctx, &Press::default())?;
+        }
+
+        Ok(())
+    }
+
+    /// A mouse button was released, or the finger lifted.
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> GameResult {
+        if button == MouseButton::Left {
+            self.drag.release();
+        }
+        Ok(())
+    }
+
+    /// The mouse moved: dragging it around the head keeps steering.
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult {
+        if self.steering() {
+            let (head, dead_zone) = self.head_center();
+            if let Some(dir) = self.drag.motion(head, Vec2::new(x, y), dead_zone) {
+                self.turns[0].push(&self.sim, dir);
+            }
+        }
+        Ok(())
+    }
+
+    /// A character was typed: it's part of the name of the player, if it's being entered.
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> GameResult {
+        if let Some(Overlay::NameEntry(name)) = &mut self.overlay {
+            if !character.is_control() && name.chars().count() < MAX_NAME_LEN {
+                name.push(character);
+            }
+        }
+        Ok(())
+    }
+
+    /// The window is closing: don't lose the game in progress.
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        if !matches!(self.mode, Mode::Replay(_)) && self.recording.ticks > 0 {
+            match self.sim.state() {
+                GameState::Running | GameState::Paused | GameState::Quitting => {
+                    self.save_recording()
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl MyGame {
+    /// A key or a gamepad button was pressed.
+    fn press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        if self.overlay.is_some() {
+            return self.overlay_press_event(press);
+        }
+
+        match self.mode {
+            Mode::Replay(_) => return self.playback_press_event(ctx, press),
+            Mode::Peer(_) => return self.peer_press_event(ctx, press),
+            Mode::Watch(_) => return self.view_press_event(ctx, press),
+            Mode::Play(_) | Mode::Versus(_) | Mode::Lan(_) => {}
+        }
+
+        if self.sim.state() == GameState::Starting {
+            if press.is(Action::HighScores) {
+                self.overlay = Some(Overlay::HighScores(None));
+                return Ok(());
+            }
+            if press.is(Action::Continue) {
+                self.continue_game();
+                return Ok(());
+            }
+            if press.is(Action::Bindings) {
+                self.overlay = Some(Overlay::Bindings(BindingsScreen {
+                    selected: 0,
+                    waiting: false,
+                    conflict: None,
+                }));
+                return Ok(());
+            }
+        }
+
+        // Over the network the host starts the rounds, and the others only turn
+        if let Mode::Lan(net) = &self.mode {
+            match (net.is_host(), self.sim.state()) {
+                (false, _) => return self.join_press_event(ctx, press),
+                (true, GameState::Starting | GameState::GameOver) => {
+                    return self.host_press_event(ctx, press);
+                }
+                _ => {}
+            }
+        }
+
+        // Queue the turns: every press counts, even several between two ticks
+        if self.sim.state() == GameState::Running {
+            if let Some(dir) = press.turn() {
+                self.turns[0].push(&self.sim, dir);
+                return Ok(());
+            }
+        }
+
+        if self.campaign().is_some() {
+            match self.sim.state() {
+                GameState::Starting | GameState::LevelComplete | GameState::Victory => {
+                    return self.campaign_press_event(ctx, press);
+                }
+                _ => {}
+            }
+        }
+
+        match self.sim.state() {
+            GameState::Starting => match press {
+                p if p.is(Action::Quit) => ctx.request_quit(),
                 _ => self.restart(),
             },
-            GameState::GameOver => match input.keycode.unwrap() {
-                KeyCode::Q | KeyCode::Escape | KeyCode::N => ctx.request_quit(),
-                KeyCode::Y => {
+            GameState::GameOver | GameState::LevelComplete | GameState::Victory => match press {
+                p if p.is(Action::Cancel) || p.is(Action::Quit) => ctx.request_quit(),
+                p if p.is(Action::Confirm) => {
                     self.restart();
                 }
                 _ => {}
             },
-            GameState::Quitting => match input.keycode.unwrap() {
-                KeyCode::Q | KeyCode::Escape | KeyCode::Y => ctx.request_quit(),
-                KeyCode::N => {
-                    self.state = GameState::Running;
+            GameState::Quitting => match press {
+                p if p.is(Action::Confirm) || p.is(Action::Quit) => ctx.request_quit(),
+                p if p.is(Action::SaveAndQuit) && self.save_game() => ctx.request_quit(),
+                p if p.is(Action::Cancel) => {
+                    self.sim.set_state(GameState::Running);
                 }
                 _ => {}
             },
-            _ => match input.keycode.unwrap() {
-                KeyCode::G => self.show_grid = !self.show_grid,
-                KeyCode::F => self.show_fps = !self.show_fps,
-                KeyCode::P | KeyCode::Space => {
-                    self.state = match self.state {
-                        GameState::Running => GameState::Paused,
-                        GameState::Paused => GameState::Running,
-                        _ => self.state,
-                    }
+            _ => match press {
+                p if p.is(Action::ToggleGrid) => self.show_grid = !self.show_grid,
+                p if p.is(Action::ToggleFps) => self.show_fps = !self.show_fps,
+                p if p.is(Action::Pause) => match self.sim.state() {
+                    GameState::Running => self.sim.set_state(GameState::Paused),
+                    GameState::Paused => self.sim.set_state(GameState::Running),
+                    _ => {}
+                },
+                p if p.is(Action::Quit) => self.sim.set_state(GameState::Quitting),
+                // The autopilot drives the player's snake, in the games alone or against bots
+                p if p.is(Action::Assist)
+                    && matches!(&self.mode, Mode::Play(play) if play.agent.is_none()) =>
+                {
+                    self.assist = !self.assist;
+                    self.assisted |= self.assist;
+                    self.turns[0].clear();
                 }
-                KeyCode::Q | KeyCode::Escape => self.state = GameState::Quitting,
                 _ => {}
             },
         };
 
         Ok(())
     }
+
+    /// A key or a button was pressed while watching a game, or in a game
+    /// played by another process: only the view changes.
+    fn view_press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        match press {
+            p if p.is(Action::Quit) => ctx.request_quit(),
+            p if p.is(Action::ToggleGrid) => self.show_grid = !self.show_grid,
+            p if p.is(Action::ToggleFps) => self.show_fps = !self.show_fps,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// A key or a button was pressed while an overlay is shown.
+    fn overlay_press_event(&mut self, press: &Press) -> GameResult {
+        // A gamepad confirms or skips the name as it is
+        let gamepad = press.key.is_none();
+        match &mut self.overlay {
+            Some(Overlay::NameEntry(name)) => match press.key {
+                Some(KeyCode::Return | KeyCode::NumpadEnter) => {
+                    let name = name.to_string();
+                    self.enter_name(&name);
+                }
+                None if press.is(Action::Confirm) => {
+                    let name = name.to_string();
+                    self.enter_name(&name);
+                }
+                Some(KeyCode::Back) => {
+                    name.pop();
+                }
+                Some(KeyCode::Escape) => self.overlay = None,
+                None if press.is(Action::Cancel) => self.overlay = None,
+                _ => {}
+            },
+            Some(Overlay::HighScores(_)) => self.overlay = None,
+            Some(Overlay::Bindings(_)) => self.bindings_press_event(press, gamepad),
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Enter the name typed for a new high score, "Player" if there is none
+    fn enter_name(&mut self, name: &str) {
+        let name = match name.trim() {
+            "" => "Player",
+            name => name,
+        };
+        self.enter_highscore(name);
+    }
+
+    /// A key or a button was pressed on the controls screen.
+    /// The arrows, Return and Escape always work there, not to get stuck with bad bindings.
+    fn bindings_press_event(&mut self, press: &Press, gamepad: bool) {
+        let Some(Overlay::Bindings(screen)) = &mut self.overlay else {
+            return;
+        };
+        let action = Action::ALL[screen.selected];
+        let actions = Action::ALL.len();
+
+        // Any key can be bound, even the ones of this screen; a button gives up
+        if screen.waiting {
+            screen.waiting = false;
+            if let Some(key) = press.key {
+                screen.conflict = self.controls.bind(action, key).err();
+            }
+            return;
+        }
+
+        screen.conflict = None;
+        match press.key {
+            Some(KeyCode::Key1 | KeyCode::Numpad1) => {
+                self.controls = Controls::preset(Preset::Arrows)
+            }
+            Some(KeyCode::Key2 | KeyCode::Numpad2) => {
+                self.controls = Controls::preset(Preset::Wasd)
+            }
+            Some(KeyCode::Key3 | KeyCode::Numpad3) => self.controls = Controls::preset(Preset::Vim),
+            Some(KeyCode::Up) => screen.selected = (screen.selected + actions - 1) % actions,
+            Some(KeyCode::Down) => screen.selected = (screen.selected + 1) % actions,
+            _ if press.is(Action::TurnUp) => {
+                screen.selected = (screen.selected + actions - 1) % actions
+            }
+            _ if press.is(Action::TurnDown) => screen.selected = (screen.selected + 1) % actions,
+            Some(KeyCode::Return | KeyCode::NumpadEnter) => screen.waiting = true,
+            Some(KeyCode::Escape) => {
+                self.overlay = None;
+                self.save_controls();
+            }
+            _ if press.is(Action::Bindings) || (gamepad && press.is(Action::Cancel)) => {
+                self.overlay = None;
+                self.save_controls();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Play a game without a window, with the agent or else the autopilot
+/// driving the player's snake, then print how it went
+fn play_headless(args: &Args, setup: Setup) {
+    let Setup {
+        settings,
+        rules,
+        bots,
+        mut agent,
+        ..
+    } = setup;
+    let mut autopilot = settings.strategy().unwrap_or_default().autopilot();
+    let pilot: &mut dyn Autopilot = match &mut agent {
+        Some(agent) => agent,
+        None => autopilot.as_mut(),
+    };
+
+    let seed = args.seed.unwrap_or_else(random_seed);
+    let mut sim = Simulation::new(rules.clone(), seed);
+    sim.restart(seed);
+    let mut recording = Replay::new(seed, rules);
+    let max_ticks = args.max_ticks.unwrap_or(HEADLESS_MAX_TICKS);
+
+    while sim.state() == GameState::Running && sim.tick() < max_ticks {
+        let mut inputs = vec![None; sim.snakes().len()];
+        inputs[0] = pilot.choose(&sim, 0);
+        for (i, bot) in bots.iter().enumerate() {
+            inputs[i + 1] = bot.choose(&sim, i + 1);
+        }
+        recording.record_all(sim.tick(), &inputs);
+
+        // Against the bots the game is over when the player dies
+        let events = sim.step_all(&inputs);
+        if events
+            .iter()
+            .any(|e| matches!(e, Event::Died { snake: 0, .. }))
+        {
+            sim.set_state(GameState::GameOver);
+        }
+    }
+    pilot.end(&sim, 0);
+
+    if let Some(path) = &args.record {
+        if let Err(e) = recording.save(path) {
+            eprintln!("Could not save the replay to {}: {}", path.display(), e);
+        }
+    }
+
+    let end = match (sim.state(), sim.death()) {
+        (GameState::Victory, _) => "Victory".to_string(),
+        (GameState::LevelComplete, _) => "Level complete".to_string(),
+        (_, Some(death)) => death.to_string(),
+        (GameState::GameOver, None) => "The bots are out".to_string(),
+        _ => format!("Still alive after {} ticks", max_ticks),
+    };
+    println!("Seed: {}", sim.seed());
+    println!("Ticks: {}", sim.tick());
+    println!("Score: {}", sim.score());
+    println!("Length: {}", sim.length());
+    println!("End: {}", end);
+    if let Some(e) = agent.as_ref().and_then(|a| a.0.error()) {
+        println!("Agent: {}", e);
+    }
+}
+
+/// The command line arguments
+#[derive(Default)]
+struct Args {
+    /// Play with this seed instead of a random one
+    seed: Option<u64>,
+    /// The settings file, if not the default one
+    config: Option<PathBuf>,
+    /// The board, the cells and the bots, if not as the settings say
+    overrides: Overrides,
+    /// The level to play, if not the one of the settings
+    level: Option<String>,
+    /// Play the campaign
+    campaign: bool,
+    /// Save the replay of the game to this file
+    record: Option<PathBuf>,
+    /// Watch the replay in this file instead of playing
+    replay: Option<PathBuf>,
+    /// Play a match between two players on one keyboard
+    versus: bool,
+    /// The command running the program that drives the player's snake
+    agent: Option<String>,
+    /// How long the agent may think about a move, if not `DEFAULT_TIMEOUT`
+    agent_timeout: Option<Duration>,
+    /// Play without a window and print the result
+    headless: bool,
+    /// The longest game without a window, if not `HEADLESS_MAX_TICKS`
+    max_ticks: Option<u64>,
+    /// Host a game over the network on this address or port
+    host: Option<String>,
+    /// Join the game hosted at this address
+    join: Option<String>,
+    /// The name of the player over the network, if not the last one entered
+    name: Option<String>,
+    /// Play the match against the machine at this address, peer to peer
+    peer: Option<String>,
+    /// The local port of the match peer to peer, if not `DEFAULT_PORT`
+    port: Option<u16>,
+    /// Broadcast the game to the spectators on this address or port
+    broadcast: Option<String>,
+    /// Watch the game broadcast at this address instead of playing
+    watch: Option<String>,
+}
+
+impl Args {
+    const USAGE: &'static str =
+        "Usage: snake-ggez [--config <file>] [--seed <number>] [--grid <width>x<height> | --level <name> | --campaign] [--cell-size <pixels>] [--record <file>] [--versus | --bots <count> [--difficulty <name>]] [--agent <command> [--agent-timeout <ms>]] [--broadcast [<address>:]<port>]\n       snake-ggez [--config <file>] --replay <file> [--cell-size <pixels>] [--broadcast [<address>:]<port>]\n       snake-ggez [--config <file>] --headless [--seed <number>] [--grid <width>x<height> | --level <name>] [--record <file>] [--bots <count> [--difficulty <name>]] [--agent <command> [--agent-timeout <ms>]] [--max-ticks <count>]\n       snake-ggez [--config <file>] --host [<address>:]<port> [--seed <number>] [--grid <width>x<height> | --level <name>] [--cell-size <pixels>] [--name <name>] [--broadcast [<address>:]<port>]\n       snake-ggez [--config <file>] --join <address>[:<port>] [--cell-size <pixels>] [--name <name>]\n       snake-ggez [--config <file>] --versus --peer <address>[:<port>] [--port <port>] [--seed <number>] [--grid <width>x<height> | --level <name>] [--cell-size <pixels>] [--broadcast [<address>:]<port>]\n       snake-ggez [--config <file>] --watch <address>[:<port>] [--cell-size <pixels>]";
+
+    /// Parse the command line arguments
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let value = iter.next().ok_or("--config needs a file")?;
+                    args.config = Some(PathBuf::from(value));
+                }
+                "--seed" => {
+                    let value = iter.next().ok_or("--seed needs a value")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed '{}'", value))?;
+                    args.seed = Some(seed);
+                }
+                "--grid" => {
+                    let value = iter.next().ok_or("--grid needs a size")?;
+                    args.overrides.board = Some(value.parse()?);
+                }
+                "--level" => {
+                    let value = iter.next().ok_or("--level needs a name or a file")?;
+                    args.level = Some(value);
+                }
+                "--campaign" => args.campaign = true,
+                "--versus" => args.versus = true,
+                "--bots" => {
+                    let value = iter.next().ok_or("--bots needs a count")?;
+                    let count = value
+                        .parse()
+                        .map_err(|_| format!("Invalid bot count '{}'", value))?;
+                    args.overrides.bots = Some(count);
+                }
+                "--difficulty" => {
+                    let value = iter.next().ok_or("--difficulty needs a name")?;
+                    args.overrides.difficulty = Some(value);
+                }
+                "--cell-size" => {
+                    let value = iter.next().ok_or("--cell-size needs a value")?;
+                    let size = value
+                        .parse()
+                        .ok()
+                        .filter(|s| *s > 0)
+                        .ok_or_else(|| format!("Invalid cell size '{}'", value))?;
+                    args.overrides.cell_size = Some(size);
+                }
+                "--record" => {
+                    let value = iter.next().ok_or("--record needs a file")?;
+                    args.record = Some(PathBuf::from(value));
+                }
+                "--replay" => {
+                    let value = iter.next().ok_or("--replay needs a file")?;
+                    args.replay = Some(PathBuf::from(value));
+                }
+                "--agent" => {
+                    let value = iter.next().ok_or("--agent needs a command")?;
+                    args.agent = Some(value);
+                }
+                "--agent-timeout" => {
+                    let value = iter.next().ok_or("--agent-timeout needs a value")?;
+                    let ms = value
+                        .parse()
+                        .ok()
+                        .filter(|ms| *ms > 0)
+                        .ok_or_else(|| format!("Invalid agent timeout '{}'", value))?;
+                    args.agent_timeout = Some(Duration::from_millis(ms));
+                }
+                "--headless" => args.headless = true,
+                "--max-ticks" => {
+                    let value = iter.next().ok_or("--max-ticks needs a count")?;
+                    let ticks = value
+                        .parse()
+                        .ok()
+                        .filter(|t| *t > 0)
+                        .ok_or_else(|| format!("Invalid tick count '{}'", value))?;
+                    args.max_ticks = Some(ticks);
+                }
+                "--host" => {
+                    let value = iter.next().ok_or("--host needs a port or an address")?;
+                    args.host = Some(value);
+                }
+                "--join" => {
+                    let value = iter.next().ok_or("--join needs an address")?;
+                    args.join = Some(value);
+                }
+                "--name" => {
+                    let value = iter.next().ok_or("--name needs a name")?;
+                    args.name = Some(value);
+                }
+                "--peer" => {
+                    let value = iter.next().ok_or("--peer needs an address")?;
+                    args.peer = Some(value);
+                }
+                "--port" => {
+                    let value = iter.next().ok_or("--port needs a number")?;
+                    let port = value
+                        .parse()
+                        .ok()
+                        .filter(|p| *p > 0)
+                        .ok_or_else(|| format!("Invalid port '{}'", value))?;
+                    args.port = Some(port);
+                }
+                "--broadcast" => {
+                    let value = iter
+                        .next()
+                        .ok_or("--broadcast needs a port or an address")?;
+                    args.broadcast = Some(value);
+                }
+                "--watch" => {
+                    let value = iter.next().ok_or("--watch needs an address")?;
+                    args.watch = Some(value);
+                }
+                "-h" | "--help" => {
+                    println!("{}", Args::USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+
+        let custom_game = args.seed.is_some()
+            || args.overrides.board.is_some()
+            || args.level.is_some()
+            || args.campaign
+            || args.record.is_some()
+            || args.versus
+            || args.overrides.bots.is_some()
+            || args.agent.is_some();
+        if args.replay.is_some() && custom_game {
+            return Err(
+                "--replay can't be used with --seed, --grid, --level, --campaign, --record, --versus, --bots or --agent"
+                    .to_string(),
+            );
+        }
+        if args.agent.is_some() && args.versus {
+            return Err("--agent can't be used with --versus".to_string());
+        }
+        if args.agent_timeout.is_some() && args.agent.is_none() {
+            return Err("--agent-timeout needs --agent".to_string());
+        }
+        if args.headless && (args.replay.is_some() || args.campaign || args.versus) {
+            return Err(
+                "--headless can't be used with --replay, --campaign or --versus".to_string(),
+            );
+        }
+        if args.max_ticks.is_some() && !args.headless {
+            return Err("--max-ticks needs --headless".to_string());
+        }
+        if args.versus && args.campaign {
+            return Err("--versus can't be used with --campaign".to_string());
+        }
+        if args.overrides.bots.is_some() && (args.campaign || args.versus) {
+            return Err("--bots can't be used with --campaign or --versus".to_string());
+        }
+        let boards = [
+            args.overrides.board.is_some(),
+            args.level.is_some(),
+            args.campaign,
+        ];
+        if boards.iter().filter(|b| **b).count() > 1 {
+            return Err("--grid, --level and --campaign can't be used together".to_string());
+        }
+        if args.host.is_some() && args.join.is_some() {
+            return Err("--host and --join can't be used together".to_string());
+        }
+        let solo = args.replay.is_some()
+            || args.campaign
+            || args.record.is_some()
+            || args.versus
+            || args.overrides.bots.is_some()
+            || args.agent.is_some()
+            || args.headless;
+        if args.online() && solo {
+            return Err(
+                "--host and --join can't be used with --replay, --campaign, --record, --versus, --bots, --agent or --headless"
+                    .to_string(),
+            );
+        }
+        // The host chooses the game
+        if args.join.is_some()
+            && (args.seed.is_some() || args.overrides.board.is_some() || args.level.is_some())
+        {
+            return Err("--join can't be used with --seed, --grid or --level".to_string());
+        }
+        if args.name.is_some() && !args.online() {
+            return Err("--name needs --host or --join".to_string());
+        }
+        if args.peer.is_some() && !args.versus {
+            return Err("--peer needs --versus".to_string());
+        }
+        // The turns of the other machine come late, when the rounds were already played
+        if args.peer.is_some() && args.record.is_some() {
+            return Err("--peer can't be used with --record".to_string());
+        }
+        if args.port.is_some() && args.peer.is_none() {
+            return Err("--port needs --peer".to_string());
+        }
+        if args.broadcast.is_some() && (args.join.is_some() || args.headless) {
+            return Err("--broadcast can't be used with --join or --headless".to_string());
+        }
+        let game = custom_game
+            || args.replay.is_some()
+            || args.headless
+            || args.online()
+            || args.broadcast.is_some();
+        if args.watch.is_some() && game {
+            return Err("--watch can only be used with --config and --cell-size".to_string());
+        }
+
+        Ok(args)
+    }
+
+    /// Play over the network
+    fn online(&self) -> bool {
+        self.host.is_some() || self.join.is_some()
+    }
 }
 
 /// The main function
 fn main() -> GameResult {
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, Args::USAGE);
+        std::process::exit(2);
+    });
+
+    let setup = Setup::new(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    if args.headless {
+        play_headless(&args, setup);
+        return Ok(());
+    }
+
     let (mut ctx, event_loop) = ContextBuilder::new("snake-ggez", "author")
         .window_setup(WindowSetup::default().title("Snake ggez"))
-        .window_mode(WindowMode::default().dimensions(WINDOW_WIDTH, WINDOW_HEIGHT))
+        .window_mode(WindowMode::default().dimensions(setup.layout.width, setup.layout.height))
         .build()
         .expect("Could not create ggez context!");
 
-    let my_game = MyGame::new(&mut ctx)?;
+    let my_game = MyGame::new(&mut ctx, &args, setup)?;
 
     event::run(ctx, event_loop, my_game)
 }
@@ -0,0 +1,257 @@
+//! A game over the network: the host starts the rounds from the lobby,
+//! the players who joined only turn their snakes.
+
+use super::Mode;
+use crate::MyGame;
+use crate::COLOR_GRAY;
+use crate::SNAKE_COLORS;
+use ggez::graphics::Canvas;
+use ggez::graphics::Color;
+use ggez::graphics::DrawParam;
+use ggez::graphics::PxScale;
+use ggez::graphics::Text;
+use ggez::Context;
+use ggez::GameResult;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Press;
+use snake_ggez::net::LanEvent;
+use snake_ggez::net::NetError;
+use snake_ggez::net::ServerEvent;
+use snake_ggez::simulation::random_seed;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Snake;
+use snake_ggez::turns::TurnQueue;
+use std::time::Duration;
+
+impl MyGame {
+    /// Take the players and their turns, or the game of the host
+    pub(crate) fn update_net(&mut self, ctx: &mut Context) {
+        let Mode::Lan(net) = &mut self.mode else {
+            return;
+        };
+        let events = match net.poll(&mut self.sim, random_seed()) {
+            Ok(events) => events,
+            Err(NetError::Invalid(e)) => {
+                eprintln!("Invalid game from the host: {}", e);
+                ctx.request_quit();
+                return;
+            }
+            Err(e) => {
+                eprintln!("Left the game: {}", e);
+                ctx.request_quit();
+                return;
+            }
+        };
+        for event in events {
+            match event {
+                LanEvent::Lobby(ServerEvent::Left(name, Some(e))) => {
+                    eprintln!("{} left: {}", name, e);
+                }
+                LanEvent::NewGame => {
+                    self.fit_board(ctx, self.sim.board());
+                    self.head_timer = Duration::from_millis(0);
+                }
+                LanEvent::Lobby(_) => {}
+            }
+        }
+    }
+
+    /// Start a round with the players in the lobby, when there are two at least
+    fn start_round(&mut self) {
+        let seed = self.fixed_seed.unwrap_or_else(random_seed);
+        let Mode::Lan(net) = &mut self.mode else {
+            return;
+        };
+        let Some(sim) = net.start_round(seed) else {
+            return;
+        };
+        self.turns = vec![TurnQueue::new(); sim.snakes().len()];
+        self.sim = sim;
+        self.head_timer = Duration::from_millis(0);
+    }
+
+    /// Go back to the lobby after a round, with everyone who joined
+    fn open_lobby(&mut self) {
+        let Mode::Lan(net) = &mut self.mode else {
+            return;
+        };
+        let Some(sim) = net.open_lobby(random_seed()) else {
+            return;
+        };
+        self.sim = sim;
+        self.head_timer = Duration::from_millis(0);
+    }
+
+    /// The game shown is played by another process
+    pub(crate) fn joined(&self) -> bool {
+        matches!(&self.mode, Mode::Lan(net) if !net.is_host())
+    }
+
+    /// A key or a button was pressed by the host, in the lobby or after a round.
+    pub(crate) fn host_press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        let over = self.sim.state() == GameState::GameOver;
+        match press {
+            p if p.is(Action::Quit) || (over && p.is(Action::Cancel)) => ctx.request_quit(),
+            p if p.is(Action::Confirm) && over => self.open_lobby(),
+            p if p.is(Action::Confirm) => self.start_round(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// A key or a button was pressed in a game joined over the network.
+    pub(crate) fn join_press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        if let (Some(dir), Mode::Lan(net)) = (press.turn(), &mut self.mode) {
+            match net.input(&self.sim, dir) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("{}", e);
+                    ctx.request_quit();
+                    return Ok(());
+                }
+            }
+        }
+        self.view_press_event(ctx, press)
+    }
+
+    /// Draw the players waiting for a game over the network
+    pub(crate) fn draw_lobby(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        // Draw the demo
+        self.draw_board(ctx, canvas)?;
+        let Mode::Lan(net) = &self.mode else {
+            return Ok(());
+        };
+
+        // Create a new text
+        let mut text1 = Text::new("LOBBY");
+        let players: Vec<String> = net
+            .players()
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                if i == net.you() {
+                    format!("{}. {} (you)", i + 1, name)
+                } else {
+                    format!("{}. {}", i + 1, name)
+                }
+            })
+            .collect();
+        let mut text2 = Text::new(players.join("\n"));
+        let mut text3 = if !net.is_host() {
+            match net.players().first() {
+                Some(host) => Text::new(format!("Waiting for {} to start", host)),
+                None => Text::new("Joining..."),
+            }
+        } else if net.players().len() < 2 {
+            let port = net.local_addr().map_or(0, |addr| addr.port());
+            Text::new(format!("Waiting for players on port {}", port))
+        } else {
+            Text::new(format!(
+                "Press {} to start, {} to quit",
+                self.controls.key_name(Action::Confirm),
+                self.controls.key_name(Action::Quit)
+            ))
+        };
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 8.0).min(100.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y - m2.y) / 2.0;
+        let y2 = y1 + m1.y;
+        let y3 = y2 + m2.y + m3.y;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::GREEN);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(Color::YELLOW);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        Ok(())
+    }
+
+    /// Draw the end of a round over the network
+    pub(crate) fn draw_net_over(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
+        let Mode::Lan(net) = &self.mode else {
+            return Ok(());
+        };
+
+        // Create a new text
+        let mut text1 = match net.last_winner() {
+            Some(winner) if winner == net.you() => Text::new("YOU WIN!"),
+            Some(winner) => Text::new(format!("{} WINS!", self.snake_name(winner).to_uppercase())),
+            None => Text::new("DRAW"),
+        };
+        let mut text2 = if net.is_host() {
+            Text::new(format!("Back to the lobby? {}", self.yes_no()))
+        } else {
+            Text::new(format!(
+                "Waiting for {} - {} to quit",
+                self.snake_name(0),
+                self.controls.key_name(Action::Quit)
+            ))
+        };
+        let mut text3 = match self.sim.snakes().get(net.you()).and_then(Snake::death) {
+            Some(death) => Text::new(death.to_string()),
+            None => Text::new(""),
+        };
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 12.0).min(60.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y) / 2.0;
+        let y2 = (self.layout.height - m2.y + m1.y) / 2.0;
+        let y3 = y2 + m2.y * 1.5;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params, in the color of the winner
+        let color = match net.last_winner() {
+            Some(winner) => SNAKE_COLORS[winner % SNAKE_COLORS.len()],
+            None => Color::YELLOW,
+        };
+        let params = DrawParam::default().dest(coords1).color(color);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(COLOR_GRAY);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+//! The modes of the game: who drives the snakes, and what the screens ask.
+//!
+//! Each mode has its own module, with its state and the methods of `MyGame`
+//! that update it, answer the presses and draw its screens. `main.rs` keeps
+//! what all the modes share: the board, the overlays and the ticks.
+
+mod lan;
+mod peer;
+mod play;
+mod replay;
+mod versus;
+mod watch;
+
+pub(crate) use play::CampaignRun;
+pub(crate) use play::Play;
+pub(crate) use replay::Playback;
+pub(crate) use versus::Match;
+pub(crate) use versus::ROUNDS_TO_WIN;
+
+use snake_ggez::broadcast::Spectator;
+use snake_ggez::lockstep::PeerMatch;
+use snake_ggez::net::LanGame;
+
+/// Who drives the snakes
+pub(crate) enum Mode {
+    /// The player, alone, against the bots or in the campaign
+    Play(Play),
+    /// A replay, with the inputs of a game played before
+    Replay(Playback),
+    /// Two players on one keyboard
+    Versus(Match),
+    /// The players of a game over the network, hosted or joined
+    Lan(LanGame),
+    /// The player and another machine, in a match played in lockstep.
+    /// Boxed: its session keeps the match twice, confirmed and predicted.
+    Peer(Box<PeerMatch>),
+    /// Another process, whose game is broadcast and watched
+    Watch(Spectator),
+}
@@ -0,0 +1,50 @@
+//! A match against another machine, played in lockstep.
+
+use super::Mode;
+use crate::MyGame;
+use ggez::Context;
+use ggez::GameResult;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Press;
+use snake_ggez::lockstep::LockstepError;
+
+impl MyGame {
+    /// Play the match against the other machine, and show it as this side predicts it
+    pub(crate) fn update_peer(&mut self, ctx: &mut Context) {
+        let Mode::Peer(peer) = &mut self.mode else {
+            return;
+        };
+        match peer.tick(ctx.time.delta()) {
+            Ok(()) => {}
+            Err(LockstepError::Desync(tick)) => {
+                eprintln!("The games went out of sync at tick {}", tick);
+                ctx.request_quit();
+                return;
+            }
+            Err(e) => {
+                eprintln!("Lost the other player: {}", e);
+                ctx.request_quit();
+                return;
+            }
+        }
+        self.sim = peer.game().sim().clone();
+    }
+
+    /// A key or a button was pressed in a match against another machine.
+    pub(crate) fn peer_press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        if let (Mode::Peer(peer), Some(dir)) = (&mut self.mode, press.turn()) {
+            if peer.input(dir) {
+                return Ok(());
+            }
+        }
+
+        let over = matches!(&self.mode, Mode::Peer(peer) if peer.game().winner().is_some());
+        match press {
+            p if p.is(Action::Quit) || (over && p.is(Action::Cancel)) => ctx.request_quit(),
+            p if p.is(Action::ToggleGrid) => self.show_grid = !self.show_grid,
+            p if p.is(Action::ToggleFps) => self.show_fps = !self.show_fps,
+            _ => {}
+        }
+        Ok(())
+    }
+}
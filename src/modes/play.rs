@@ -0,0 +1,258 @@
+//! The games of the player: alone, against the bots or in the campaign,
+//! maybe with an agent driving the snake.
+
+use super::Mode;
+use crate::data_dir;
+use crate::load_or_set_aside;
+use crate::MyGame;
+use ggez::Context;
+use ggez::GameResult;
+use snake_ggez::agent::AgentPilot;
+use snake_ggez::agent::Subprocess;
+use snake_ggez::bot::Bot;
+use snake_ggez::campaign::Campaign;
+use snake_ggez::campaign::Progress;
+use snake_ggez::campaign::CAMPAIGN_FILE;
+use snake_ggez::campaign::PROGRESS_FILE;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Press;
+use snake_ggez::level::find_resource;
+use snake_ggez::savegame::SaveGame;
+use snake_ggez::simulation::random_seed;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A game of the player
+pub(crate) struct Play {
+    /// The campaign being played, if any
+    pub(crate) campaign: Option<CampaignRun>,
+    /// The bots driving the snakes after the player's, in order
+    pub(crate) bots: Vec<Bot>,
+    /// The program driving the player's snake instead of the keys, if any
+    pub(crate) agent: Option<AgentPilot<Subprocess>>,
+}
+
+/// The campaign being played
+pub(crate) struct CampaignRun {
+    pub(crate) campaign: Campaign,
+    pub(crate) progress: Progress,
+    /// Where the progress is saved, if there is a data directory
+    progress_path: Option<PathBuf>,
+    /// The index of the stage being played
+    pub(crate) stage: usize,
+    /// The points for each fruit, from the settings
+    fruit_points: u32,
+}
+
+impl CampaignRun {
+    /// Load the campaign of the levels directory and the progress of the player
+    pub(crate) fn load(fruit_points: u32) -> Result<CampaignRun, String> {
+        let file = find_resource(&Path::new("levels").join(CAMPAIGN_FILE))
+            .ok_or_else(|| format!("Campaign file '{}' not found", CAMPAIGN_FILE))?;
+        let dir = file.parent().unwrap_or(Path::new("."));
+        let campaign = Campaign::load(dir).map_err(|e| e.to_string())?;
+
+        let progress_path = data_dir().map(|dir| dir.join(PROGRESS_FILE));
+        let progress = match &progress_path {
+            Some(path) => load_or_set_aside(path, "the progress in the campaign", Progress::load),
+            None => Progress::default(),
+        };
+
+        // Start from the last unlocked stage
+        let stage = progress.completed.min(campaign.stages.len() - 1);
+
+        Ok(CampaignRun {
+            campaign,
+            progress,
+            progress_path,
+            stage,
+            fruit_points,
+        })
+    }
+
+    /// The rules of the current stage
+    pub(crate) fn rules(&self) -> Rules {
+        Rules {
+            fruit_points: self.fruit_points,
+            ..self.campaign.stages[self.stage].level.rules.clone()
+        }
+    }
+
+    /// The largest board of all the stages, for the size of the window
+    pub(crate) fn largest_board(&self) -> Board {
+        let boards = self.campaign.stages.iter().map(|s| s.level.rules.board);
+        Board {
+            width: boards.clone().map(|b| b.width).max().unwrap_or_default(),
+            height: boards.map(|b| b.height).max().unwrap_or_default(),
+        }
+    }
+
+    /// Record the score of a game of the current stage, and if it was completed
+    pub(crate) fn record(&mut self, score: u32, completed: bool) {
+        let id = &self.campaign.stages[self.stage].id;
+        self.progress.record_score(id, score);
+        if completed {
+            self.progress.complete(self.stage);
+        }
+        if let Some(path) = &self.progress_path {
+            if let Err(e) = self.progress.save(path) {
+                eprintln!(
+                    "Could not save the campaign progress to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+impl MyGame {
+    /// The campaign being played, if any
+    pub(crate) fn campaign(&self) -> Option<&CampaignRun> {
+        match &self.mode {
+            Mode::Play(play) => play.campaign.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn campaign_mut(&mut self) -> Option<&mut CampaignRun> {
+        match &mut self.mode {
+            Mode::Play(play) => play.campaign.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// The bots driving the snakes after the player's, in order
+    pub(crate) fn bots(&self) -> &[Bot] {
+        match &self.mode {
+            Mode::Play(play) => &play.bots,
+            _ => &[],
+        }
+    }
+
+    /// Go to a stage of the campaign, in the attract mode
+    fn select_stage(&mut self, stage: usize) {
+        let Some(campaign) = self.campaign_mut() else {
+            return;
+        };
+        campaign.stage = stage;
+        let rules = campaign.rules();
+        self.layout = self.layout.resized(rules.board);
+        self.sim = Simulation::new(rules, self.fixed_seed.unwrap_or_else(random_seed));
+        self.head_timer = Duration::from_millis(0);
+    }
+
+    /// The saved game, if it can be continued: it must have been played
+    /// on the same level, or in the campaign if the campaign is played.
+    pub(crate) fn saved_game(&self) -> Option<&SaveGame> {
+        let Mode::Play(play) = &self.mode else {
+            return None;
+        };
+        if !self.alone() {
+            return None;
+        }
+        let save = self.savegame.as_ref()?;
+        let level = match (&play.campaign, save.stage) {
+            (Some(campaign), Some(stage)) => campaign.campaign.stages.get(stage).map(|s| &s.id),
+            (None, None) => self.level_id.as_ref(),
+            _ => return None,
+        };
+        (level == save.level.as_ref()).then_some(save)
+    }
+
+    /// Save the game in progress, to continue it the next time. Return `false` on failure.
+    pub(crate) fn save_game(&self) -> bool {
+        // Only the games alone are saved
+        if !self.alone() {
+            return false;
+        }
+        let Some(path) = &self.savegame_path else {
+            eprintln!("Could not save the game: no user data directory");
+            return false;
+        };
+
+        // The game goes on when continued, even if saved from the quit screen
+        let mut game = self.sim.snapshot();
+        game.state = GameState::Running;
+
+        let save = SaveGame {
+            turns: self.turns[0].to_vec(),
+            head_timer: self.head_timer,
+            level: self.level().map(str::to_string),
+            stage: self.campaign().map(|c| c.stage),
+            recording: self.recording.clone(),
+            game,
+            assisted: self.assisted,
+        };
+        match save.save(path) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Could not save the game to {}: {}", path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// Continue the saved game where it was left, and forget it
+    pub(crate) fn continue_game(&mut self) {
+        if self.saved_game().is_none() {
+            return;
+        }
+        let Some(save) = self.savegame.take() else {
+            return;
+        };
+
+        if let (Some(campaign), Some(stage)) = (self.campaign_mut(), save.stage) {
+            campaign.stage = stage;
+        }
+        self.sim = Simulation::from_snapshot(save.game).expect("Invalid saved game");
+        self.layout = self.layout.resized(self.sim.board());
+        self.turns = vec![save.turns.into()];
+        self.head_timer = save.head_timer;
+        self.recording = save.recording;
+        self.assisted = save.assisted;
+        self.assist = false;
+
+        // Continue a game only once
+        if let Some(path) = &self.savegame_path {
+            if let Err(e) = std::fs::remove_file(path) {
+                eprintln!("Could not remove {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// A key or a button was pressed on the screens of the campaign:
+    /// choosing a stage, or going to the next one.
+    pub(crate) fn campaign_press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        let Some(campaign) = self.campaign() else {
+            return Ok(());
+        };
+        let stage = campaign.stage;
+        let stages = campaign.campaign.stages.len();
+        let unlocked = |s: usize| campaign.progress.is_unlocked(s);
+
+        if press.is(Action::Quit) {
+            ctx.request_quit();
+        } else if self.sim.state() != GameState::Starting {
+            // The next stage, or back to the first one at the end of the campaign
+            self.select_stage((stage + 1) % stages);
+        } else if press.is(Action::TurnLeft) {
+            if stage > 0 {
+                self.select_stage(stage - 1);
+            }
+        } else if press.is(Action::TurnRight) {
+            if stage + 1 < stages && unlocked(stage + 1) {
+                self.select_stage(stage + 1);
+            }
+        } else {
+            self.restart();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Watching a replay, at any speed, a tick at a time when paused.
+
+use super::Mode;
+use crate::MyGame;
+use crate::HUD_MARGIN_X;
+use crate::HUD_MARGIN_Y;
+use ggez::graphics::Canvas;
+use ggez::graphics::Color;
+use ggez::graphics::DrawParam;
+use ggez::graphics::PxScale;
+use ggez::graphics::Text;
+use ggez::Context;
+use ggez::GameResult;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Press;
+use snake_ggez::replay::Replay;
+use snake_ggez::replay::ReplayPlayer;
+use snake_ggez::simulation::GameState;
+
+/// The fastest fast-forward of a replay, in ticks per move time
+const MAX_REPLAY_SPEED: u32 = 16;
+
+/// The state of a replay being watched
+pub(crate) struct Playback {
+    /// Where the inputs come from
+    pub(crate) player: ReplayPlayer,
+    /// Number of ticks played every move time
+    pub(crate) speed: u32,
+}
+
+impl Playback {
+    /// Watch a replay from its start, at the normal speed
+    pub(crate) fn new(replay: Replay) -> Playback {
+        Playback {
+            player: ReplayPlayer::new(replay),
+            speed: 1,
+        }
+    }
+}
+
+impl MyGame {
+    /// Draw the replay speed, or that the replay is paused
+    pub(crate) fn draw_playback(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut Canvas,
+        playback: &Playback,
+    ) -> GameResult {
+        // Create a new text
+        let s = if self.sim.state() == GameState::Paused {
+            "REPLAY paused".to_string()
+        } else {
+            format!("REPLAY x{}", playback.speed)
+        };
+        let mut text = Text::new(s);
+
+        // Set font size
+        text.set_scale(PxScale::from(20.0));
+
+        // Set text position to the top-right of the screen
+        let m = text.measure(ctx)?;
+        let coords = [self.layout.width - m.x - HUD_MARGIN_X, HUD_MARGIN_Y];
+
+        // Set params
+        let params = DrawParam::default().dest(coords).color(Color::YELLOW);
+
+        // Draw the text
+        canvas.draw(&text, params);
+
+        Ok(())
+    }
+
+    /// A key or a button was pressed while watching a replay.
+    pub(crate) fn playback_press_event(&mut self, ctx: &mut Context, press: &Press) -> GameResult {
+        match self.sim.state() {
+            GameState::GameOver | GameState::LevelComplete | GameState::Victory => match press {
+                p if p.is(Action::Cancel) || p.is(Action::Quit) => ctx.request_quit(),
+                p if p.is(Action::Confirm) => self.restart(),
+                _ => {}
+            },
+            _ => match press {
+                p if p.is(Action::ToggleGrid) => self.show_grid = !self.show_grid,
+                p if p.is(Action::ToggleFps) => self.show_fps = !self.show_fps,
+                p if p.is(Action::Pause) => match self.sim.state() {
+                    GameState::Running => self.sim.set_state(GameState::Paused),
+                    GameState::Paused => self.sim.set_state(GameState::Running),
+                    _ => {}
+                },
+                // Fast-forward
+                p if p.is(Action::TurnUp) => {
+                    if let Mode::Replay(playback) = &mut self.mode {
+                        playback.speed = (playback.speed * 2).min(MAX_REPLAY_SPEED);
+                    }
+                }
+                p if p.is(Action::TurnDown) => {
+                    if let Mode::Replay(playback) = &mut self.mode {
+                        playback.speed = (playback.speed / 2).max(1);
+                    }
+                }
+                // Step a single tick while paused
+                p if p.is(Action::TurnRight) && self.sim.state() == GameState::Paused => {
+                    self.sim.set_state(GameState::Running);
+                    self.tick();
+                    if self.sim.state() == GameState::Running {
+                        self.sim.set_state(GameState::Paused);
+                    }
+                }
+                p if p.is(Action::Quit) => ctx.request_quit(),
+                _ => {}
+            },
+        };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! A match on one keyboard, the first to win `ROUNDS_TO_WIN` rounds.
+
+use super::Mode;
+use crate::MyGame;
+use crate::COLOR_GRAY;
+use crate::SNAKE_COLORS;
+use ggez::graphics::Canvas;
+use ggez::graphics::Color;
+use ggez::graphics::DrawParam;
+use ggez::graphics::PxScale;
+use ggez::graphics::Text;
+use ggez::input::keyboard::KeyCode;
+use ggez::Context;
+use ggez::GameResult;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Controls;
+use snake_ggez::controls::Preset;
+use snake_ggez::simulation::GameState;
+
+/// The first player to win this many rounds wins the match
+pub(crate) const ROUNDS_TO_WIN: u32 = 3;
+
+/// A match on one keyboard, between players each with their own snake
+pub(crate) struct Match {
+    /// The turn keys of each player: the arrows, then WASD
+    controls: Vec<Controls>,
+    /// The rounds won by each player
+    wins: Vec<u32>,
+    /// The winner of the last round, `None` for a draw
+    last_winner: Option<usize>,
+}
+
+impl Match {
+    pub(crate) fn new() -> Match {
+        Match {
+            controls: vec![
+                Controls::preset(Preset::Arrows),
+                Controls::preset(Preset::Wasd),
+            ],
+            wins: vec![0; 2],
+            last_winner: None,
+        }
+    }
+
+    /// The winner of the match, once a player won enough rounds
+    pub(crate) fn winner(&self) -> Option<usize> {
+        self.wins.iter().position(|w| *w >= ROUNDS_TO_WIN)
+    }
+
+    /// Count the round won by a player, if it's not a draw
+    pub(crate) fn record(&mut self, winner: Option<usize>) {
+        if let Some(winner) = winner {
+            self.wins[winner] += 1;
+        }
+        self.last_winner = winner;
+    }
+}
+
+impl MyGame {
+    /// Turn the snake of the player whose key it is. Return `false` if it's
+    /// not a turn key, or if the snakes aren't running.
+    pub(crate) fn versus_key_event(&mut self, key: KeyCode) -> bool {
+        let Mode::Versus(versus) = &self.mode else {
+            return false;
+        };
+        if self.sim.state() != GameState::Running || self.overlay.is_some() {
+            return false;
+        }
+        for (i, controls) in versus.controls.iter().enumerate() {
+            if let Some(dir) = controls.turn(key) {
+                self.turns[i].push_for(&self.sim.snakes()[i], dir);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Draw the end of a round of a match, and of the match
+    pub(crate) fn draw_round_over(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
+
+        // Against another machine the session counts the rounds, and the next one starts by itself
+        let (rounds, rounds_to_win, last_winner, you) = match &self.mode {
+            Mode::Versus(versus) => (
+                versus.wins.as_slice(),
+                ROUNDS_TO_WIN,
+                versus.last_winner,
+                None,
+            ),
+            Mode::Peer(peer) => (
+                peer.game().wins(),
+                peer.session().proposal().rounds_to_win,
+                peer.game().last_winner(),
+                Some(peer.local()),
+            ),
+            _ => return Ok(()),
+        };
+        let winner = rounds.iter().position(|w| *w >= rounds_to_win);
+        let wins = |winner: usize| match you {
+            Some(you) if you == winner => "YOU WIN".to_string(),
+            _ => format!("PLAYER {} WINS", winner + 1),
+        };
+
+        // Create a new text
+        let (mut text1, mut text2) = match (winner, last_winner) {
+            (Some(winner), _) => (
+                Text::new(format!("{}!", wins(winner))),
+                match you {
+                    Some(_) => {
+                        Text::new(format!("{} to quit", self.controls.key_name(Action::Quit)))
+                    }
+                    None => Text::new(format!("Do you want to play again? {}", self.yes_no())),
+                },
+            ),
+            (None, winner) => (
+                match winner {
+                    Some(winner) => Text::new(format!("{} THE ROUND", wins(winner))),
+                    None => Text::new("DRAW"),
+                },
+                match you {
+                    Some(_) => Text::new("Next round in a moment"),
+                    None => Text::new(format!("Next round? {}", self.yes_no())),
+                },
+            ),
+        };
+        let wins: Vec<String> = rounds.iter().map(u32::to_string).collect();
+        let mut text3 = Text::new(format!(
+            "Rounds: {} - first to {}",
+            wins.join(" - "),
+            rounds_to_win
+        ));
+
+        // Set font size
+        text1.set_scale(PxScale::from((self.layout.width / 12.0).min(60.0)));
+        text2.set_scale(PxScale::from(20.0));
+        text3.set_scale(PxScale::from(20.0));
+
+        // Set text position to the center of the screen
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let m3 = text3.measure(ctx)?;
+
+        let y1 = (self.layout.height - m1.y) / 2.0;
+        let y2 = (self.layout.height - m2.y + m1.y) / 2.0;
+        let y3 = y2 + m2.y * 1.5;
+        let coords1 = [(self.layout.width - m1.x) / 2.0, y1];
+        let coords2 = [(self.layout.width - m2.x) / 2.0, y2];
+        let coords3 = [(self.layout.width - m3.x) / 2.0, y3];
+
+        // Set params, in the color of the winner
+        let color = match last_winner {
+            Some(winner) if winner > 0 => SNAKE_COLORS[winner % SNAKE_COLORS.len()],
+            Some(_) => Color::GREEN,
+            None => Color::YELLOW,
+        };
+        let params = DrawParam::default().dest(coords1).color(color);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords2).color(Color::WHITE);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        // Set params
+        let params = DrawParam::default().dest(coords3).color(COLOR_GRAY);
+        // Draw the text
+        canvas.draw(&text3, params);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! Watching a game broadcast by another process.
+
+use super::Mode;
+use crate::MyGame;
+use crate::HUD_MARGIN_X;
+use crate::HUD_MARGIN_Y;
+use crate::SNAKE_COLORS;
+use ggez::graphics::Canvas;
+use ggez::graphics::Color;
+use ggez::graphics::DrawParam;
+use ggez::graphics::PxScale;
+use ggez::graphics::Text;
+use ggez::Context;
+use ggez::GameResult;
+use snake_ggez::net::NetError;
+use snake_ggez::simulation::GameState;
+
+impl MyGame {
+    /// Follow the game watched, on its board
+    pub(crate) fn update_spectator(&mut self, ctx: &mut Context) {
+        let Mode::Watch(spectator) = &mut self.mode else {
+            return;
+        };
+        match spectator.poll() {
+            Ok(true) => {
+                if let Some(game) = spectator.game() {
+                    let game = game.clone();
+                    self.fit_board(ctx, game.board());
+                    self.sim = game;
+                }
+            }
+            Ok(false) => {}
+            Err(NetError::Closed) => {
+                eprintln!("The broadcast is over");
+                ctx.request_quit();
+            }
+            Err(e) => {
+                eprintln!("Lost the broadcast: {}", e);
+                ctx.request_quit();
+            }
+        }
+    }
+
+    /// Draw the game watched, with its tick and how it's going
+    pub(crate) fn draw_watching(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
+        self.draw_running(ctx, canvas)?;
+
+        // Create a new text
+        let mut text1 = Text::new(format!("WATCHING  Tick {}", self.sim.tick()));
+        // With several snakes, the last one alive wins
+        let snakes = self.sim.snakes();
+        let alive: Vec<usize> = (0..snakes.len())
+            .filter(|i| snakes[*i].is_alive())
+            .collect();
+        let winner = match alive[..] {
+            [winner] if snakes.len() > 1 => Some(winner),
+            _ => None,
+        };
+        let mut text2 = match self.sim.state() {
+            GameState::Starting => Text::new("Waiting for the next game"),
+            GameState::Paused | GameState::Quitting => Text::new("PAUSED"),
+            GameState::GameOver if snakes.len() > 1 => match winner {
+                Some(winner) => {
+                    Text::new(format!("{} WINS!", self.snake_name(winner).to_uppercase()))
+                }
+                None => Text::new("DRAW"),
+            },
+            GameState::GameOver => Text::new("GAME OVER"),
+            GameState::LevelComplete => Text::new("LEVEL COMPLETE"),
+            GameState::Victory => Text::new("VICTORY!"),
+            GameState::Running => Text::new(""),
+        };
+
+        // Set font size
+        text1.set_scale(PxScale::from(20.0));
+        text2.set_scale(PxScale::from((self.layout.width / 12.0).min(60.0)));
+
+        // Set text position to the top-right of the screen, and to the center
+        let m1 = text1.measure(ctx)?;
+        let m2 = text2.measure(ctx)?;
+        let coords1 = [self.layout.width - m1.x - HUD_MARGIN_X, HUD_MARGIN_Y];
+        let coords2 = [
+            (self.layout.width - m2.x) / 2.0,
+            (self.layout.height - m2.y) / 2.0,
+        ];
+
+        // Set params
+        let params = DrawParam::default().dest(coords1).color(Color::YELLOW);
+        // Draw the text
+        canvas.draw(&text1, params);
+
+        // Set params, in the color of the winner
+        let color = match (self.sim.state(), winner) {
+            (GameState::GameOver, Some(winner)) => SNAKE_COLORS[winner % SNAKE_COLORS.len()],
+            _ => Color::WHITE,
+        };
+        let params = DrawParam::default().dest(coords2).color(color);
+        // Draw the text
+        canvas.draw(&text2, params);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,782 @@
+//! Games over the network.
+//!
+//! One process hosts the game with a `Server`: it runs the only simulation,
+//! and the other players join it with a `Client`. The host is player 0; the
+//! others get the next snakes in the order they joined the lobby.
+//!
+//! Both sides talk JSON over TCP, one message per line. A client says hello,
+//! then sends the turns of its snake; the server sends the lobby until the
+//! host starts the game, then the rules once and a `Frame` at every tick:
+//!
+//! ```text
+//! > {"type":"hello","protocol":1,"name":"Ada"}
+//! < {"type":"lobby","players":["Host","Ada"],"you":1}
+//! < {"type":"start","rules":{...},"players":["Host","Ada"],"you":1}
+//! < {"type":"frame","tick":0,"state":"Running","snakes":[...],"fruits":[...]}
+//! > {"type":"turn","dir":"Up"}
+//! < {"type":"round-over","winner":1}
+//! ```
+//!
+//! The sockets don't block: the game polls them once per frame, so neither
+//! side needs threads.
+//!
+//! A `LanGame` is the game as a front end plays it, on either side: the
+//! lobby, the rounds and the names of the players.
+
+use crate::bot::bot_starts;
+use crate::bot::MAX_BOTS;
+use crate::highscores::clean_name;
+use crate::simulation::Direction;
+use crate::simulation::Frame;
+use crate::simulation::GameState;
+use crate::simulation::Rules;
+use crate::simulation::Simulation;
+use crate::turns::TurnQueue;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+/// The port of a game when the address doesn't have one
+pub const DEFAULT_PORT: u16 = 7357;
+
+/// The version of the messages; a client of another version is turned away
+pub const PROTOCOL: u32 = 1;
+
+/// The host and the players who joined
+pub const MAX_PLAYERS: usize = MAX_BOTS + 1;
+
+/// How long a client tries to reach the host
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The longest message, to not fill the memory with a line that never ends
+const MAX_LINE: usize = 1 << 20;
+
+/// A message from a client to the host
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ClientMessage {
+    /// The first message, to join the lobby
+    Hello { protocol: u32, name: String },
+    /// Turn the snake of the player at one of the next ticks
+    Turn { dir: Direction },
+}
+
+/// A message from the host to a client
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ServerMessage {
+    /// The players waiting for the game, the host first, sent when it changes
+    /// and when the host goes back to the lobby after a round
+    Lobby { players: Vec<String>, you: usize },
+    /// A round starts, with the names of the players by snake, and the snake
+    /// of the client
+    Start {
+        rules: Rules,
+        players: Vec<String>,
+        you: usize,
+    },
+    /// The game after a tick, or after a pause
+    Frame(Frame),
+    /// The round is over; the winner is the index of a snake
+    RoundOver { winner: Option<usize> },
+    /// The host turned the client away, and closes the connection
+    Rejected { reason: String },
+}
+
+/// Everything that can go wrong with a connection
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    /// The other side closed the connection
+    Closed,
+    /// A line is not a message
+    Parse(String, serde_json::Error),
+    /// The host turned the client away
+    Rejected(String),
+    /// A message makes no sense, such as a game that doesn't fit its rules
+    Invalid(String),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "{}", e),
+            NetError::Closed => write!(f, "the connection was closed"),
+            NetError::Parse(line, e) => write!(f, "'{}' is not a message: {}", line, e),
+            NetError::Rejected(reason) => write!(f, "the host refused: {}", reason),
+            NetError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    /// A connection reset by the other side is closed, like any other
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => NetError::Closed,
+            _ => NetError::Io(e),
+        }
+    }
+}
+
+/// An address with `DEFAULT_PORT` if it has no port, and `host` if it's only
+/// a port: `7000` is `<host>:7000`, `example.org` is `example.org:7357`
+pub fn with_port(address: &str, host: &str) -> String {
+    with_default_port(address, host, DEFAULT_PORT)
+}
+
+/// Like `with_port`, with `port` if the address has no port
+pub fn with_default_port(address: &str, host: &str, port: u16) -> String {
+    if !address.is_empty() && address.chars().all(|c| c.is_ascii_digit()) {
+        format!("{}:{}", host, address)
+    } else if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, port)
+    }
+}
+
+/// A socket that doesn't block, sending and receiving lines of JSON
+pub(crate) struct Connection {
+    stream: TcpStream,
+    /// What was received after the last full line
+    input: Vec<u8>,
+    /// What the socket couldn't take yet
+    output: Vec<u8>,
+}
+
+impl Connection {
+    pub(crate) fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            input: Vec::new(),
+            output: Vec::new(),
+        })
+    }
+
+    /// Connect to the first address that answers within `CONNECT_TIMEOUT`
+    pub(crate) fn connect(address: impl ToSocketAddrs) -> Result<Connection, NetError> {
+        let mut error = io::Error::new(io::ErrorKind::NotFound, "no address to join");
+        for addr in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => return Ok(Connection::new(stream)?),
+                Err(e) => error = e,
+            }
+        }
+        Err(error.into())
+    }
+
+    /// The bytes queued that the socket didn't take yet
+    pub(crate) fn backlog(&self) -> usize {
+        self.output.len()
+    }
+
+    /// Queue a message, and send what the socket takes
+    pub(crate) fn send<T: Serialize>(&mut self, message: &T) -> Result<(), NetError> {
+        serde_json::to_writer(&mut self.output, message).map_err(io::Error::other)?;
+        self.output.push(b'\n');
+        self.flush()
+    }
+
+    /// Send what was queued, as far as the socket takes it
+    fn flush(&mut self) -> Result<(), NetError> {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return Err(NetError::Closed),
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// The messages received since the last call, without waiting
+    pub(crate) fn receive<T: DeserializeOwned>(&mut self) -> Result<Vec<T>, NetError> {
+        self.flush()?;
+        let mut closed = false;
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(n) => self.input.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(end) = self.input.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let message =
+                serde_json::from_str(line).map_err(|e| NetError::Parse(line.to_string(), e))?;
+            messages.push(message);
+        }
+        if self.input.len() > MAX_LINE {
+            let e = io::Error::new(io::ErrorKind::InvalidData, "the line is too long");
+            return Err(e.into());
+        }
+        // The messages sent before closing still count
+        if closed && messages.is_empty() {
+            return Err(NetError::Closed);
+        }
+        Ok(messages)
+    }
+}
+
+/// Something that happened in the lobby
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerEvent {
+    /// A player joined, with their name
+    Joined(String),
+    /// A player left, or their connection failed
+    Left(String, Option<String>),
+}
+
+/// A player who joined
+struct Peer {
+    conn: Connection,
+    name: String,
+    /// The snake of the player in the current round, if they play it
+    snake: Option<usize>,
+    /// The turns received since the last tick
+    turns: Vec<Direction>,
+}
+
+/// The host of a game: it lets players join, takes their turns and sends
+/// them the game. The host runs the simulation itself, as player 0.
+pub struct Server {
+    listener: TcpListener,
+    /// The name of the host
+    name: String,
+    /// The players who joined, in order
+    peers: Vec<Peer>,
+    /// The connections that didn't say hello yet
+    pending: Vec<Connection>,
+}
+
+impl Server {
+    /// Listen for players on an address, such as `0.0.0.0:7357`
+    pub fn bind(address: impl ToSocketAddrs, name: &str) -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let name = match clean_name(name) {
+            name if name.is_empty() => "Host".to_string(),
+            name => name,
+        };
+        Ok(Server {
+            listener,
+            name,
+            peers: Vec::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// The address the players join, with the port chosen by the system if
+    /// bound to port 0
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The names of the players in the lobby, the host first
+    pub fn players(&self) -> Vec<String> {
+        let mut players = vec![self.name.clone()];
+        players.extend(self.peers.iter().map(|peer| peer.name.clone()));
+        players
+    }
+
+    /// Take the new players and the messages of the others, without waiting
+    pub fn poll(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(conn) = Connection::new(stream) {
+                self.pending.push(conn);
+            }
+        }
+
+        for mut conn in std::mem::take(&mut self.pending) {
+            let hello = match conn.receive::<ClientMessage>() {
+                Ok(messages) => messages.into_iter().next(),
+                Err(_) => continue,
+            };
+            let Some(hello) = hello else {
+                self.pending.push(conn);
+                continue;
+            };
+            match self.welcome(hello) {
+                Ok(name) => {
+                    events.push(ServerEvent::Joined(name.clone()));
+                    self.peers.push(Peer {
+                        conn,
+                        name,
+                        snake: None,
+                        turns: Vec::new(),
+                    });
+                }
+                Err(reason) => {
+                    let _ = conn.send(&ServerMessage::Rejected { reason });
+                }
+            }
+        }
+
+        self.peers.retain_mut(|peer| match peer.conn.receive() {
+            Ok(messages) => {
+                for message in messages {
+                    if let ClientMessage::Turn { dir } = message {
+                        peer.turns.push(dir);
+                    }
+                }
+                true
+            }
+            Err(e) => {
+                let reason = match e {
+                    NetError::Closed => None,
+                    e => Some(e.to_string()),
+                };
+                events.push(ServerEvent::Left(peer.name.clone(), reason));
+                false
+            }
+        });
+
+        if !events.is_empty() {
+            self.send_lobby();
+        }
+        events
+    }
+
+    /// The name of a new player, or why they can't join
+    fn welcome(&self, hello: ClientMessage) -> Result<String, String> {
+        let ClientMessage::Hello { protocol, name } = hello else {
+            return Err("say hello first".to_string());
+        };
+        if protocol != PROTOCOL {
+            return Err(format!(
+                "the host speaks version {}, not {}",
+                PROTOCOL, protocol
+            ));
+        }
+        if self.peers.len() + 1 >= MAX_PLAYERS {
+            return Err(format!("the game is full, {} players", MAX_PLAYERS));
+        }
+        let name = clean_name(&name);
+        if name.is_empty() {
+            Ok(format!("P{}", self.peers.len() + 2))
+        } else {
+            Ok(name)
+        }
+    }
+
+    /// Send everyone back to the lobby, after a round
+    pub fn lobby(&mut self) {
+        for peer in self.peers.iter_mut() {
+            peer.snake = None;
+        }
+        self.send_lobby();
+    }
+
+    /// Tell the players out of the round who is in the lobby
+    fn send_lobby(&mut self) {
+        let players = self.players();
+        for (i, peer) in self.peers.iter_mut().enumerate() {
+            if peer.snake.is_some() {
+                continue;
+            }
+            let lobby = ServerMessage::Lobby {
+                players: players.clone(),
+                you: i + 1,
+            };
+            let _ = peer.conn.send(&lobby);
+        }
+    }
+
+    /// Start a round with the players in the lobby: the rules have a start for
+    /// the host and for each of them, in order. Return the names of the
+    /// players of the round.
+    pub fn start(&mut self, rules: &Rules) -> Vec<String> {
+        let mut players = self.players();
+        players.truncate(rules.starts().len());
+        for (i, peer) in self.peers.iter_mut().enumerate() {
+            let snake = i + 1;
+            peer.snake = Some(snake).filter(|snake| *snake < players.len());
+            peer.turns.clear();
+            if let Some(you) = peer.snake {
+                let start = ServerMessage::Start {
+                    rules: rules.clone(),
+                    players: players.clone(),
+                    you,
+                };
+                let _ = peer.conn.send(&start);
+            }
+        }
+        players
+    }
+
+    /// The turns of the player of a snake since the last call, in order
+    pub fn turns(&mut self, snake: usize) -> Vec<Direction> {
+        self.peers
+            .iter_mut()
+            .find(|peer| peer.snake == Some(snake))
+            .map(|peer| std::mem::take(&mut peer.turns))
+            .unwrap_or_default()
+    }
+
+    /// Send the game to the players of the round
+    pub fn send_frame(&mut self, frame: &Frame) {
+        self.broadcast(&ServerMessage::Frame(frame.clone()));
+    }
+
+    /// Tell the players of the round who won it
+    pub fn round_over(&mut self, winner: Option<usize>) {
+        self.broadcast(&ServerMessage::RoundOver { winner });
+    }
+
+    /// Send a message to the players of the round. A connection that fails
+    /// is dropped at the next poll.
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for peer in self.peers.iter_mut().filter(|peer| peer.snake.is_some()) {
+            let _ = peer.conn.send(message);
+        }
+    }
+}
+
+/// A player of a game hosted by another process
+pub struct Client {
+    conn: Connection,
+}
+
+impl Client {
+    /// Join the game at an address, such as `192.168.1.20:7357`
+    pub fn connect(address: impl ToSocketAddrs, name: &str) -> Result<Client, NetError> {
+        let mut client = Client {
+            conn: Connection::connect(address)?,
+        };
+        client.send(&ClientMessage::Hello {
+            protocol: PROTOCOL,
+            name: name.to_string(),
+        })?;
+        Ok(client)
+    }
+
+    /// The messages of the host since the last call, without waiting. A
+    /// round with rules that can't be played is an error.
+    pub fn poll(&mut self) -> Result<Vec<ServerMessage>, NetError> {
+        let messages = self.conn.receive()?;
+        for message in messages.iter() {
+            match message {
+                ServerMessage::Rejected { reason } => {
+                    return Err(NetError::Rejected(reason.clone()));
+                }
+                ServerMessage::Start { rules, you, .. } => {
+                    rules.validate().map_err(NetError::Invalid)?;
+                    if *you >= rules.starts().len() {
+                        return Err(NetError::Invalid(format!("there is no snake {}", you)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Turn the snake of the player
+    pub fn send_turn(&mut self, dir: Direction) -> Result<(), NetError> {
+        self.send(&ClientMessage::Turn { dir })
+    }
+
+    fn send(&mut self, message: &ClientMessage) -> Result<(), NetError> {
+        self.conn.send(message)
+    }
+}
+
+/// Which side of the network game this process is on
+enum Side {
+    /// This process runs the game, and the others join it
+    Host {
+        server: Server,
+        /// The rules of the rounds, before the starts of the players
+        rules: Rules,
+        /// The tick and the state of the last frame sent, not to send it twice
+        sent: Option<(u64, GameState)>,
+    },
+    /// Another process runs the game: this one shows its frames and sends the turns
+    Join {
+        client: Client,
+        /// A round is played: the game shown is the one of the host
+        playing: bool,
+    },
+}
+
+/// What changed in a game over the network
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LanEvent {
+    /// A player joined or left the lobby of the host
+    Lobby(ServerEvent),
+    /// The game shown was replaced, at the start of a round or back in the
+    /// lobby: its board may have another size
+    NewGame,
+}
+
+/// A game over the network, hosted by this process or joined. The front end
+/// ticks the game of the host, with the turns of the players who joined, and
+/// shows the game of a player who joined as the host sends it.
+pub struct LanGame {
+    side: Side,
+    /// The names of the players, the host first: the ones in the lobby, then
+    /// the ones of the round
+    players: Vec<String>,
+    /// The snake of this player
+    you: usize,
+    /// The winner of the last round, `None` for a draw
+    last_winner: Option<usize>,
+}
+
+impl LanGame {
+    /// Host a game: the rules of the rounds get a start for each player
+    pub fn host(server: Server, rules: Rules) -> LanGame {
+        LanGame::new(Side::Host {
+            server,
+            rules,
+            sent: None,
+        })
+    }
+
+    /// Play the game of another process
+    pub fn join(client: Client) -> LanGame {
+        LanGame::new(Side::Join {
+            client,
+            playing: false,
+        })
+    }
+
+    fn new(side: Side) -> LanGame {
+        LanGame {
+            side,
+            players: Vec::new(),
+            you: 0,
+            last_winner: None,
+        }
+    }
+
+    /// This process runs the game
+    pub fn is_host(&self) -> bool {
+        matches!(self.side, Side::Host { .. })
+    }
+
+    /// The game shown is played by the host, not ticked by this process
+    pub fn following(&self) -> bool {
+        matches!(self.side, Side::Join { playing: true, .. })
+    }
+
+    /// The address the players join, on the host
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.side {
+            Side::Host { server, .. } => server.local_addr().ok(),
+            Side::Join { .. } => None,
+        }
+    }
+
+    /// The names of the players, the host first: the ones in the lobby, then
+    /// the ones of the round
+    pub fn players(&self) -> &[String] {
+        &self.players
+    }
+
+    /// The snake of this player
+    pub fn you(&self) -> usize {
+        self.you
+    }
+
+    /// The winner of the last round, `None` for a draw
+    pub fn last_winner(&self) -> Option<usize> {
+        self.last_winner
+    }
+
+    /// Take the players and their turns, or the game of the host, without
+    /// waiting. `sim` is the game shown; back in the lobby, a player who
+    /// joined gets a demo on the board of the host, with `seed`.
+    pub fn poll(&mut self, sim: &mut Simulation, seed: u64) -> Result<Vec<LanEvent>, NetError> {
+        let messages = match &mut self.side {
+            Side::Host { server, .. } => {
+                let events = server.poll();
+                if sim.state() == GameState::Starting {
+                    self.players = server.players();
+                }
+                return Ok(events.into_iter().map(LanEvent::Lobby).collect());
+            }
+            Side::Join { client, .. } => client.poll()?,
+        };
+        let mut events = Vec::new();
+        for message in messages {
+            if self.receive(sim, seed, message)? {
+                events.push(LanEvent::NewGame);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Follow the game of the host. Return whether the game shown was replaced.
+    fn receive(
+        &mut self,
+        sim: &mut Simulation,
+        seed: u64,
+        message: ServerMessage,
+    ) -> Result<bool, NetError> {
+        let Side::Join { playing, .. } = &mut self.side else {
+            return Ok(false);
+        };
+        match message {
+            ServerMessage::Lobby { players, you } => {
+                self.players = players;
+                self.you = you;
+                // Back to the lobby, with the demo on the board of the host
+                if *playing {
+                    *playing = false;
+                    let mut rules = sim.rules().clone();
+                    rules.rivals.clear();
+                    *sim = Simulation::new(rules, seed);
+                    return Ok(true);
+                }
+            }
+            ServerMessage::Start {
+                rules,
+                players,
+                you,
+            } => {
+                self.players = players;
+                self.you = you;
+                self.last_winner = None;
+                *playing = true;
+                *sim = Simulation::new(rules, 0);
+                sim.restart(0);
+                return Ok(true);
+            }
+            ServerMessage::Frame(frame) if *playing => {
+                sim.apply_frame(frame).map_err(NetError::Invalid)?;
+            }
+            ServerMessage::RoundOver { winner } => self.last_winner = winner,
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Give the turns of the players who joined to their snakes, before a
+    /// tick of the host
+    pub fn tick(&mut self, sim: &Simulation, turns: &mut [TurnQueue]) {
+        let Side::Host { server, .. } = &mut self.side else {
+            return;
+        };
+        if sim.state() != GameState::Running {
+            return;
+        }
+        for (i, queue) in turns.iter_mut().enumerate().skip(1) {
+            for dir in server.turns(i) {
+                if let Some(snake) = sim.snakes().get(i) {
+                    queue.push_for(snake, dir);
+                }
+            }
+        }
+    }
+
+    /// Turn the snake of a player who joined, during a round. Return whether
+    /// the turn was sent.
+    pub fn input(&mut self, sim: &Simulation, dir: Direction) -> Result<bool, NetError> {
+        match &mut self.side {
+            Side::Join { client, .. } if sim.state() == GameState::Running => {
+                client.send_turn(dir)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Show the players the end of the round, then who won it
+    pub fn round_over(&mut self, sim: &Simulation, winner: Option<usize>) {
+        self.send_frame(sim);
+        if let Side::Host { server, .. } = &mut self.side {
+            self.last_winner = winner;
+            server.round_over(winner);
+        }
+    }
+
+    /// Send the game to the players who joined, if it changed
+    pub fn send_frame(&mut self, sim: &Simulation) {
+        let Side::Host { server, sent, .. } = &mut self.side else {
+            return;
+        };
+        let state = sim.state();
+        if state == GameState::Starting || *sent == Some((sim.tick(), state)) {
+            return;
+        }
+        let mut frame = sim.frame();
+        // The players see the quit screen of the host as a pause
+        if frame.state == GameState::Quitting {
+            frame.state = GameState::Paused;
+        }
+        server.send_frame(&frame);
+        *sent = Some((sim.tick(), state));
+    }
+
+    /// Start a round with the players in the lobby, when there are two at
+    /// least. Return the game of the round, started with `seed`.
+    pub fn start_round(&mut self, seed: u64) -> Option<Simulation> {
+        let Side::Host {
+            server,
+            rules,
+            sent,
+        } = &mut self.side
+        else {
+            return None;
+        };
+        let count = server.players().len().min(MAX_PLAYERS);
+        if count < 2 {
+            return None;
+        }
+
+        let mut rules = rules.clone();
+        rules.rivals = bot_starts(&rules, count - 1);
+        self.players = server.start(&rules);
+        self.last_winner = None;
+        *sent = None;
+
+        let mut sim = Simulation::new(rules, seed);
+        sim.restart(seed);
+        Some(sim)
+    }
+
+    /// Go back to the lobby after a round, with everyone who joined. Return
+    /// the demo to show in the lobby, with `seed`.
+    pub fn open_lobby(&mut self, seed: u64) -> Option<Simulation> {
+        let Side::Host { server, rules, .. } = &mut self.side else {
+            return None;
+        };
+        server.lobby();
+        Some(Simulation::new(rules.clone(), seed))
+    }
+}
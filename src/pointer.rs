@@ -0,0 +1,75 @@
+//! Steering with a mouse or a touch screen.
+//!
+//! A click turns the snake toward the clicked point: the board is split in
+//! four quadrants by the diagonals through the center of the head, and the
+//! quadrant of the point is the direction. Dragging keeps steering, the snake
+//! turns each time the pointer moves to another quadrant.
+
+use crate::simulation::Direction;
+use ggez::glam::Vec2;
+
+/// The direction of a point seen from the head, or `None` if the point is
+/// within `dead_zone` of the head. The y axis points down, as on the screen.
+pub fn direction_to(head: Vec2, point: Vec2, dead_zone: f32) -> Option<Direction> {
+    let d = point - head;
+    if d.x.abs().max(d.y.abs()) < dead_zone {
+        None
+    } else if d.x.abs() > d.y.abs() {
+        Some(if d.x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        })
+    } else {
+        Some(if d.y > 0.0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        })
+    }
+}
+
+/// The state of the pointer, which turns its events into turns
+#[derive(Clone, Debug, Default)]
+pub struct Drag {
+    /// The button is down, or the screen touched
+    pressed: bool,
+    /// The last direction given by the drag
+    dir: Option<Direction>,
+}
+
+impl Drag {
+    pub fn new() -> Self {
+        Drag::default()
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// The pointer was pressed: turn toward it
+    pub fn press(&mut self, head: Vec2, point: Vec2, dead_zone: f32) -> Option<Direction> {
+        self.pressed = true;
+        self.dir = direction_to(head, point, dead_zone);
+        self.dir
+    }
+
+    /// The pointer moved: turn if it's dragged to another quadrant
+    pub fn motion(&mut self, head: Vec2, point: Vec2, dead_zone: f32) -> Option<Direction> {
+        if !self.pressed {
+            return None;
+        }
+        let dir = direction_to(head, point, dead_zone);
+        if dir.is_none() || dir == self.dir {
+            return None;
+        }
+        self.dir = dir;
+        dir
+    }
+
+    /// The pointer was released
+    pub fn release(&mut self) {
+        self.pressed = false;
+        self.dir = None;
+    }
+}
@@ -0,0 +1,428 @@
+//! Recording and playback of games.
+//!
+//! A game only depends on its rules, its seed and on the turns made by the snakes,
+//! so a replay stores just those: the seed, the rules (with the walls and the
+//! fruit spawns of the level, and the starts of the other snakes, if any) and,
+//! for every tick where a snake turned, the tick number and the new direction.
+//! The turns of the other snakes end with the index of the snake.
+//!
+//! The file is plain text, one record per line:
+//!
+//! ```text
+//! snake-replay 6
+//! seed 1234
+//! grid 25 20
+//! boundary wrap
+//! points 10
+//! start 4 4 1 R
+//! rival 20 15 L
+//! fruits separate
+//! wall 10 10
+//! fruit 2 3
+//! goal length 10
+//! turn 3 U
+//! turn 9 L
+//! turn 4 D 1
+//! end 57
+//! ```
+//!
+//! The first line holds the format version: a file written by another version
+//! is rejected instead of being played back with different rules.
+
+use crate::simulation::Board;
+use crate::simulation::Direction;
+use crate::simulation::Fruits;
+use crate::simulation::GridPosition;
+use crate::simulation::Rules;
+use crate::simulation::Start;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The version of the replay format written by this build
+pub const REPLAY_VERSION: u32 = 6;
+
+/// The first word of every replay file
+const MAGIC: &str = "snake-replay";
+
+/// Everything that can go wrong reading or writing a replay
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// The file was written with an unsupported version of the format
+    Version(u32),
+    /// The file is not a valid replay
+    Parse {
+        line: usize,
+        msg: String,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Version(v) => write!(
+                f,
+                "replay format version {} is not supported (expected {})",
+                v, REPLAY_VERSION
+            ),
+            ReplayError::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+/// A recorded game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    /// The seed the game was started with
+    pub seed: u64,
+    /// The rules the game was played with
+    pub rules: Rules,
+    /// The ticks where the player turned, in increasing order
+    pub turns: Vec<(u64, Direction)>,
+    /// The ticks where the other snakes turned, with the index of the snake in
+    /// `Simulation::snakes`, in increasing order
+    pub rival_turns: Vec<(u64, usize, Direction)>,
+    /// The number of ticks played
+    pub ticks: u64,
+}
+
+impl Replay {
+    /// Start recording a new game
+    pub fn new(seed: u64, rules: Rules) -> Self {
+        Replay {
+            seed,
+            rules,
+            turns: Vec::new(),
+            rival_turns: Vec::new(),
+            ticks: 0,
+        }
+    }
+
+    /// Record the input given to `Simulation::step` at the given tick
+    pub fn record(&mut self, tick: u64, input: Option<Direction>) {
+        if let Some(dir) = input {
+            self.turns.push((tick, dir));
+        }
+        self.ticks = tick + 1;
+    }
+
+    /// Record the inputs given to `Simulation::step_all` at the given tick
+    pub fn record_all(&mut self, tick: u64, inputs: &[Option<Direction>]) {
+        self.record(tick, inputs.first().copied().flatten());
+        for (snake, input) in inputs.iter().enumerate().skip(1) {
+            if let Some(dir) = input {
+                self.rival_turns.push((tick, snake, *dir));
+            }
+        }
+    }
+
+    /// Load a replay from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Save the replay to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", MAGIC, REPLAY_VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        let rules = &self.rules;
+        writeln!(f, "grid {} {}", rules.board.width, rules.board.height)?;
+        writeln!(f, "boundary {}", rules.boundary)?;
+        writeln!(f, "points {}", rules.fruit_points)?;
+        let start = rules.start_pos;
+        writeln!(
+            f,
+            "start {} {} {} {}",
+            start.x,
+            start.y,
+            rules.start_length,
+            dir_to_char(rules.start_dir)
+        )?;
+        for rival in rules.rivals.iter() {
+            writeln!(
+                f,
+                "rival {} {} {}",
+                rival.pos.x,
+                rival.pos.y,
+                dir_to_char(rival.dir)
+            )?;
+        }
+        if rules.fruits != Fruits::Shared {
+            writeln!(f, "fruits {}", rules.fruits)?;
+        }
+        for wall in rules.walls.iter() {
+            writeln!(f, "wall {} {}", wall.x, wall.y)?;
+        }
+        for spawn in rules.fruit_spawns.iter() {
+            writeln!(f, "fruit {} {}", spawn.x, spawn.y)?;
+        }
+        if let Some(goal) = rules.goal {
+            writeln!(f, "goal {}", goal)?;
+        }
+        for (tick, dir) in self.turns.iter() {
+            writeln!(f, "turn {} {}", tick, dir_to_char(*dir))?;
+        }
+        for (tick, snake, dir) in self.rival_turns.iter() {
+            writeln!(f, "turn {} {} {}", tick, dir_to_char(*dir), snake)?;
+        }
+        writeln!(f, "end {}", self.ticks)
+    }
+}
+
+impl std::str::FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, l)| (i + 1, l.trim()));
+
+        // The header must come first, before anything else is trusted
+        let (_, header) = lines.next().unwrap_or((1, ""));
+        match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [MAGIC, version] => {
+                let version = parse_number(1, version)?;
+                if version != REPLAY_VERSION {
+                    return Err(ReplayError::Version(version));
+                }
+            }
+            _ => return Err(parse_error(1, "not a snake replay file")),
+        }
+
+        let mut seed = None;
+        let mut board = None;
+        let mut boundary = None;
+        let mut points = None;
+        let mut start = None;
+        let mut rivals = Vec::new();
+        let mut fruits = Fruits::Shared;
+        let mut walls = Vec::new();
+        let mut fruit_spawns = Vec::new();
+        let mut goal = None;
+        let mut ticks = None;
+        let mut turns: Vec<(u64, Direction)> = Vec::new();
+        let mut rival_turns: Vec<(u64, usize, Direction)> = Vec::new();
+
+        for (n, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+            if ticks.is_some() {
+                return Err(parse_error(n, "unexpected data after 'end'"));
+            }
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["seed", value] => seed = Some(parse_number(n, value)?),
+                ["grid", w, h] => {
+                    let b = Board::new(parse_number(n, w)?, parse_number(n, h)?)
+                        .map_err(|e| parse_error(n, &e))?;
+                    board = Some(b);
+                }
+                ["boundary", value] => {
+                    boundary = Some(value.parse().map_err(|e: String| parse_error(n, &e))?);
+                }
+                ["points", value] => points = Some(parse_number(n, value)?),
+                ["start", x, y, length, dir] => {
+                    let pos = GridPosition::new(parse_number(n, x)?, parse_number(n, y)?);
+                    let dir = dir_from_str(dir)
+                        .ok_or_else(|| parse_error(n, &format!("invalid direction '{}'", dir)))?;
+                    start = Some((pos, parse_number(n, length)?, dir));
+                }
+                ["rival", x, y, dir] => {
+                    let pos = GridPosition::new(parse_number(n, x)?, parse_number(n, y)?);
+                    let dir = dir_from_str(dir)
+                        .ok_or_else(|| parse_error(n, &format!("invalid direction '{}'", dir)))?;
+                    rivals.push(Start { pos, dir });
+                }
+                ["fruits", value] => {
+                    fruits = value.parse().map_err(|e: String| parse_error(n, &e))?;
+                }
+                ["wall", x, y] => {
+                    walls.push(GridPosition::new(parse_number(n, x)?, parse_number(n, y)?));
+                }
+                ["fruit", x, y] => {
+                    fruit_spawns.push(GridPosition::new(parse_number(n, x)?, parse_number(n, y)?));
+                }
+                ["turn", tick, dir] => {
+                    let tick = parse_number(n, tick)?;
+                    if turns.last().is_some_and(|(last, _)| *last >= tick) {
+                        return Err(parse_error(n, "turns are not in tick order"));
+                    }
+                    let dir = dir_from_str(dir)
+                        .ok_or_else(|| parse_error(n, &format!("invalid direction '{}'", dir)))?;
+                    turns.push((tick, dir));
+                }
+                ["turn", tick, dir, snake] => {
+                    let tick = parse_number(n, tick)?;
+                    let snake = parse_number(n, snake)?;
+                    if snake == 0 {
+                        return Err(parse_error(n, "the player turns without a snake index"));
+                    }
+                    if rival_turns
+                        .last()
+                        .is_some_and(|(last, s, _)| (*last, *s) >= (tick, snake))
+                    {
+                        return Err(parse_error(n, "turns are not in tick order"));
+                    }
+                    let dir = dir_from_str(dir)
+                        .ok_or_else(|| parse_error(n, &format!("invalid direction '{}'", dir)))?;
+                    rival_turns.push((tick, snake, dir));
+                }
+                ["goal", kind, value] => {
+                    let text = format!("{} {}", kind, value);
+                    goal = Some(text.parse().map_err(|e: String| parse_error(n, &e))?);
+                }
+                ["end", value] => ticks = Some(parse_number(n, value)?),
+                _ => return Err(parse_error(n, &format!("unexpected record '{}'", line))),
+            }
+        }
+
+        let last = s.lines().count();
+        let seed = seed.ok_or_else(|| parse_error(last, "missing 'seed'"))?;
+        let board = board.ok_or_else(|| parse_error(last, "missing 'grid'"))?;
+        let boundary = boundary.ok_or_else(|| parse_error(last, "missing 'boundary'"))?;
+        let fruit_points = points.ok_or_else(|| parse_error(last, "missing 'points'"))?;
+        let (start_pos, start_length, start_dir) =
+            start.ok_or_else(|| parse_error(last, "missing 'start'"))?;
+        let ticks = ticks.ok_or_else(|| parse_error(last, "missing 'end', truncated file?"))?;
+
+        let rules = Rules {
+            board,
+            boundary,
+            fruit_points,
+            start_pos,
+            start_dir,
+            start_length,
+            walls,
+            fruit_spawns,
+            goal,
+            rivals,
+            fruits,
+        };
+        rules.validate().map_err(|e| parse_error(last, &e))?;
+        if rival_turns
+            .iter()
+            .any(|(_, snake, _)| *snake > rules.rivals.len())
+        {
+            return Err(parse_error(
+                last,
+                "turns of a snake that is not on the board",
+            ));
+        }
+
+        Ok(Replay {
+            seed,
+            rules,
+            turns,
+            rival_turns,
+            ticks,
+        })
+    }
+}
+
+/// Feed the inputs of a replay back to a simulation, tick after tick
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// Index of the next turn to play
+    next: usize,
+    /// Index of the next turn of the other snakes to play
+    next_rival: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer {
+            replay,
+            next: 0,
+            next_rival: 0,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Return the input to give to `Simulation::step` at the given tick
+    pub fn input(&mut self, tick: u64) -> Option<Direction> {
+        match self.replay.turns.get(self.next) {
+            Some((t, dir)) if *t == tick => {
+                self.next += 1;
+                Some(*dir)
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the inputs of all the snakes to give to `Simulation::step_all`
+    /// at the given tick, the player's first
+    pub fn inputs(&mut self, tick: u64) -> Vec<Option<Direction>> {
+        let mut inputs = vec![None; self.replay.rules.rivals.len() + 1];
+        inputs[0] = self.input(tick);
+        while let Some((t, snake, dir)) = self.replay.rival_turns.get(self.next_rival) {
+            if *t != tick {
+                break;
+            }
+            inputs[*snake] = Some(*dir);
+            self.next_rival += 1;
+        }
+        inputs
+    }
+
+    /// Check if all the recorded ticks have been played
+    pub fn is_finished(&self, tick: u64) -> bool {
+        tick >= self.replay.ticks
+    }
+
+    /// Start again from the first tick
+    pub fn rewind(&mut self) {
+        self.next = 0;
+        self.next_rival = 0;
+    }
+}
+
+fn parse_error(line: usize, msg: &str) -> ReplayError {
+    ReplayError::Parse {
+        line,
+        msg: msg.to_string(),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(line: usize, value: &str) -> Result<T, ReplayError> {
+    value
+        .parse()
+        .map_err(|_| parse_error(line, &format!("invalid number '{}'", value)))
+}
+
+fn dir_to_char(dir: Direction) -> char {
+    match dir {
+        Direction::Up => 'U',
+        Direction::Down => 'D',
+        Direction::Left => 'L',
+        Direction::Right => 'R',
+    }
+}
+
+fn dir_from_str(s: &str) -> Option<Direction> {
+    match s {
+        "U" => Some(Direction::Up),
+        "D" => Some(Direction::Down),
+        "L" => Some(Direction::Left),
+        "R" => Some(Direction::Right),
+        _ => None,
+    }
+}
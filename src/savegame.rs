@@ -0,0 +1,115 @@
+//! Saved games, to quit in the middle of a game and continue it later.
+//!
+//! A saved game holds a `Snapshot` of the simulation, RNG included, and the
+//! little state kept by the front-end between two ticks, so the game goes on
+//! exactly as if it had never been interrupted.
+//! It is saved in a TOML file in the user data directory.
+
+use crate::replay::Replay;
+use crate::simulation::Direction;
+use crate::simulation::Simulation;
+use crate::simulation::Snapshot;
+use crate::toml_file;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The name of the saved game file in the user data directory
+pub const SAVEGAME_FILE: &str = "savegame.toml";
+
+/// Everything that can go wrong loading a saved game
+#[derive(Debug)]
+pub enum SaveGameError {
+    Io(PathBuf, io::Error),
+    /// The file is corrupted
+    Parse(PathBuf, toml::de::Error),
+    /// The file is readable but the game in it is not valid
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for SaveGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveGameError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            SaveGameError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            SaveGameError::Invalid(path, msg) => write!(f, "{}: {}", path.display(), msg),
+        }
+    }
+}
+
+impl std::error::Error for SaveGameError {}
+
+/// A game saved in the middle
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveGame {
+    /// The turns the player asked for, not applied yet, the next one first
+    pub turns: Vec<Direction>,
+    /// The time passed since the last move of the snake
+    pub head_timer: Duration,
+    /// The level played, if any
+    pub level: Option<String>,
+    /// The index of the stage, if the level is a stage of the campaign
+    pub stage: Option<usize>,
+    /// The inputs of the game so far, to keep recording it
+    #[serde(with = "replay_text")]
+    pub recording: Replay,
+    /// The simulation
+    pub game: Snapshot,
+    /// The autopilot drove the snake for a while, see `Entry::assisted`
+    #[serde(default)]
+    pub assisted: bool,
+}
+
+impl SaveGame {
+    /// Load a saved game, or `None` if there is none
+    pub fn load(path: &Path) -> Result<Option<SaveGame>, SaveGameError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(SaveGameError::Io(path.to_path_buf(), e)),
+        };
+        let save: SaveGame =
+            toml::from_str(&text).map_err(|e| SaveGameError::Parse(path.to_path_buf(), e))?;
+
+        // Don't trust a file edited by hand
+        Simulation::from_snapshot(save.game.clone())
+            .map_err(|e| SaveGameError::Invalid(path.to_path_buf(), e))?;
+        if save.recording.seed != save.game.seed || save.recording.rules != save.game.rules {
+            return Err(SaveGameError::Invalid(
+                path.to_path_buf(),
+                "the recording is not of the saved game".to_string(),
+            ));
+        }
+
+        Ok(Some(save))
+    }
+
+    /// Save the game to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        // Through a `Value`, which puts the tables after the plain values as TOML wants
+        let value = toml::Value::try_from(self).map_err(io::Error::other)?;
+        toml_file::save(path, &value)
+    }
+}
+
+/// The recording is saved in the replay format, not to describe it twice
+mod replay_text {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(replay: &Replay, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(replay)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Replay, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
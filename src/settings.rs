@@ -0,0 +1,427 @@
+//! The settings file.
+//!
+//! The game reads its settings from a TOML file, by default `settings.toml`
+//! in the user config directory. Every key is optional:
+//!
+//! ```toml
+//! [board]
+//! width = 25
+//! height = 20
+//! cell_size = 32        # pixels, by default the board fits on the screen
+//!
+//! [game]
+//! move_time = 300       # milliseconds between two moves of the snake
+//! fruit_points = 10
+//! boundary = "wrap"     # or "walls", "wrap-x", "wrap-y"
+//! level = "box"         # a level of resources/levels/, replaces [board] and [snake]
+//! fruits = "shared"     # or "separate": a fruit for each snake in a match
+//!
+//! [snake]
+//! x = 4                 # where the head is when the game starts
+//! y = 4
+//! length = 1            # head included
+//!
+//! [display]
+//! show_grid = true
+//! show_fps = true
+//! background = "#131926"
+//! grid = "#333333"
+//!
+//! [keys]
+//! preset = "arrows"     # or "wasd", "vim"
+//! up = ["Up"]           # only the actions that don't keep the keys of the preset
+//! down = ["Down"]
+//! left = ["Left"]
+//! right = ["Right"]
+//! pause = ["P", "Space"]
+//! toggle_grid = ["G"]
+//! toggle_fps = ["F"]
+//! quit = ["Q", "Escape"]
+//! confirm = ["Y"]
+//! cancel = ["N"]
+//! save = ["S"]          # save and quit
+//! continue = ["C"]      # continue the saved game
+//! high_scores = ["H"]
+//! bindings = ["B"]      # the controls screen
+//! assist = ["Tab"]      # the autopilot drives the snake, or gives it back
+//!
+//! [mouse]
+//! steering = false      # click or drag around the head to turn, for touch screens
+//! buttons = false       # clickable buttons on the start, pause and game over screens
+//!
+//! [bots]
+//! count = 0             # computer-controlled snakes, up to 3
+//! difficulty = "pathfinding"  # or "greedy", "cautious"
+//!
+//! [autopilot]
+//! strategy = "safe-path"  # or "cycle", for the demo and the assist
+//! ```
+
+use crate::autopilot::Strategy;
+use crate::bot::Difficulty;
+use crate::bot::MAX_BOTS;
+use crate::simulation::Board;
+use crate::simulation::Boundary;
+use crate::simulation::Fruits;
+use crate::simulation::GridPosition;
+use crate::simulation::Rules;
+use crate::simulation::FRUIT_POINTS;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The name of the settings file in the user config directory
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Everything that can go wrong loading the settings
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, io::Error),
+    /// The file is not valid TOML, or a key has the wrong type
+    Parse(PathBuf, toml::de::Error),
+    /// A key has a value out of range
+    Invalid {
+        key: String,
+        msg: String,
+    },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            SettingsError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            SettingsError::Invalid { key, msg } => write!(f, "invalid '{}': {}", key, msg),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// Build a `SettingsError::Invalid` for the given key
+pub fn invalid(key: &str, msg: impl Into<String>) -> SettingsError {
+    SettingsError::Invalid {
+        key: key.to_string(),
+        msg: msg.into(),
+    }
+}
+
+/// All the settings of the game
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub board: BoardSettings,
+    pub game: GameSettings,
+    pub snake: SnakeSettings,
+    pub display: DisplaySettings,
+    pub keys: KeySettings,
+    pub mouse: MouseSettings,
+    pub bots: BotSettings,
+    pub autopilot: AutopilotSettings,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardSettings {
+    pub width: u32,
+    pub height: u32,
+    /// The size of a cell in pixels, computed from the screen size when missing
+    pub cell_size: Option<u32>,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        let board = Board::default();
+        BoardSettings {
+            width: board.width,
+            height: board.height,
+            cell_size: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameSettings {
+    /// Milliseconds between two moves of the snake
+    pub move_time: u64,
+    pub fruit_points: u32,
+    /// What happens at the edges of the board, see `Boundary`
+    pub boundary: String,
+    /// The level to play, by name or path, see `level`
+    pub level: Option<String>,
+    /// Whether the snakes of a match share the fruit, see `Fruits`
+    pub fruits: String,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            move_time: 300,
+            fruit_points: FRUIT_POINTS,
+            boundary: Boundary::default().to_string(),
+            level: None,
+            fruits: Fruits::default().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnakeSettings {
+    pub x: u32,
+    pub y: u32,
+    pub length: u32,
+}
+
+impl Default for SnakeSettings {
+    fn default() -> Self {
+        let rules = Rules::default();
+        SnakeSettings {
+            x: rules.start_pos.x,
+            y: rules.start_pos.y,
+            length: rules.start_length,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplaySettings {
+    pub show_grid: bool,
+    pub show_fps: bool,
+    /// The background color, as `#rrggbb`
+    pub background: String,
+    /// The color of the grid lines, as `#rrggbb`
+    pub grid: String,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            show_grid: true,
+            show_fps: true,
+            background: "#131926".to_string(),
+            grid: "#333333".to_string(),
+        }
+    }
+}
+
+/// The names of the keys bound to each action, see `controls`.
+///
+/// The actions are checked by `Controls::from_settings`, not by the parser.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeySettings {
+    /// The bindings to start from, see `Preset`
+    pub preset: String,
+    /// The keys of the actions that don't keep the ones of the preset, by action name
+    #[serde(flatten)]
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for KeySettings {
+    fn default() -> Self {
+        KeySettings {
+            preset: "arrows".to_string(),
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MouseSettings {
+    /// Turn the snake toward the clicked point, see `pointer`
+    pub steering: bool,
+    /// Show buttons to click instead of the keys on the screens that wait for an answer
+    pub buttons: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotSettings {
+    /// The number of computer-controlled snakes, up to `MAX_BOTS`
+    pub count: usize,
+    /// How well they play, see `Difficulty`
+    pub difficulty: String,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        BotSettings {
+            count: 0,
+            difficulty: Difficulty::default().to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutopilotSettings {
+    /// How the autopilot plays the demo and the assisted games, see `Strategy`
+    pub strategy: String,
+}
+
+impl Default for AutopilotSettings {
+    fn default() -> Self {
+        AutopilotSettings {
+            strategy: Strategy::default().to_string(),
+        }
+    }
+}
+
+/// The values given on the command line, which win over the settings
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    /// The size of the board, if not the one of the settings
+    pub board: Option<Board>,
+    /// The size of a cell in pixels, if not the one of the settings
+    pub cell_size: Option<u32>,
+    /// The number of bots, if not the one of the settings
+    pub bots: Option<usize>,
+    /// How well the bots play, if not as the settings say
+    pub difficulty: Option<String>,
+}
+
+impl Settings {
+    /// Load the settings from a file and check them
+    pub fn load(path: &Path) -> Result<Settings, SettingsError> {
+        let text =
+            fs::read_to_string(path).map_err(|e| SettingsError::Io(path.to_path_buf(), e))?;
+        let settings: Settings =
+            toml::from_str(&text).map_err(|e| SettingsError::Parse(path.to_path_buf(), e))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Load the settings from a file if it exists, otherwise use the defaults
+    pub fn load_or_default(path: &Path) -> Result<Settings, SettingsError> {
+        if path.exists() {
+            Settings::load(path)
+        } else {
+            Ok(Settings::default())
+        }
+    }
+
+    /// Load the settings from a file, or use the defaults without one,
+    /// then apply the overrides
+    pub fn load_with(
+        path: Option<&Path>,
+        overrides: &Overrides,
+    ) -> Result<Settings, SettingsError> {
+        let mut settings = match path {
+            Some(path) => Settings::load(path)?,
+            None => Settings::default(),
+        };
+        settings.apply(overrides)?;
+        Ok(settings)
+    }
+
+    /// Replace the values given by the overrides and check the result
+    pub fn apply(&mut self, overrides: &Overrides) -> Result<(), SettingsError> {
+        if let Some(board) = overrides.board {
+            self.board.width = board.width;
+            self.board.height = board.height;
+        }
+        if overrides.cell_size.is_some() {
+            self.board.cell_size = overrides.cell_size;
+        }
+        if let Some(count) = overrides.bots {
+            self.bots.count = count;
+        }
+        if let Some(difficulty) = &overrides.difficulty {
+            self.bots.difficulty = difficulty.clone();
+        }
+        self.validate()
+    }
+
+    /// Check that all the values are in range
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        self.rules()?;
+        if self.board.cell_size == Some(0) {
+            return Err(invalid("board.cell_size", "must be greater than 0"));
+        }
+        if self.game.move_time == 0 {
+            return Err(invalid("game.move_time", "must be greater than 0"));
+        }
+        parse_color(&self.display.background).map_err(|e| invalid("display.background", e))?;
+        parse_color(&self.display.grid).map_err(|e| invalid("display.grid", e))?;
+        if self.bots.count > MAX_BOTS {
+            return Err(invalid(
+                "bots.count",
+                format!("there can be {} bots at most", MAX_BOTS),
+            ));
+        }
+        self.difficulty()?;
+        self.strategy()?;
+        Ok(())
+    }
+
+    /// The rules of the game described by the settings
+    pub fn rules(&self) -> Result<Rules, SettingsError> {
+        let board = Board::new(self.board.width, self.board.height)
+            .map_err(|e| invalid("board.width / board.height", e))?;
+        let boundary = self
+            .game
+            .boundary
+            .parse()
+            .map_err(|e: String| invalid("game.boundary", e))?;
+        let fruits = self
+            .game
+            .fruits
+            .parse()
+            .map_err(|e: String| invalid("game.fruits", e))?;
+        let rules = Rules {
+            board,
+            boundary,
+            fruits,
+            fruit_points: self.game.fruit_points,
+            start_pos: GridPosition::new(self.snake.x, self.snake.y),
+            start_length: self.snake.length,
+            ..Rules::default()
+        };
+        rules
+            .validate()
+            .map_err(|e| invalid("snake.x / snake.y / snake.length", e))?;
+        Ok(rules)
+    }
+
+    /// How well the bots play
+    pub fn difficulty(&self) -> Result<Difficulty, SettingsError> {
+        self.bots
+            .difficulty
+            .parse()
+            .map_err(|e: String| invalid("bots.difficulty", e))
+    }
+
+    /// How the autopilot plays
+    pub fn strategy(&self) -> Result<Strategy, SettingsError> {
+        self.autopilot
+            .strategy
+            .parse()
+            .map_err(|e: String| invalid("autopilot.strategy", e))
+    }
+
+    /// The time between two moves of the snake
+    pub fn move_time(&self) -> Duration {
+        Duration::from_millis(self.game.move_time)
+    }
+}
+
+/// Parse a `#rrggbb` color
+pub fn parse_color(s: &str) -> Result<(u8, u8, u8), String> {
+    let error = || format!("'{}' is not a color like #rrggbb", s);
+    let hex = s
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or_else(error)?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| error());
+    Ok((channel(0)?, channel(2)?, channel(4)?))
+}
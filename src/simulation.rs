@@ -0,0 +1,1246 @@
+//! The rules of the game, without any rendering.
+//!
+//! A `Simulation` knows nothing about windows, images or time: it is advanced
+//! one tick at a time with `step` and reports what happened as a list of `Event`s.
+//! The ggez front-end in `main.rs` just drives it and draws the result.
+//!
+//! There is one snake, the player's, unless the rules add rivals: then all
+//! the snakes move at once at each tick, and the last one alive wins the round.
+
+use oorandom::Rand32;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::LinkedList;
+use std::fmt;
+use std::hash::Hasher;
+
+/// The points given for each fruit, unless the rules say otherwise
+pub const FRUIT_POINTS: u32 = 10;
+
+/// The bonus points for filling the whole board, in fruits
+pub const VICTORY_BONUS_FRUITS: u32 = 10;
+
+/// The smallest board, in cells, along each side
+pub const MIN_GRID_SIZE: u32 = 5;
+/// The largest board, in cells, along each side
+pub const MAX_GRID_SIZE: u32 = 200;
+
+/// An enum with all the possible game states
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameState {
+    Starting,
+    Running,
+    Paused,
+    GameOver,
+    /// The goal of the level was reached
+    LevelComplete,
+    /// The snake filled the whole board
+    Victory,
+    Quitting,
+}
+
+/// An enum that will represent all the possible
+/// directions that our snake could move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// An helper function that will allow us to easily check the opposite
+    /// of a `Direction` which we can use later to check if the player should be
+    /// able to move the snake in a certain direction.
+    pub fn opposite(&self, op: Direction) -> bool {
+        match *self {
+            Direction::Up => op == Direction::Down,
+            Direction::Down => op == Direction::Up,
+            Direction::Left => op == Direction::Right,
+            Direction::Right => op == Direction::Left,
+        }
+    }
+
+    /// The direction opposite to this one
+    pub fn reverse(&self) -> Direction {
+        match *self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+/// What happens when the snake reaches an edge of the board
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    /// Every edge wraps around to the opposite one
+    #[default]
+    Wrap,
+    /// Every edge is a wall
+    Walls,
+    /// The left and right edges wrap, the top and bottom ones are walls
+    WrapX,
+    /// The top and bottom edges wrap, the left and right ones are walls
+    WrapY,
+}
+
+impl Boundary {
+    /// Check if the left and right edges wrap around
+    pub fn wraps_x(&self) -> bool {
+        matches!(self, Boundary::Wrap | Boundary::WrapX)
+    }
+
+    /// Check if the top and bottom edges wrap around
+    pub fn wraps_y(&self) -> bool {
+        matches!(self, Boundary::Wrap | Boundary::WrapY)
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Boundary::Wrap => "wrap",
+            Boundary::Walls => "walls",
+            Boundary::WrapX => "wrap-x",
+            Boundary::WrapY => "wrap-y",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Boundary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(Boundary::Wrap),
+            "walls" => Ok(Boundary::Walls),
+            "wrap-x" => Ok(Boundary::WrapX),
+            "wrap-y" => Ok(Boundary::WrapY),
+            _ => Err(format!(
+                "unknown boundary '{}', expected wrap, walls, wrap-x or wrap-y",
+                s
+            )),
+        }
+    }
+}
+
+/// What the snake must achieve to complete a level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Goal {
+    /// Grow up to this length, head included
+    Length(u32),
+    /// Reach this score
+    Score(u32),
+}
+
+impl Goal {
+    /// Check if a snake with this length and score reached the goal
+    pub fn is_reached(&self, length: u32, score: u32) -> bool {
+        match *self {
+            Goal::Length(l) => length >= l,
+            Goal::Score(s) => score >= s,
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Goal::Length(l) => write!(f, "length {}", l),
+            Goal::Score(s) => write!(f, "score {}", s),
+        }
+    }
+}
+
+impl std::str::FromStr for Goal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid goal '{}', expected 'length N' or 'score N'", s);
+        let (kind, value) = s.split_once(' ').ok_or_else(error)?;
+        let value = value.trim().parse().map_err(|_| error())?;
+        match kind {
+            "length" => Ok(Goal::Length(value)),
+            "score" => Ok(Goal::Score(value)),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Whether the snakes race for the same fruit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fruits {
+    /// A single fruit that any snake can eat
+    #[default]
+    Shared,
+    /// A fruit for each snake, the others pass over it
+    Separate,
+}
+
+impl fmt::Display for Fruits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Fruits::Shared => "shared",
+            Fruits::Separate => "separate",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Fruits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shared" => Ok(Fruits::Shared),
+            "separate" => Ok(Fruits::Separate),
+            _ => Err(format!(
+                "unknown fruits '{}', expected shared or separate",
+                s
+            )),
+        }
+    }
+}
+
+/// Where a snake starts, and which way it goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Start {
+    pub pos: GridPosition,
+    pub dir: Direction,
+}
+
+impl Start {
+    /// The start on the other side of the board, through its center,
+    /// going the other way: a fair start for a second player
+    pub fn mirrored(&self, board: Board) -> Start {
+        Start {
+            pos: GridPosition::new(board.width - 1 - self.pos.x, board.height - 1 - self.pos.y),
+            dir: self.dir.reverse(),
+        }
+    }
+}
+
+/// The size of the game board, in cells
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Board {
+    /// Create a board, checking that its size is between
+    /// `MIN_GRID_SIZE` and `MAX_GRID_SIZE`
+    pub fn new(width: u32, height: u32) -> Result<Board, String> {
+        let range = MIN_GRID_SIZE..=MAX_GRID_SIZE;
+        if !range.contains(&width) || !range.contains(&height) {
+            return Err(format!(
+                "the board must be between {0}x{0} and {1}x{1} cells, not {2}x{3}",
+                MIN_GRID_SIZE, MAX_GRID_SIZE, width, height
+            ));
+        }
+        Ok(Board { width, height })
+    }
+
+    /// The number of cells on the board
+    pub fn cells(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+impl std::str::FromStr for Board {
+    type Err = String;
+
+    /// Read a size such as `25x20`, as given to `--grid`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .ok_or_else(|| format!("Invalid grid size '{}'", s))?;
+        Board::new(width, height)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board {
+            width: 25,
+            height: 20,
+        }
+    }
+}
+
+/// The settings that change how a game plays out.
+///
+/// Two games with the same rules, seed and inputs are identical.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rules {
+    /// The size of the board
+    pub board: Board,
+    /// What happens at the edges of the board
+    pub boundary: Boundary,
+    /// The points given for each fruit
+    pub fruit_points: u32,
+    /// Where the head of the snake is when the game starts
+    pub start_pos: GridPosition,
+    /// The direction of the snake when the game starts
+    pub start_dir: Direction,
+    /// The length of the snake, head included, when the game starts
+    pub start_length: u32,
+    /// The cells taken by walls, deadly like the edges in `Boundary::Walls`
+    pub walls: Vec<GridPosition>,
+    /// Where the fruits appear; anywhere on the board when empty
+    pub fruit_spawns: Vec<GridPosition>,
+    /// What completes the level; the game is endless when missing
+    pub goal: Option<Goal>,
+    /// Where the snakes of the other players start; none in a game alone
+    #[serde(default)]
+    pub rivals: Vec<Start>,
+    /// Whether the snakes share the fruit or each has its own
+    #[serde(default)]
+    pub fruits: Fruits,
+}
+
+impl Rules {
+    /// Check the size of the board, and that the walls, the fruit spawns and
+    /// the snake fit on it
+    pub fn validate(&self) -> Result<(), String> {
+        let board = self.board;
+        // Rules read from a file or received over the network didn't go through `Board::new`
+        Board::new(board.width, board.height)?;
+        let inside = |p: &GridPosition| p.x < board.width && p.y < board.height;
+
+        if let Some(p) = self.walls.iter().find(|p| !inside(p)) {
+            return Err(format!(
+                "the wall at {},{} is outside of the board",
+                p.x, p.y
+            ));
+        }
+        if let Some(p) = self.fruit_spawns.iter().find(|p| !inside(p)) {
+            return Err(format!(
+                "the fruit spawn at {},{} is outside of the board",
+                p.x, p.y
+            ));
+        }
+        if let Some(p) = self.fruit_spawns.iter().find(|p| self.walls.contains(p)) {
+            return Err(format!("the fruit spawn at {},{} is on a wall", p.x, p.y));
+        }
+        if self.start_length == 0 {
+            return Err("the snake length must be at least 1".to_string());
+        }
+        let mut cells = Vec::new();
+        for start in self.starts() {
+            if !inside(&start.pos) {
+                return Err(format!(
+                    "the snake starts at {},{} outside of the {}x{} board",
+                    start.pos.x, start.pos.y, board.width, board.height
+                ));
+            }
+            let body = self.start_body_at(start)?;
+            for seg in std::iter::once(start.pos).chain(body) {
+                if cells.contains(&seg) {
+                    return Err(format!("two snakes start on {},{}", seg.x, seg.y));
+                }
+                cells.push(seg);
+            }
+        }
+        let fruits = match self.fruits {
+            Fruits::Shared => 1,
+            Fruits::Separate => self.starts().len(),
+        };
+        if cells.len() + fruits > board.cells() - self.walls.len() {
+            return Err("the snake must leave at least one free cell for the fruit".to_string());
+        }
+        match self.goal {
+            Some(Goal::Length(l)) if l <= self.start_length => Err(format!(
+                "the goal length {} must be longer than the snake, {}",
+                l, self.start_length
+            )),
+            Some(Goal::Length(l)) if l as usize > board.cells() - self.walls.len() => {
+                Err(format!("the goal length {} doesn't fit on the board", l))
+            }
+            Some(Goal::Score(0)) => Err("the goal score must be greater than 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The body of the snake when the game starts, behind the head.
+    ///
+    /// Fail if the snake runs into a wall or into itself
+    pub fn start_body(&self) -> Result<Vec<GridPosition>, String> {
+        self.start_body_at(self.start())
+    }
+
+    /// The body of a snake starting there, behind the head
+    pub fn start_body_at(&self, start: Start) -> Result<Vec<GridPosition>, String> {
+        let mut body = Vec::new();
+        let mut seg = start.pos;
+        let mut cells = vec![seg];
+        for _ in 1..self.start_length {
+            if !seg.try_move_to_direction(start.dir.reverse(), self.board, self.boundary)
+                || self.walls.contains(&seg)
+                || cells.contains(&seg)
+            {
+                return Err(format!(
+                    "a snake of length {} starting at {},{} doesn't fit on the board",
+                    self.start_length, start.pos.x, start.pos.y
+                ));
+            }
+            cells.push(seg);
+            body.push(seg);
+        }
+        if self.walls.contains(&start.pos) {
+            return Err(format!(
+                "the snake starts at {},{} on a wall",
+                start.pos.x, start.pos.y
+            ));
+        }
+        Ok(body)
+    }
+
+    /// Where the snake of the player starts
+    pub fn start(&self) -> Start {
+        Start {
+            pos: self.start_pos,
+            dir: self.start_dir,
+        }
+    }
+
+    /// Where all the snakes start, the player's first
+    pub fn starts(&self) -> Vec<Start> {
+        std::iter::once(self.start())
+            .chain(self.rivals.iter().copied())
+            .collect()
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            board: Board::default(),
+            boundary: Boundary::Wrap,
+            fruit_points: FRUIT_POINTS,
+            start_pos: GridPosition::new(4, 4),
+            start_dir: Direction::Right,
+            start_length: 1,
+            walls: Vec::new(),
+            fruit_spawns: Vec::new(),
+            goal: None,
+            rivals: Vec::new(),
+            fruits: Fruits::Shared,
+        }
+    }
+}
+
+/// A struct that will hold an entity's position on our game board
+/// or grid which we defined above.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct GridPosition {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl GridPosition {
+    /// We make a standard helper function so that we can create a new `GridPosition`
+    /// more easily.
+    pub fn new(x: u32, y: u32) -> Self {
+        GridPosition { x, y }
+    }
+
+    /// Move the position in the given direction.
+    /// The grid is toroidal
+    pub fn move_to_direction(&mut self, dir: Direction, board: Board) {
+        match dir {
+            Direction::Down => {
+                self.y += 1;
+                if self.y >= board.height {
+                    self.y = 0;
+                }
+            }
+            Direction::Up => {
+                if self.y == 0 {
+                    self.y = board.height;
+                }
+                self.y -= 1;
+            }
+            Direction::Right => {
+                self.x += 1;
+                if self.x >= board.width {
+                    self.x = 0;
+                }
+            }
+            Direction::Left => {
+                if self.x == 0 {
+                    self.x = board.width;
+                }
+                self.x -= 1;
+            }
+        }
+    }
+
+    /// Move the position in the given direction, unless it would go through a wall.
+    /// Return `false` if a wall is in the way
+    pub fn try_move_to_direction(
+        &mut self,
+        dir: Direction,
+        board: Board,
+        boundary: Boundary,
+    ) -> bool {
+        let blocked = match dir {
+            Direction::Down => !boundary.wraps_y() && self.y + 1 >= board.height,
+            Direction::Up => !boundary.wraps_y() && self.y == 0,
+            Direction::Right => !boundary.wraps_x() && self.x + 1 >= board.width,
+            Direction::Left => !boundary.wraps_x() && self.x == 0,
+        };
+        if !blocked {
+            self.move_to_direction(dir, board);
+        }
+        !blocked
+    }
+
+    /// The index of the position in a row-major array of the board cells
+    pub fn index(&self, board: Board) -> usize {
+        (self.y * board.width + self.x) as usize
+    }
+}
+
+/// Something that happened during a call to `Simulation::step`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The snake head moved to a new cell
+    Moved(GridPosition),
+    /// A snake ate a fruit and grew by one segment
+    FruitEaten { snake: usize, points: u32 },
+    /// The game is over
+    GameOver(Death),
+    /// A snake died, in a game with several snakes
+    Died { snake: usize, death: Death },
+    /// The round of a game with several snakes is over: the winner is
+    /// the last snake alive, or the first to reach the goal. `None` is a draw.
+    RoundOver { winner: Option<usize> },
+    /// The goal of the rules was reached
+    LevelComplete,
+    /// The snake filled the whole board and won the bonus points
+    Victory { bonus: u32 },
+}
+
+/// The reason the snake died
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Death {
+    /// The snake ate itself
+    BitItself,
+    /// The snake ran into a wall or into a walled edge of the board
+    HitWall,
+    /// The snake ran into the body of another snake
+    HitSnake,
+    /// Two snakes ran head first into each other
+    HeadOn,
+}
+
+impl fmt::Display for Death {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Death::BitItself => write!(f, "The snake bit itself"),
+            Death::HitWall => write!(f, "The snake hit a wall"),
+            Death::HitSnake => write!(f, "The snake ran into another snake"),
+            Death::HeadOn => write!(f, "The snakes collided head-on"),
+        }
+    }
+}
+
+/// A snake on the board
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snake {
+    /// The current head position on the grid
+    head_pos: GridPosition,
+    /// The current head moving direction
+    dir: Direction,
+    /// The list of body segments, from the neck to the tail
+    body: LinkedList<GridPosition>,
+    /// The points of the fruits eaten
+    score: u32,
+    /// Why the snake died, if it did
+    death: Option<Death>,
+}
+
+impl Snake {
+    /// A snake starting as the rules say
+    fn new(rules: &Rules, start: Start) -> Snake {
+        Snake {
+            head_pos: start.pos,
+            dir: start.dir,
+            body: rules
+                .start_body_at(start)
+                .expect("Invalid rules")
+                .into_iter()
+                .collect(),
+            score: 0,
+            death: None,
+        }
+    }
+
+    /// The cell of the head
+    pub fn head_pos(&self) -> GridPosition {
+        self.head_pos
+    }
+
+    /// The direction the snake moves in
+    pub fn dir(&self) -> Direction {
+        self.dir
+    }
+
+    /// The cells of the body, from the neck to the tail
+    pub fn body(&self) -> &LinkedList<GridPosition> {
+        &self.body
+    }
+
+    /// The length of the snake, head included
+    pub fn length(&self) -> u32 {
+        self.body.len() as u32 + 1
+    }
+
+    /// The points of the fruits eaten
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// How the snake died, if it did
+    pub fn death(&self) -> Option<Death> {
+        self.death
+    }
+
+    /// Check if the snake is still moving
+    pub fn is_alive(&self) -> bool {
+        self.death.is_none()
+    }
+
+    /// Check if the snake is allowed to turn in the given direction:
+    /// it can't reverse onto its own body.
+    pub fn can_turn(&self, dir: Direction) -> bool {
+        self.body.is_empty() || !self.dir.opposite(dir)
+    }
+
+    /// Check if the snake takes the given cell, head included
+    pub fn covers(&self, pos: GridPosition) -> bool {
+        self.head_pos == pos || self.body.contains(&pos)
+    }
+}
+
+/// Everything a `Simulation` is made of, RNG included,
+/// to save a game and resume it later exactly where it was left.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub rules: Rules,
+    pub head_pos: GridPosition,
+    pub dir: Direction,
+    pub fruit_pos: GridPosition,
+    /// The body segments, from the neck to the tail
+    pub body: Vec<GridPosition>,
+    #[serde(with = "crate::u64_string")]
+    pub seed: u64,
+    /// The state of the RNG, as `Rand32::state` returns it
+    #[serde(with = "crate::u64_string")]
+    pub rng_state: u64,
+    #[serde(with = "crate::u64_string")]
+    pub rng_inc: u64,
+    pub score: u32,
+    pub state: GameState,
+    pub death: Option<Death>,
+    pub tick: u64,
+    /// The snakes of the other players, if any
+    #[serde(default)]
+    pub rivals: Vec<Snake>,
+    /// The fruits of the rivals, with `Fruits::Separate`
+    #[serde(default)]
+    pub rival_fruits: Vec<GridPosition>,
+}
+
+/// What changes in a game at every tick: the snakes, the fruits and the state,
+/// but not the rules. A client of a game over the network draws the game from
+/// the frames sent by the host.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub tick: u64,
+    pub state: GameState,
+    /// The snakes, the player's first
+    pub snakes: Vec<Snake>,
+    pub fruits: Vec<GridPosition>,
+}
+
+/// The whole state of a game: board, snakes, fruits and scores.
+#[derive(Clone)]
+pub struct Simulation {
+    /// The rules of the game
+    rules: Rules,
+    /// The cells taken by walls, indexed by `GridPosition::index`
+    walls: Vec<bool>,
+
+    /// The snakes, the player's first
+    snakes: Vec<Snake>,
+
+    /// The current fruit positions on the grid: one shared by all the snakes,
+    /// or one for each snake with `Fruits::Separate`
+    fruits: Vec<GridPosition>,
+
+    /// The seed of the current game
+    seed: u64,
+    /// Our RNG state
+    rng: Rand32,
+
+    /// Game state
+    state: GameState,
+    /// Number of ticks played since the game started
+    tick: u64,
+}
+
+/// Return a seed taken from the system RNG
+pub fn random_seed() -> u64 {
+    let mut seed: [u8; 8] = [0; 8];
+    getrandom::getrandom(&mut seed[..]).expect("Could not create RNG seed");
+    u64::from_ne_bytes(seed)
+}
+
+impl Simulation {
+    /// Create a new simulation in the `Starting` state, with the snakes
+    /// placed as the rules say.
+    ///
+    /// The `Starting` state is the attract mode: the game plays by its rules,
+    /// usually driven by an `Autopilot`, until it ends like any other game.
+    /// Panics if the rules don't pass `Rules::validate`.
+    pub fn new(rules: Rules, seed: u64) -> Self {
+        let mut walls = vec![false; rules.board.cells()];
+        for wall in rules.walls.iter() {
+            walls[wall.index(rules.board)] = true;
+        }
+
+        let mut sim = Simulation {
+            rules,
+            walls,
+            snakes: Vec::new(),
+            fruits: Vec::new(),
+            seed,
+            rng: Rand32::new(seed),
+            state: GameState::Starting,
+            tick: 0,
+        };
+        sim.restart(seed);
+        sim.state = GameState::Starting;
+
+        sim
+    }
+
+    /// Start a new game, with the snakes placed as the rules say.
+    ///
+    /// The RNG is reseeded, so the new game depends only on `seed` and on the inputs.
+    /// Panics if the rules don't pass `Rules::validate`.
+    pub fn restart(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rand32::new(seed);
+        self.snakes = self
+            .rules
+            .starts()
+            .into_iter()
+            .map(|start| Snake::new(&self.rules, start))
+            .collect();
+        let fruits = match self.rules.fruits {
+            Fruits::Shared => 1,
+            Fruits::Separate => self.snakes.len(),
+        };
+        self.fruits.clear();
+        for _ in 0..fruits {
+            let pos = self.random_free_pos(None).expect("No free cell");
+            self.fruits.push(pos);
+        }
+        self.state = GameState::Running;
+        self.tick = 0;
+    }
+
+    /// Take a snapshot of the game, to restore it with `from_snapshot`
+    pub fn snapshot(&self) -> Snapshot {
+        let (rng_state, rng_inc) = self.rng.state();
+        let player = &self.snakes[0];
+        Snapshot {
+            rules: self.rules.clone(),
+            head_pos: player.head_pos,
+            dir: player.dir,
+            fruit_pos: self.fruits[0],
+            body: player.body.iter().copied().collect(),
+            seed: self.seed,
+            rng_state,
+            rng_inc,
+            score: player.score,
+            state: self.state,
+            death: player.death,
+            tick: self.tick,
+            rivals: self.snakes[1..].to_vec(),
+            rival_fruits: self.fruits[1..].to_vec(),
+        }
+    }
+
+    /// Restore a game from a snapshot. It then plays out exactly
+    /// as the game the snapshot was taken from would have.
+    ///
+    /// The snapshot may come from a file, so it is checked first.
+    pub fn from_snapshot(snapshot: Snapshot) -> Result<Self, String> {
+        let rules = snapshot.rules;
+        rules.validate()?;
+
+        let player = Snake {
+            head_pos: snapshot.head_pos,
+            dir: snapshot.dir,
+            body: snapshot.body.into_iter().collect(),
+            score: snapshot.score,
+            death: snapshot.death,
+        };
+        let snakes: Vec<Snake> = std::iter::once(player).chain(snapshot.rivals).collect();
+        let fruits: Vec<GridPosition> = std::iter::once(snapshot.fruit_pos)
+            .chain(snapshot.rival_fruits)
+            .collect();
+
+        check_pieces(&rules, &snakes, &fruits)?;
+
+        let board = rules.board;
+        let mut walls = vec![false; board.cells()];
+        for wall in rules.walls.iter() {
+            walls[wall.index(board)] = true;
+        }
+
+        Ok(Simulation {
+            rules,
+            walls,
+            snakes,
+            fruits,
+            seed: snapshot.seed,
+            rng: Rand32::from_state((snapshot.rng_state, snapshot.rng_inc)),
+            state: snapshot.state,
+            tick: snapshot.tick,
+        })
+    }
+
+    /// The part of the game that changed since the rules were known
+    pub fn frame(&self) -> Frame {
+        Frame {
+            tick: self.tick,
+            state: self.state,
+            snakes: self.snakes.clone(),
+            fruits: self.fruits.clone(),
+        }
+    }
+
+    /// A checksum of everything that changes in the game, the RNG included:
+    /// two games with the same rules are the same when their checksums are
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Fnv::default();
+        let frame = serde_json::to_vec(&self.frame()).expect("A frame is always valid JSON");
+        hasher.write(&frame);
+        let (state, inc) = self.rng.state();
+        hasher.write(&state.to_le_bytes());
+        hasher.write(&inc.to_le_bytes());
+        hasher.finish()
+    }
+
+    /// Show the game of a frame, taken from a game with the same rules.
+    /// The frame may come from the network, so it is checked first; the
+    /// RNG is left as it is; a game shown from frames is not played.
+    pub fn apply_frame(&mut self, frame: Frame) -> Result<(), String> {
+        check_pieces(&self.rules, &frame.snakes, &frame.fruits)?;
+        if frame.snakes.len() != self.rules.starts().len() {
+            return Err("the number of snakes doesn't match the rules".to_string());
+        }
+        self.snakes = frame.snakes;
+        self.fruits = frame.fruits;
+        self.state = frame.state;
+        self.tick = frame.tick;
+        Ok(())
+    }
+
+    /// The rules the game is played with
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// The board of the rules
+    pub fn board(&self) -> Board {
+        self.rules.board
+    }
+
+    /// All the snakes, the player's first
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    /// The snake of the player
+    pub fn player(&self) -> &Snake {
+        &self.snakes[0]
+    }
+
+    /// The cell of the player's head
+    pub fn head_pos(&self) -> GridPosition {
+        self.player().head_pos
+    }
+
+    /// The seed the game was started with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The direction the player's snake moves in
+    pub fn dir(&self) -> Direction {
+        self.player().dir
+    }
+
+    /// The fruit of the player, the only one unless `Fruits::Separate`
+    pub fn fruit_pos(&self) -> GridPosition {
+        self.fruits[0]
+    }
+
+    /// The fruit a snake goes for, by its index in `snakes`
+    pub fn fruit_of(&self, snake: usize) -> GridPosition {
+        match self.rules.fruits {
+            Fruits::Shared => self.fruits[0],
+            Fruits::Separate => self.fruits[snake],
+        }
+    }
+
+    /// All the fruits; with `Fruits::Separate` the one of each snake, in the same order
+    pub fn fruits(&self) -> &[GridPosition] {
+        &self.fruits
+    }
+
+    /// The cells of the player's body, from the neck to the tail
+    pub fn body(&self) -> &LinkedList<GridPosition> {
+        &self.player().body
+    }
+
+    /// The length of the snake, head included
+    pub fn length(&self) -> u32 {
+        self.player().length()
+    }
+
+    /// The score of the player
+    pub fn score(&self) -> u32 {
+        self.player().score
+    }
+
+    /// The state of the game
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    /// How the player died, if they did
+    pub fn death(&self) -> Option<Death> {
+        self.player().death
+    }
+
+    /// The number of moves played since the start
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Check if there is a wall on the given cell
+    pub fn is_wall(&self, pos: GridPosition) -> bool {
+        self.walls[pos.index(self.rules.board)]
+    }
+
+    /// Change the state of the game, such as to pause it
+    pub fn set_state(&mut self, state: GameState) {
+        self.state = state;
+    }
+
+    /// Move the fruit to the given position
+    pub fn place_fruit(&mut self, pos: GridPosition) {
+        self.fruits[0] = pos;
+    }
+
+    /// Check if the snake is allowed to turn in the given direction:
+    /// it can't reverse onto its own body.
+    pub fn can_turn(&self, dir: Direction) -> bool {
+        self.player().can_turn(dir)
+    }
+
+    /// Advance the game by one tick.
+    ///
+    /// `input` is the direction the player wants to turn to, if any;
+    /// it is ignored when the snake can't turn that way.
+    /// Nothing happens unless the game is `Running` or `Starting`.
+    pub fn step(&mut self, input: Option<Direction>) -> Vec<Event> {
+        self.step_all(&[input])
+    }
+
+    /// Advance the game by one tick, with the input of each snake, in the order
+    /// of `snakes`. A missing input is no turn.
+    ///
+    /// All the snakes move at once: a snake dies running into a wall, into
+    /// itself or into another snake, and two heads meeting on a cell both die.
+    pub fn step_all(&mut self, inputs: &[Option<Direction>]) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if !self.is_playing() {
+            return events;
+        }
+
+        let dirs_new: Vec<Option<Direction>> = self
+            .snakes
+            .iter()
+            .enumerate()
+            .map(|(i, snake)| {
+                inputs
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .filter(|d| snake.can_turn(*d))
+            })
+            .collect();
+
+        // Eventually change the directions
+        for (snake, dir_new) in self.snakes.iter_mut().zip(dirs_new) {
+            if let Some(dir_new) = dir_new.filter(|_| snake.is_alive()) {
+                snake.dir = dir_new;
+            }
+        }
+
+        // Move the heads
+        let mut moved = Vec::new();
+        for i in 0..self.snakes.len() {
+            let snake = &self.snakes[i];
+            if !snake.is_alive() {
+                continue;
+            }
+            let mut head_pos = snake.head_pos;
+            if !head_pos.try_move_to_direction(snake.dir, self.rules.board, self.rules.boundary)
+                || self.is_wall(head_pos)
+            {
+                // If the snake hits a wall it dies, and it stays where it was
+                self.kill(i, Death::HitWall, &mut events);
+                continue;
+            }
+            let snake = &mut self.snakes[i];
+            snake.body.push_front(snake.head_pos);
+            snake.head_pos = head_pos;
+            events.push(Event::Moved(head_pos));
+            moved.push(i);
+        }
+
+        // The collisions, checked once all the snakes moved
+        let mut deaths = Vec::new();
+        for &i in moved.iter() {
+            let head_pos = self.snakes[i].head_pos;
+            let head_on = moved
+                .iter()
+                .any(|&j| j != i && self.snakes[j].head_pos == head_pos);
+            let hit = self
+                .snakes
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && other.covers(head_pos));
+            if self.snakes[i].body.contains(&head_pos) {
+                // If the snake eats itself it dies
+                deaths.push((i, Death::BitItself));
+            } else if head_on {
+                deaths.push((i, Death::HeadOn));
+            } else if hit {
+                deaths.push((i, Death::HitSnake));
+            }
+        }
+        for (i, death) in deaths {
+            self.kill(i, death, &mut events);
+        }
+
+        // The snakes on their fruit grow, the others move their tail
+        let mut eaten = Vec::new();
+        for &i in moved.iter() {
+            let fruit = match self.rules.fruits {
+                Fruits::Shared => 0,
+                Fruits::Separate => i,
+            };
+            if self.snakes[i].is_alive() && self.snakes[i].head_pos == self.fruits[fruit] {
+                eaten.push((i, fruit));
+            } else {
+                self.snakes[i].body.pop_back();
+            }
+        }
+
+        // The first snake to reach the goal, or to fill the board, wins the round
+        let mut winner = None;
+
+        // If a snake eats a fruit increment its score and its body lenght
+        for (i, fruit) in eaten {
+            let points = self.rules.fruit_points;
+            self.snakes[i].score += points;
+            events.push(Event::FruitEaten { snake: i, points });
+
+            match self.random_free_pos(Some(fruit)) {
+                Some(pos) => self.fruits[fruit] = pos,
+                // If the snake fills the board the player wins
+                None if self.is_playing() && self.snakes.len() == 1 => {
+                    let bonus = VICTORY_BONUS_FRUITS * self.rules.fruit_points;
+                    self.snakes[i].score += bonus;
+                    self.state = GameState::Victory;
+                    events.push(Event::Victory { bonus });
+                }
+                None if self.is_playing() => {
+                    winner = winner.or(Some(i));
+                }
+                None => {}
+            }
+
+            // If the snake reaches the goal the level is complete
+            let snake = &self.snakes[i];
+            let goal_reached = self
+                .rules
+                .goal
+                .is_some_and(|goal| goal.is_reached(snake.length(), snake.score));
+            if self.is_playing() && goal_reached {
+                if self.snakes.len() == 1 {
+                    self.state = GameState::LevelComplete;
+                    events.push(Event::LevelComplete);
+                } else {
+                    winner = winner.or(Some(i));
+                }
+            }
+        }
+
+        // The round is over when a snake wins, or when at most one is left
+        if self.is_playing() && self.snakes.len() > 1 {
+            let alive: Vec<usize> = (0..self.snakes.len())
+                .filter(|&i| self.snakes[i].is_alive())
+                .collect();
+            let round_over = match winner {
+                Some(winner) => Some(Some(winner)),
+                None if alive.len() <= 1 => Some(alive.first().copied()),
+                None => None,
+            };
+            if let Some(winner) = round_over {
+                self.state = GameState::GameOver;
+                events.push(Event::RoundOver { winner });
+            }
+        }
+
+        self.tick += 1;
+
+        events
+    }
+
+    /// Check if the snakes move: in a game or in the attract mode
+    fn is_playing(&self) -> bool {
+        self.state == GameState::Running || self.state == GameState::Starting
+    }
+
+    /// The snake dies; alone, it's game over
+    fn kill(&mut self, snake: usize, death: Death, events: &mut Vec<Event>) {
+        self.snakes[snake].death = Some(death);
+        if self.snakes.len() == 1 {
+            self.state = GameState::GameOver;
+            events.push(Event::GameOver(death));
+        } else {
+            events.push(Event::Died { snake, death });
+        }
+    }
+
+    /// Return a random free position on the grid, or `None` if the snakes fill it.
+    /// The fruits are not free, but the one being replaced, if any.
+    fn random_free_pos(&mut self, replacing: Option<usize>) -> Option<GridPosition> {
+        let board = self.rules.board;
+
+        // Mark the cells taken by the walls, by the snakes and by the other fruits
+        let mut taken = self.walls.clone();
+        for snake in self.snakes.iter() {
+            taken[snake.head_pos.index(board)] = true;
+            for seg in snake.body.iter() {
+                taken[seg.index(board)] = true;
+            }
+        }
+        for (i, fruit) in self.fruits.iter().enumerate() {
+            if Some(i) != replacing {
+                taken[fruit.index(board)] = true;
+            }
+        }
+
+        // Prefer the fruit spawns of the rules, if any is free
+        let mut freepos: Vec<GridPosition> = self
+            .rules
+            .fruit_spawns
+            .iter()
+            .copied()
+            .filter(|p| !taken[p.index(board)])
+            .collect();
+        if !freepos.is_empty() {
+            let i = self.rng.rand_range(0..freepos.len() as u32) as usize;
+            return Some(freepos[i]);
+        }
+
+        for y in 0..board.height {
+            for x in 0..board.width {
+                let p = GridPosition::new(x, y);
+                if !taken[p.index(board)] {
+                    freepos.push(p)
+                }
+            }
+        }
+
+        // This happens only when the snake body fills all the grid
+        if freepos.is_empty() {
+            return None;
+        }
+
+        let i = self.rng.rand_range(0..freepos.len() as u32) as usize;
+
+        Some(freepos[i])
+    }
+}
+
+/// Check that snakes and fruits from outside fit the rules
+fn check_pieces(rules: &Rules, snakes: &[Snake], fruits: &[GridPosition]) -> Result<(), String> {
+    let board = rules.board;
+    let inside = |pos: &GridPosition| pos.x < board.width && pos.y < board.height;
+    if !fruits.iter().all(inside) {
+        return Err("the fruit is outside the board".to_string());
+    }
+    for snake in snakes.iter() {
+        if !inside(&snake.head_pos) || !snake.body.iter().all(inside) {
+            return Err("the snake is outside the board".to_string());
+        }
+        if snake.body.len() >= board.cells() {
+            return Err("the snake is longer than the board".to_string());
+        }
+    }
+    if snakes.len() > 1 && snakes.len() != rules.starts().len() {
+        return Err("the number of snakes doesn't match the rules".to_string());
+    }
+    let expected_fruits = match rules.fruits {
+        Fruits::Separate => snakes.len(),
+        Fruits::Shared => 1,
+    };
+    if fruits.len() != expected_fruits {
+        return Err("the number of fruits doesn't match the rules".to_string());
+    }
+    Ok(())
+}
+
+/// The 64-bit FNV-1a hash, the same on every machine, unlike `DefaultHasher`
+pub(crate) struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
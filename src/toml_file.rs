@@ -0,0 +1,16 @@
+//! The files the game writes as TOML: the high scores, the saved game, the
+//! progress in the campaign and the keys.
+
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Save a value to a TOML file, creating its directory if needed
+pub(crate) fn save<T: Serialize + ?Sized>(path: &Path, value: &T) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(value).map_err(io::Error::other)?;
+    fs::write(path, text)
+}
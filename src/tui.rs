@@ -0,0 +1,228 @@
+//! The game drawn with text, for front ends in a terminal.
+//!
+//! `tiles` turns a `Simulation` into rows of `Tile`s, the board framed by its
+//! edges, and a `Charset` gives the characters of each tile: two for a cell,
+//! so that the cells look square, one for the sides of the frame. The edges
+//! that wrap around are drawn dotted. The colors are left to the front end.
+//!
+//! ```text
+//! ┌┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┐
+//! ┆                ┆
+//! ┆  ██████▶▶    ● ┆
+//! ┆                ┆
+//! └┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┘
+//! ```
+
+use crate::simulation::Direction;
+use crate::simulation::Fruits;
+use crate::simulation::Goal;
+use crate::simulation::GridPosition;
+use crate::simulation::Simulation;
+
+/// What a place of the screen shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    /// A fruit, with the snake it's for when each snake has its own
+    Fruit {
+        owner: Option<usize>,
+    },
+    Head {
+        snake: usize,
+        dir: Direction,
+        alive: bool,
+    },
+    Body {
+        snake: usize,
+        alive: bool,
+    },
+    /// A part of the frame around the board
+    Edge(Edge),
+}
+
+/// The parts of the frame around the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    /// The top or the bottom edge, over a cell
+    Horizontal {
+        wraps: bool,
+    },
+    /// The left or the right edge
+    Vertical {
+        wraps: bool,
+    },
+}
+
+/// The characters the tiles are drawn with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Charset {
+    /// Blocks, arrows and box drawing
+    #[default]
+    Unicode,
+    /// For the terminals that only have ASCII
+    Ascii,
+}
+
+impl Charset {
+    /// The characters of a tile: two for a cell, one for the sides of the frame
+    pub fn glyph(&self, tile: Tile) -> &'static str {
+        match self {
+            Charset::Unicode => match tile {
+                Tile::Empty => "  ",
+                Tile::Wall => "▓▓",
+                Tile::Fruit { .. } => "● ",
+                Tile::Head { dir, .. } => match dir {
+                    Direction::Up => "▲▲",
+                    Direction::Down => "▼▼",
+                    Direction::Left => "◀◀",
+                    Direction::Right => "▶▶",
+                },
+                Tile::Body { alive: true, .. } => "██",
+                Tile::Body { alive: false, .. } => "░░",
+                Tile::Edge(edge) => match edge {
+                    Edge::TopLeft => "┌",
+                    Edge::TopRight => "┐",
+                    Edge::BottomLeft => "└",
+                    Edge::BottomRight => "┘",
+                    Edge::Horizontal { wraps: false } => "──",
+                    Edge::Horizontal { wraps: true } => "┄┄",
+                    Edge::Vertical { wraps: false } => "│",
+                    Edge::Vertical { wraps: true } => "┆",
+                },
+            },
+            Charset::Ascii => match tile {
+                Tile::Empty => "  ",
+                Tile::Wall => "##",
+                Tile::Fruit { .. } => "()",
+                Tile::Head { dir, .. } => match dir {
+                    Direction::Up => "^^",
+                    Direction::Down => "vv",
+                    Direction::Left => "<<",
+                    Direction::Right => ">>",
+                },
+                Tile::Body { alive: true, .. } => "[]",
+                Tile::Body { alive: false, .. } => "::",
+                Tile::Edge(edge) => match edge {
+                    Edge::TopLeft | Edge::TopRight | Edge::BottomLeft | Edge::BottomRight => "+",
+                    Edge::Horizontal { wraps: false } => "--",
+                    Edge::Horizontal { wraps: true } => "..",
+                    Edge::Vertical { wraps: false } => "|",
+                    Edge::Vertical { wraps: true } => ":",
+                },
+            },
+        }
+    }
+}
+
+/// The board and its frame, row by row from the top. The heads are drawn
+/// over the bodies, and the bodies over the fruits and the walls.
+pub fn tiles(sim: &Simulation) -> Vec<Vec<Tile>> {
+    let board = sim.board();
+    let boundary = sim.rules().boundary;
+    let (width, height) = (board.width as usize, board.height as usize);
+    let mut cells = vec![vec![Tile::Empty; width]; height];
+    let mut set = |pos: GridPosition, tile: Tile| {
+        if let Some(cell) = cells
+            .get_mut(pos.y as usize)
+            .and_then(|row| row.get_mut(pos.x as usize))
+        {
+            *cell = tile;
+        }
+    };
+
+    for wall in sim.rules().walls.iter() {
+        set(*wall, Tile::Wall);
+    }
+    for (i, fruit) in sim.fruits().iter().enumerate() {
+        let owner = match sim.rules().fruits {
+            Fruits::Shared => None,
+            Fruits::Separate => Some(i),
+        };
+        set(*fruit, Tile::Fruit { owner });
+    }
+    for (snake, s) in sim.snakes().iter().enumerate() {
+        let alive = s.is_alive();
+        for seg in s.body().iter() {
+            set(*seg, Tile::Body { snake, alive });
+        }
+    }
+    for (snake, s) in sim.snakes().iter().enumerate() {
+        let head = Tile::Head {
+            snake,
+            dir: s.dir(),
+            alive: s.is_alive(),
+        };
+        set(s.head_pos(), head);
+    }
+
+    let horizontal = Tile::Edge(Edge::Horizontal {
+        wraps: boundary.wraps_y(),
+    });
+    let vertical = Tile::Edge(Edge::Vertical {
+        wraps: boundary.wraps_x(),
+    });
+    let frame = |left: Edge, right: Edge| {
+        let mut row = vec![Tile::Edge(left)];
+        row.extend(std::iter::repeat_n(horizontal, width));
+        row.push(Tile::Edge(right));
+        row
+    };
+
+    let mut rows = vec![frame(Edge::TopLeft, Edge::TopRight)];
+    for row in cells {
+        let mut line = vec![vertical];
+        line.extend(row);
+        line.push(vertical);
+        rows.push(line);
+    }
+    rows.push(frame(Edge::BottomLeft, Edge::BottomRight));
+    rows
+}
+
+/// The board and its frame as lines of text, without colors
+pub fn render(sim: &Simulation, charset: Charset) -> Vec<String> {
+    tiles(sim)
+        .into_iter()
+        .map(|row| row.into_iter().map(|tile| charset.glyph(tile)).collect())
+        .collect()
+}
+
+/// The width and the height in characters of the board and its frame
+pub fn size(sim: &Simulation) -> (u16, u16) {
+    let board = sim.board();
+    let width = board.width * 2 + 2;
+    let height = board.height + 2;
+    (
+        width.min(u16::MAX as u32) as u16,
+        height.min(u16::MAX as u32) as u16,
+    )
+}
+
+/// The line under the board: the scores with the names of the players by
+/// snake, the goal of the level if any, and the tick
+pub fn status(sim: &Simulation, names: &[String]) -> String {
+    let mut parts = vec![match sim.snakes() {
+        [player] => format!("Points: {}", player.score()),
+        snakes => snakes
+            .iter()
+            .enumerate()
+            .map(|(i, snake)| {
+                let name = names.get(i).cloned().unwrap_or(format!("P{}", i + 1));
+                format!("{}: {}", name, snake.score())
+            })
+            .collect::<Vec<_>>()
+            .join("  "),
+    }];
+    match sim.rules().goal {
+        Some(Goal::Length(l)) => parts.push(format!("Length: {} / {}", sim.length(), l)),
+        Some(Goal::Score(s)) => parts.push(format!("Goal: {} / {}", sim.score(), s)),
+        None => {}
+    }
+    parts.push(format!("Tick {}", sim.tick()));
+    parts.join("   ")
+}
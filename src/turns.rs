@@ -0,0 +1,77 @@
+//! The turns asked by the player, waiting for the next ticks.
+//!
+//! Keys may be pressed faster than the snake moves: a quick Up then Left
+//! between two ticks must give two turns, one per tick, not just the last one.
+//! Each turn is checked against the one queued before it, so Up then Down
+//! can't sneak a U-turn through while the snake still moves sideways.
+
+use crate::simulation::Direction;
+use crate::simulation::Simulation;
+use crate::simulation::Snake;
+use std::collections::VecDeque;
+
+/// The most turns waiting at once; more are ignored, not to lag behind the keys
+pub const MAX_QUEUED_TURNS: usize = 3;
+
+/// The turns waiting to be given to `Simulation::step`, one per tick
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TurnQueue {
+    turns: VecDeque<Direction>,
+}
+
+impl TurnQueue {
+    pub fn new() -> Self {
+        TurnQueue::default()
+    }
+
+    /// Queue a turn of the snake of the given simulation.
+    /// Return `false` if it's ignored: the queue is full, the snake already goes
+    /// that way after the queued turns, or it would reverse onto its body.
+    pub fn push(&mut self, sim: &Simulation, dir: Direction) -> bool {
+        self.push_for(sim.player(), dir)
+    }
+
+    /// Queue a turn of the given snake, as `push` does for the player's
+    pub fn push_for(&mut self, snake: &Snake, dir: Direction) -> bool {
+        let last = self.turns.back().copied().unwrap_or(snake.dir());
+        if self.turns.len() >= MAX_QUEUED_TURNS
+            || dir == last
+            || (!snake.body().is_empty() && last.opposite(dir))
+        {
+            return false;
+        }
+        self.turns.push_back(dir);
+        true
+    }
+
+    /// Take the turn for the next tick
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.turns.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// The queued turns, the next one first
+    pub fn to_vec(&self) -> Vec<Direction> {
+        self.turns.iter().copied().collect()
+    }
+}
+
+impl From<Vec<Direction>> for TurnQueue {
+    /// A queue with the given turns, as they were queued: they are not checked again
+    fn from(turns: Vec<Direction>) -> Self {
+        TurnQueue {
+            turns: turns.into(),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Serialize a `u64` as a string, for the fields with `#[serde(with = "crate::u64_string")]`.
+//!
+//! TOML integers are signed, so the largest seeds don't fit in them.
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serializer;
+
+pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
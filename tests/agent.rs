@@ -0,0 +1,131 @@
+use snake_ggez::agent::AgentError;
+use snake_ggez::agent::AgentPilot;
+use snake_ggez::agent::Message;
+use snake_ggez::agent::Reply;
+use snake_ggez::agent::SnakeAgent;
+use snake_ggez::agent::Subprocess;
+use snake_ggez::agent::View;
+use snake_ggez::autopilot::Autopilot;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::simulation::Start;
+use std::time::Duration;
+
+/// A running game with a rival and a wall
+fn running_sim() -> Simulation {
+    let rules = Rules {
+        walls: vec![GridPosition::new(10, 10)],
+        rivals: vec![Start {
+            pos: GridPosition::new(20, 15),
+            dir: Direction::Left,
+        }],
+        ..Rules::default()
+    };
+    rules.validate().unwrap();
+    let mut sim = Simulation::new(rules, 42);
+    sim.restart(42);
+    sim
+}
+
+/// Always goes down
+struct Down;
+
+impl SnakeAgent for Down {
+    fn act(&mut self, _view: &View) -> Direction {
+        Direction::Down
+    }
+}
+
+#[test]
+fn the_view_shows_the_whole_board() {
+    let mut sim = running_sim();
+    sim.step_all(&[None, None]);
+    let view = View::new(&sim, 1);
+
+    assert_eq!(view.you, 1);
+    assert_eq!(view.tick, 1);
+    assert_eq!((view.width, view.height), (25, 20));
+    assert_eq!(view.walls, [GridPosition::new(10, 10)]);
+    assert_eq!(view.fruits, sim.fruits());
+    assert_eq!(view.snakes.len(), 2);
+    assert_eq!(view.me().head, GridPosition::new(19, 15));
+    assert_eq!(view.me().dir, Direction::Left);
+    assert!(view.me().alive);
+}
+
+#[test]
+fn messages_are_json_lines() {
+    let view = View::new(&running_sim(), 0);
+    let line = serde_json::to_string(&Message::Turn(view.clone())).unwrap();
+    assert!(
+        line.starts_with(r#"{"type":"turn","you":0,"tick":0,"#),
+        "{}",
+        line
+    );
+    assert!(!line.contains('\n'));
+    assert_eq!(
+        serde_json::from_str::<Message>(&line).unwrap(),
+        Message::Turn(view)
+    );
+
+    let reply: Reply = serde_json::from_str(r#"{"move":"Up"}"#).unwrap();
+    assert_eq!(reply.dir, Some(Direction::Up));
+    let reply: Reply = serde_json::from_str("{}").unwrap();
+    assert_eq!(reply.dir, None);
+    assert!(serde_json::from_str::<Reply>(r#"{"move":"North"}"#).is_err());
+}
+
+#[test]
+fn agents_drive_snakes_like_the_autopilot() {
+    let mut sim = running_sim();
+    let mut pilot = AgentPilot(Down);
+    assert_eq!(pilot.choose(&sim, 0), Some(Direction::Down));
+    assert_eq!(pilot.choose(&sim, 1), Some(Direction::Down));
+
+    // Going down already: no turn
+    sim.step_all(&[Some(Direction::Down), None]);
+    assert_eq!(pilot.choose(&sim, 0), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn subprocesses_answer_on_their_output() {
+    let view = View::new(&running_sim(), 0);
+    let mut agent = Subprocess::spawn("sh tests/agents/down.sh", Duration::from_secs(5)).unwrap();
+    for _ in 0..3 {
+        assert_eq!(agent.act(&view), Direction::Down);
+    }
+    agent.end(&view);
+    // Ready for another game
+    assert_eq!(agent.act(&view), Direction::Down);
+    assert!(agent.error().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_subprocesses_lose_control() {
+    let view = View::new(&running_sim(), 0);
+    let straight = view.me().dir;
+
+    let mut silent = Subprocess::spawn("sleep 10", Duration::from_millis(100)).unwrap();
+    assert_eq!(silent.act(&view), straight);
+    assert!(matches!(silent.error(), Some(AgentError::Timeout(_))));
+
+    let mut chatty =
+        Subprocess::spawn("sh tests/agents/chatty.sh", Duration::from_secs(5)).unwrap();
+    assert_eq!(chatty.act(&view), straight);
+    assert!(matches!(chatty.error(), Some(AgentError::Parse(..))));
+    // Once out, it's not asked anymore
+    assert_eq!(chatty.act(&view), straight);
+
+    assert!(matches!(
+        Subprocess::spawn("", Duration::from_secs(1)),
+        Err(AgentError::NoCommand)
+    ));
+    assert!(matches!(
+        Subprocess::spawn("no-such-agent-program", Duration::from_secs(1)),
+        Err(AgentError::Spawn(..))
+    ));
+}
@@ -0,0 +1,5 @@
+#!/bin/sh
+# An agent that doesn't answer with moves
+while read -r line; do
+    echo hello
+done
@@ -0,0 +1,8 @@
+#!/bin/sh
+# An agent that always turns down
+while read -r line; do
+    case "$line" in
+        *'"type":"end"'*) continue ;;
+    esac
+    echo '{"move":"Down"}'
+done
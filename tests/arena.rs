@@ -0,0 +1,141 @@
+use snake_ggez::arena::Arena;
+use snake_ggez::arena::ArenaError;
+use snake_ggez::arena::Entrant;
+use snake_ggez::arena::Pilot;
+use snake_ggez::autopilot::Strategy;
+use snake_ggez::bot::Difficulty;
+use snake_ggez::replay::ReplayPlayer;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use std::time::Duration;
+
+/// A short tournament on a small board
+fn tournament(entrants: &[&str], games: u64) -> Arena {
+    let rules = Rules {
+        board: Board::new(12, 12).unwrap(),
+        boundary: Boundary::Walls,
+        ..Rules::default()
+    };
+    let entrants = entrants.iter().map(|e| e.parse().unwrap()).collect();
+    let mut arena = Arena::new(rules, entrants, games);
+    arena.seed = 7;
+    arena.max_ticks = 2000;
+    arena
+}
+
+#[test]
+fn entrants_are_parsed() {
+    let greedy: Entrant = "greedy".parse().unwrap();
+    assert_eq!(greedy.name, "greedy");
+    assert_eq!(greedy.pilot, Pilot::Bot(Difficulty::Greedy));
+
+    let cycle: Entrant = "slow=cycle".parse().unwrap();
+    assert_eq!(cycle.name, "slow");
+    assert_eq!(cycle.pilot, Pilot::Autopilot(Strategy::Cycle));
+
+    let agent: Entrant = "me=agent:python3 agents/greedy.py".parse().unwrap();
+    assert_eq!(agent.name, "me");
+    assert_eq!(
+        agent.pilot,
+        Pilot::Agent("python3 agents/greedy.py".to_string())
+    );
+    // An '=' in the command doesn't name the entrant
+    let agent: Entrant = "agent:run --level=box".parse().unwrap();
+    assert_eq!(agent.pilot, Pilot::Agent("run --level=box".to_string()));
+
+    assert!("random".parse::<Entrant>().is_err());
+    assert!("agent:".parse::<Entrant>().is_err());
+}
+
+#[test]
+fn entrants_take_turns_at_the_starts() {
+    let arena = tournament(&["greedy", "pathfinding", "cautious"], 3);
+    assert_eq!(arena.seats(0), [0, 1, 2]);
+    assert_eq!(arena.seats(1), [1, 2, 0]);
+    assert_eq!(arena.seats(5), [2, 0, 1]);
+    assert_eq!(arena.game_rules().unwrap().rivals.len(), 2);
+
+    assert!(matches!(
+        tournament(&[], 1).run(),
+        Err(ArenaError::Entrants(_))
+    ));
+    assert!(matches!(
+        tournament(&["greedy"; 5], 1).run(),
+        Err(ArenaError::Entrants(_))
+    ));
+}
+
+#[test]
+fn every_game_is_counted() {
+    let mut arena = tournament(&["greedy", "cautious"], 12);
+    arena.threads = 3;
+    let report = arena.run().unwrap();
+
+    assert_eq!(report.games, 12);
+    let wins: u64 = report.standings.iter().map(|s| s.wins).sum();
+    assert_eq!(wins + report.draws, 12);
+    for s in report.standings.iter() {
+        assert_eq!(s.games, 12);
+        let c = s.crashes;
+        assert_eq!(
+            c.bit_itself + c.hit_wall + c.hit_snake + c.head_on + c.alive,
+            12
+        );
+        assert_eq!(s.win_rate, s.wins as f64 / 12.0);
+        assert!(s.mean_length >= 1.0);
+    }
+
+    assert!(report.table().contains("cautious"));
+    let csv = report.csv();
+    assert_eq!(csv.lines().count(), 3);
+    assert!(csv.lines().nth(1).unwrap().starts_with("greedy,greedy,12,"));
+    let json: serde_json::Value = serde_json::from_str(&report.json()).unwrap();
+    assert_eq!(json["games"], 12);
+    assert_eq!(json["standings"][1]["name"], "cautious");
+}
+
+#[test]
+fn reports_dont_depend_on_the_threads() {
+    let mut arena = tournament(&["pathfinding", "safe-path", "greedy"], 9);
+    let alone = arena.run().unwrap();
+    arena.threads = 4;
+    assert_eq!(arena.run().unwrap(), alone);
+}
+
+#[test]
+fn notable_games_play_back() {
+    let report = tournament(&["greedy", "pathfinding"], 6).run().unwrap();
+    assert!(!report.notable.is_empty());
+
+    for notable in report.notable.iter() {
+        let replay = notable.replay.clone();
+        let mut sim = Simulation::new(replay.rules.clone(), replay.seed);
+        sim.restart(replay.seed);
+        let mut player = ReplayPlayer::new(replay);
+        while !player.is_finished(sim.tick()) {
+            let inputs = player.inputs(sim.tick());
+            sim.step_all(&inputs);
+        }
+
+        assert_eq!(sim.tick(), notable.result.ticks, "{}", notable.kind);
+        for (snake, result) in sim.snakes().iter().zip(notable.result.snakes.iter()) {
+            assert_eq!(snake.score(), result.score, "{}", notable.kind);
+            assert_eq!(snake.death(), result.death, "{}", notable.kind);
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn agents_losing_control_are_counted() {
+    let mut arena = tournament(&["late=agent:sleep 10", "greedy"], 2);
+    arena.agent_timeout = Duration::from_millis(50);
+    let report = arena.run().unwrap();
+    assert_eq!(report.standings[0].faults, 2);
+    assert_eq!(report.standings[1].faults, 0);
+
+    let arena = tournament(&["agent:no-such-agent-program"], 1);
+    assert!(matches!(arena.run(), Err(ArenaError::Agent(..))));
+}
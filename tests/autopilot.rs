@@ -0,0 +1,91 @@
+use snake_ggez::autopilot::Autopilot;
+use snake_ggez::autopilot::Cycle;
+use snake_ggez::autopilot::SafePath;
+use snake_ggez::autopilot::Strategy;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+
+/// Let the autopilot play a new game until it ends, or for at most `ticks` moves
+fn autoplay(pilot: &mut dyn Autopilot, rules: Rules, seed: u64, ticks: u64) -> Simulation {
+    rules.validate().unwrap();
+    let mut sim = Simulation::new(rules, seed);
+    sim.restart(seed);
+    while sim.state() == GameState::Running && sim.tick() < ticks {
+        let input = pilot.choose(&sim, 0);
+        sim.step(input);
+    }
+    sim
+}
+
+#[test]
+fn strategies_have_names() {
+    for strategy in Strategy::ALL {
+        assert_eq!(strategy.to_string().parse(), Ok(strategy));
+    }
+    assert!("random".parse::<Strategy>().is_err());
+}
+
+#[test]
+fn the_cycle_fills_the_board() {
+    for (width, height) in [(6, 6), (6, 7), (7, 6)] {
+        let rules = Rules {
+            board: Board::new(width, height).unwrap(),
+            boundary: Boundary::Walls,
+            start_pos: GridPosition::new(2, 2),
+            ..Rules::default()
+        };
+        let cells = width * height;
+        let sim = autoplay(&mut Cycle::default(), rules, 5, (cells * cells).into());
+        assert_eq!(sim.state(), GameState::Victory, "{}x{}", width, height);
+        assert_eq!(sim.death(), None);
+        assert_eq!(sim.length(), cells);
+    }
+}
+
+#[test]
+fn the_cycle_falls_back_on_odd_boards() {
+    let rules = Rules {
+        board: Board::new(7, 7).unwrap(),
+        boundary: Boundary::Walls,
+        start_pos: GridPosition::new(3, 3),
+        ..Rules::default()
+    };
+    let sim = autoplay(&mut Cycle::default(), rules, 5, 200);
+    assert!(sim.length() > 1);
+}
+
+#[test]
+fn the_safe_path_fills_most_of_the_board() {
+    for seed in 0..5 {
+        let rules = Rules {
+            board: Board::new(10, 10).unwrap(),
+            boundary: Boundary::Walls,
+            ..Rules::default()
+        };
+        let sim = autoplay(&mut SafePath, rules, seed, 3000);
+        assert!(sim.length() >= 75, "seed {}: {}", seed, sim.length());
+    }
+}
+
+#[test]
+fn the_attract_mode_plays_by_the_rules() {
+    let rules = Rules {
+        board: Board::new(5, 5).unwrap(),
+        boundary: Boundary::Walls,
+        start_pos: GridPosition::new(2, 2),
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 3);
+    assert_eq!(sim.state(), GameState::Starting);
+    assert_eq!(sim.head_pos(), GridPosition::new(2, 2));
+
+    // Nobody steers: the snake runs into the wall and the demo is over
+    for _ in 0..3 {
+        sim.step(None);
+    }
+    assert_eq!(sim.state(), GameState::GameOver);
+}
@@ -0,0 +1,141 @@
+use snake_ggez::bot::bot_starts;
+use snake_ggez::bot::Bot;
+use snake_ggez::bot::Difficulty;
+use snake_ggez::bot::MAX_BOTS;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::simulation::Start;
+
+/// A running game with these rules and the fruit there
+fn running_sim(rules: Rules, fruit: GridPosition) -> Simulation {
+    rules.validate().unwrap();
+    let mut sim = Simulation::new(rules, 42);
+    sim.restart(42);
+    sim.place_fruit(fruit);
+    sim
+}
+
+#[test]
+fn difficulties_have_names() {
+    for difficulty in Difficulty::ALL {
+        assert_eq!(difficulty.to_string().parse(), Ok(difficulty));
+    }
+    assert!("expert".parse::<Difficulty>().is_err());
+}
+
+#[test]
+fn bots_start_in_the_other_corners() {
+    let rules = Rules {
+        start_length: 3,
+        rivals: bot_starts(&Rules::default(), MAX_BOTS),
+        ..Rules::default()
+    };
+    rules.validate().unwrap();
+    let heads: Vec<GridPosition> = rules.rivals.iter().map(|s| s.pos).collect();
+    assert_eq!(
+        heads,
+        [
+            GridPosition::new(20, 15),
+            GridPosition::new(20, 4),
+            GridPosition::new(4, 15)
+        ]
+    );
+}
+
+#[test]
+fn every_bot_avoids_a_wall() {
+    let rules = Rules {
+        boundary: Boundary::Walls,
+        start_pos: GridPosition::new(24, 10),
+        ..Rules::default()
+    };
+    let sim = running_sim(rules, GridPosition::new(24, 2));
+    for difficulty in Difficulty::ALL {
+        let turn = Bot::new(difficulty).choose(&sim, 0);
+        assert_eq!(turn, Some(Direction::Up), "{}", difficulty);
+    }
+}
+
+#[test]
+fn greedy_heads_for_the_fruit() {
+    let sim = running_sim(Rules::default(), GridPosition::new(4, 10));
+    let bot = Bot::new(Difficulty::Greedy);
+    assert_eq!(bot.choose(&sim, 0), Some(Direction::Down));
+}
+
+#[test]
+fn pathfinding_goes_around_the_walls() {
+    // A wall between the snake and the fruit, open only at the bottom
+    let rules = Rules {
+        boundary: Boundary::Walls,
+        start_pos: GridPosition::new(5, 5),
+        walls: (0..15).map(|y| GridPosition::new(10, y)).collect(),
+        ..Rules::default()
+    };
+    let mut sim = running_sim(rules, GridPosition::new(15, 5));
+    let bot = Bot::new(Difficulty::Pathfinding);
+
+    for _ in 0..30 {
+        sim.step(bot.choose(&sim, 0));
+        if sim.score() > 0 {
+            break;
+        }
+    }
+    assert_eq!(sim.state(), GameState::Running);
+    assert!(sim.score() > 0);
+}
+
+#[test]
+fn cautious_keeps_away_from_the_heads() {
+    // The shortest way goes next to the head of the rival
+    let rules = Rules {
+        start_pos: GridPosition::new(5, 5),
+        rivals: vec![Start {
+            pos: GridPosition::new(7, 5),
+            dir: Direction::Up,
+        }],
+        ..Rules::default()
+    };
+    let sim = running_sim(rules, GridPosition::new(10, 5));
+
+    assert_eq!(Bot::new(Difficulty::Pathfinding).choose(&sim, 0), None);
+    assert_eq!(
+        Bot::new(Difficulty::Cautious).choose(&sim, 0),
+        Some(Direction::Up)
+    );
+}
+
+#[test]
+fn bots_play_the_same_game_twice() {
+    let play = |difficulty: Difficulty| {
+        let rules = Rules {
+            start_length: 3,
+            rivals: bot_starts(&Rules::default(), MAX_BOTS),
+            ..Rules::default()
+        };
+        let mut sim = Simulation::new(rules, 7);
+        sim.restart(7);
+        let bot = Bot::new(difficulty);
+        while sim.state() == GameState::Running && sim.tick() < 2000 {
+            let inputs: Vec<Option<Direction>> = (0..sim.snakes().len())
+                .map(|i| bot.choose(&sim, i))
+                .collect();
+            sim.step_all(&inputs);
+        }
+        sim.snapshot()
+    };
+
+    for difficulty in Difficulty::ALL {
+        let game = play(difficulty);
+        assert!(
+            game.score > 0 || game.rivals.iter().any(|s| s.score() > 0),
+            "{}",
+            difficulty
+        );
+        assert_eq!(game, play(difficulty));
+    }
+}
@@ -0,0 +1,246 @@
+use snake_ggez::bot::bot_starts;
+use snake_ggez::broadcast::BroadcastMessage;
+use snake_ggez::broadcast::Broadcaster;
+use snake_ggez::broadcast::Spectator;
+use snake_ggez::broadcast::PROTOCOL;
+use snake_ggez::net::with_default_port;
+use snake_ggez::net::NetError;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A broadcast on the loopback, on a port chosen by the system
+fn broadcaster() -> Broadcaster {
+    Broadcaster::bind("127.0.0.1:0").unwrap()
+}
+
+/// Poll until `done` says so, or fail after a while
+fn wait_for(mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// A game of two snakes, started
+fn game(seed: u64) -> Simulation {
+    let mut rules = Rules::default();
+    rules.rivals = bot_starts(&rules, 1);
+    let mut sim = Simulation::new(rules, seed);
+    sim.restart(seed);
+    sim
+}
+
+fn names() -> Vec<String> {
+    vec!["Ada".to_string(), "Bot 1".to_string()]
+}
+
+/// Publish the game until the spectator sees it as it is
+fn watch(spectator: &mut Spectator, broadcaster: &mut Broadcaster, sim: &Simulation) {
+    wait_for(|| {
+        broadcaster.publish(sim.rules(), &names(), sim.frame());
+        spectator.poll().unwrap();
+        spectator
+            .game()
+            .is_some_and(|game| game.frame() == sim.frame())
+    });
+}
+
+#[test]
+fn broadcasts_get_their_own_port() {
+    assert_eq!(with_default_port("7000", "0.0.0.0", 7358), "0.0.0.0:7000");
+    assert_eq!(
+        with_default_port("example.org", "0.0.0.0", 7358),
+        "example.org:7358"
+    );
+    assert_eq!(
+        with_default_port("10.0.0.2:7000", "0.0.0.0", 7358),
+        "10.0.0.2:7000"
+    );
+}
+
+#[test]
+fn spectators_follow_the_game() {
+    let mut broadcaster = broadcaster();
+    let mut spectator = Spectator::connect(broadcaster.local_addr().unwrap()).unwrap();
+    let mut sim = game(3);
+    watch(&mut spectator, &mut broadcaster, &sim);
+    assert_eq!(broadcaster.spectators(), 1);
+    assert_eq!(spectator.players(), names());
+    assert_eq!(spectator.game().unwrap().rules(), sim.rules());
+
+    for i in 0..20 {
+        let turn = [Direction::Down, Direction::Right][i % 2];
+        sim.step_all(&[Some(turn), None]);
+        watch(&mut spectator, &mut broadcaster, &sim);
+        let game = spectator.game().unwrap();
+        assert_eq!(game.tick(), sim.tick());
+        assert_eq!(game.snakes(), sim.snakes());
+    }
+}
+
+#[test]
+fn late_spectators_get_a_snapshot() {
+    let mut broadcaster = broadcaster();
+    let mut sim = game(5);
+    for _ in 0..30 {
+        sim.step_all(&[None, None]);
+        broadcaster.publish(sim.rules(), &names(), sim.frame());
+    }
+
+    let mut late = Spectator::connect(broadcaster.local_addr().unwrap()).unwrap();
+    watch(&mut late, &mut broadcaster, &sim);
+    let game = late.game().unwrap();
+    assert_eq!(game.tick(), 30);
+    assert_eq!(game.snakes(), sim.snakes());
+    assert_eq!(late.players(), names());
+}
+
+#[test]
+fn a_new_game_sends_a_snapshot() {
+    let mut broadcaster = broadcaster();
+    let mut spectator = Spectator::connect(broadcaster.local_addr().unwrap()).unwrap();
+    let sim = game(1);
+    watch(&mut spectator, &mut broadcaster, &sim);
+
+    // The next level of the campaign, on another board
+    let rules = Rules {
+        board: Board::new(12, 8).unwrap(),
+        ..Rules::default()
+    };
+    let mut next = Simulation::new(rules, 2);
+    next.restart(2);
+    let players = vec!["Ada".to_string()];
+    wait_for(|| {
+        broadcaster.publish(next.rules(), &players, next.frame());
+        spectator.poll().unwrap();
+        spectator.players() == players
+    });
+    let game = spectator.game().unwrap();
+    assert_eq!(game.rules(), next.rules());
+    assert_eq!(game.frame(), next.frame());
+}
+
+#[test]
+fn the_game_is_sent_when_it_changes() {
+    let mut broadcaster = broadcaster();
+    let stream = TcpStream::connect(broadcaster.local_addr().unwrap()).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut read = || {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(n) if n > 0 => Some(serde_json::from_str::<BroadcastMessage>(&line).unwrap()),
+            _ => None,
+        }
+    };
+
+    let mut sim = game(8);
+    wait_for(|| {
+        broadcaster.publish(sim.rules(), &names(), sim.frame());
+        broadcaster.spectators() == 1
+    });
+    assert!(matches!(read(), Some(BroadcastMessage::Snapshot { .. })));
+
+    // The same game again sends nothing
+    broadcaster.publish(sim.rules(), &names(), sim.frame());
+    assert_eq!(read(), None);
+
+    sim.step_all(&[None, None]);
+    broadcaster.publish(sim.rules(), &names(), sim.frame());
+    broadcaster.publish(sim.rules(), &names(), sim.frame());
+    assert_eq!(read(), Some(BroadcastMessage::Frame(sim.frame())));
+    assert_eq!(read(), None);
+}
+
+#[test]
+fn spectators_wait_for_the_first_game() {
+    // Nothing is published
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let result = Spectator::watch(listener.local_addr().unwrap(), Duration::from_millis(100));
+    assert!(matches!(result, Err(NetError::Io(_))));
+
+    let mut broadcaster = broadcaster();
+    let addr = broadcaster.local_addr().unwrap();
+    let mut sim = game(6);
+    sim.set_state(GameState::Quitting);
+    let publisher = thread::spawn(move || {
+        wait_for(|| {
+            broadcaster.publish(sim.rules(), &names(), sim.frame());
+            broadcaster.spectators() == 1
+        });
+    });
+    let spectator = Spectator::watch(addr, Duration::from_secs(5)).unwrap();
+    publisher.join().unwrap();
+
+    // The spectators see the quit screen as a pause
+    assert_eq!(spectator.game().unwrap().state(), GameState::Paused);
+    assert_eq!(spectator.players(), names());
+}
+
+#[test]
+fn other_versions_are_not_watched() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut spectator = Spectator::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let sim = game(4);
+    let snapshot = BroadcastMessage::Snapshot {
+        protocol: 999,
+        rules: sim.rules().clone(),
+        players: names(),
+        frame: sim.frame(),
+    };
+    serde_json::to_writer(&mut stream, &snapshot).unwrap();
+    writeln!(stream).unwrap();
+
+    let mut result = Ok(false);
+    wait_for(|| {
+        result = spectator.poll();
+        !matches!(result, Ok(false))
+    });
+    assert!(matches!(result, Err(NetError::Invalid(_))));
+    assert!(spectator.game().is_none());
+}
+
+#[test]
+fn games_that_cant_be_played_are_not_watched() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut spectator = Spectator::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    // A wall off the board
+    let sim = game(4);
+    let mut rules = sim.rules().clone();
+    rules.walls.push(GridPosition::new(500, 2));
+    let snapshot = BroadcastMessage::Snapshot {
+        protocol: PROTOCOL,
+        rules,
+        players: names(),
+        frame: sim.frame(),
+    };
+    serde_json::to_writer(&mut stream, &snapshot).unwrap();
+    writeln!(stream).unwrap();
+
+    let mut result = Ok(false);
+    wait_for(|| {
+        result = spectator.poll();
+        !matches!(result, Ok(false))
+    });
+    assert!(matches!(result, Err(NetError::Invalid(_))));
+    assert!(spectator.game().is_none());
+}
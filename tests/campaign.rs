@@ -0,0 +1,89 @@
+use snake_ggez::campaign::Campaign;
+use snake_ggez::campaign::Progress;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Event;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Goal;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use std::path::Path;
+
+#[test]
+fn reaching_the_goal_completes_the_level() {
+    let rules = Rules {
+        goal: Some(Goal::Length(3)),
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 1);
+    sim.restart(1);
+
+    sim.place_fruit(GridPosition::new(5, 4));
+    sim.step(None);
+    assert_eq!(sim.state(), GameState::Running);
+
+    sim.place_fruit(GridPosition::new(6, 4));
+    let events = sim.step(Some(Direction::Right));
+    assert_eq!(sim.length(), 3);
+    assert!(events.contains(&Event::LevelComplete));
+    assert_eq!(sim.state(), GameState::LevelComplete);
+
+    // Nothing moves once the level is complete
+    assert!(sim.step(None).is_empty());
+}
+
+#[test]
+fn score_goals_count_points() {
+    let goal = Goal::Score(20);
+    assert!(!goal.is_reached(10, 10));
+    assert!(goal.is_reached(1, 20));
+    assert_eq!("score 20".parse(), Ok(goal));
+    assert_eq!(goal.to_string(), "score 20");
+    assert!("score".parse::<Goal>().is_err());
+}
+
+#[test]
+fn bundled_campaign_is_valid() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/levels");
+    let campaign = Campaign::load(&dir).unwrap();
+    assert!(campaign.stages.len() > 1);
+    assert!(campaign.stages.iter().all(|s| s.level.rules.goal.is_some()));
+}
+
+#[test]
+fn completing_a_stage_unlocks_the_next_one() {
+    let mut progress = Progress::default();
+    assert!(progress.is_unlocked(0));
+    assert!(!progress.is_unlocked(1));
+
+    progress.complete(0);
+    assert!(progress.is_unlocked(1));
+    assert!(!progress.is_unlocked(2));
+
+    // Playing an earlier stage again doesn't lock anything
+    progress.complete(0);
+    assert!(progress.is_unlocked(1));
+
+    assert!(progress.record_score("box", 50));
+    assert!(!progress.record_score("box", 30));
+    assert_eq!(progress.best("box"), 50);
+    assert_eq!(progress.best("cross"), 0);
+}
+
+#[test]
+fn progress_is_saved() {
+    let path = std::env::temp_dir()
+        .join(format!("snake-ggez-test-{}", std::process::id()))
+        .join("campaign.toml");
+
+    // A missing file is a new campaign
+    assert_eq!(Progress::load(&path).unwrap(), Progress::default());
+
+    let mut progress = Progress::default();
+    progress.complete(1);
+    progress.record_score("cross", 120);
+    progress.save(&path).unwrap();
+    assert_eq!(Progress::load(&path).unwrap(), progress);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
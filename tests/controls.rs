@@ -0,0 +1,97 @@
+use ggez::input::keyboard::KeyCode;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Controls;
+use snake_ggez::controls::Preset;
+use snake_ggez::settings::KeySettings;
+use snake_ggez::settings::SettingsError;
+use snake_ggez::simulation::Direction;
+
+/// Resolve the `[keys]` of a settings file
+fn keys(text: &str) -> Result<Controls, SettingsError> {
+    let settings: KeySettings = toml::from_str(text).unwrap();
+    Controls::from_settings(&settings)
+}
+
+/// The key of the error, for the errors about a setting
+fn error_key(result: Result<Controls, SettingsError>) -> String {
+    match result {
+        Err(SettingsError::Invalid { key, .. }) => key,
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn presets_have_no_conflicts() {
+    for preset in Preset::ALL {
+        let controls = Controls::preset(preset);
+        assert_eq!(controls.conflicts(), [], "{}", preset);
+        for action in Action::ALL {
+            assert!(!controls.keys(action).is_empty(), "{} {}", preset, action);
+        }
+    }
+}
+
+#[test]
+fn settings_change_the_keys_of_the_preset() {
+    let controls = keys("preset = \"wasd\"\nquit = [\"Escape\"]\n").unwrap();
+    assert_eq!(controls.turn(KeyCode::W), Some(Direction::Up));
+    assert_eq!(controls.turn(KeyCode::Up), None);
+    assert_eq!(controls.keys(Action::Quit), [KeyCode::Escape]);
+    assert_eq!(controls.keys(Action::Pause), [KeyCode::P, KeyCode::Space]);
+
+    let controls = keys("preset = \"vim\"\n").unwrap();
+    assert_eq!(controls.turn(KeyCode::H), Some(Direction::Left));
+    assert_eq!(keys("").unwrap(), Controls::default());
+}
+
+#[test]
+fn bad_key_settings_name_the_key() {
+    assert_eq!(error_key(keys("preset = \"emacs\"\n")), "keys.preset");
+    assert_eq!(error_key(keys("jump = [\"J\"]\n")), "keys.jump");
+    assert_eq!(error_key(keys("pause = []\n")), "keys.pause");
+    // Pause and turn up are both used while playing
+    assert_eq!(error_key(keys("pause = [\"Up\"]\n")), "keys.pause");
+}
+
+#[test]
+fn conflicting_bindings_are_refused() {
+    let mut controls = Controls::default();
+    let conflict = controls.bind(Action::Pause, KeyCode::Up).unwrap_err();
+    assert_eq!(conflict.key, KeyCode::Up);
+    assert_eq!(conflict.actions, (Action::TurnUp, Action::Pause));
+    assert_eq!(controls, Controls::default());
+
+    // Not on the same screen: the high scores are only shown on the start screen
+    controls.bind(Action::HighScores, KeyCode::Up).unwrap();
+    assert_eq!(controls.keys(Action::HighScores), [KeyCode::Up]);
+    assert_eq!(controls.conflicts(), []);
+}
+
+#[test]
+fn bindings_are_saved() {
+    let path = std::env::temp_dir()
+        .join(format!("snake-ggez-controls-{}", std::process::id()))
+        .join("controls.toml");
+    assert_eq!(Controls::load(&path).unwrap(), None);
+
+    let mut controls = Controls::preset(Preset::Vim);
+    controls.bind(Action::Confirm, KeyCode::Return).unwrap();
+    controls.save(&path).unwrap();
+    let loaded = Controls::load(&path).unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert_eq!(loaded, Some(controls));
+}
+
+#[test]
+fn keys_press_all_their_actions() {
+    let mut controls = Controls::default();
+    controls
+        .bind(Action::HighScores, KeyCode::Left)
+        .unwrap_err();
+    controls.bind(Action::Continue, KeyCode::Up).unwrap();
+    let press = controls.press(KeyCode::Up);
+    assert_eq!(press.key, Some(KeyCode::Up));
+    assert_eq!(press.actions, [Action::TurnUp, Action::Continue]);
+    assert_eq!(press.turn(), Some(Direction::Up));
+    assert_eq!(controls.press(KeyCode::Escape).turn(), None);
+}
@@ -0,0 +1,185 @@
+use snake_ggez::bot::Difficulty;
+use snake_ggez::env::Action;
+use snake_ggez::env::Encoding;
+use snake_ggez::env::Env;
+use snake_ggez::env::EnvConfig;
+use snake_ggez::env::Rewards;
+use snake_ggez::env::FEATURES;
+use snake_ggez::env::GRID_CHANNELS;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Death;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Event;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+
+/// A small walled board, with the fruit always right in front of the snake
+fn config(encoding: Encoding) -> EnvConfig {
+    EnvConfig {
+        rules: Rules {
+            board: Board::new(6, 6).unwrap(),
+            boundary: Boundary::Walls,
+            start_pos: GridPosition::new(2, 2),
+            fruit_spawns: vec![GridPosition::new(3, 2)],
+            ..Rules::default()
+        },
+        encoding,
+        ..EnvConfig::default()
+    }
+}
+
+#[test]
+fn actions_turn_from_the_head() {
+    for (i, action) in Action::ALL.into_iter().enumerate() {
+        assert_eq!(Action::from_index(i), Some(action));
+        assert_eq!(action.index(), i);
+    }
+    assert_eq!(Action::from_index(3), None);
+
+    assert_eq!(
+        Action::Straight.direction(Direction::Right),
+        Direction::Right
+    );
+    assert_eq!(Action::Left.direction(Direction::Right), Direction::Up);
+    assert_eq!(Action::Right.direction(Direction::Right), Direction::Down);
+    assert_eq!(Action::Left.direction(Direction::Down), Direction::Right);
+    assert_eq!(Action::Right.direction(Direction::Up), Direction::Right);
+}
+
+#[test]
+fn the_grid_has_a_channel_for_each_thing() {
+    let mut env = Env::new(config(Encoding::Grid)).unwrap();
+    assert_eq!(env.shape(), [GRID_CHANNELS, 6, 6]);
+    let grid = env.reset(1);
+    assert_eq!(grid.len(), GRID_CHANNELS * 36);
+    assert_eq!(grid, env.reset(1));
+
+    let cell = |channel: usize, x: usize, y: usize| grid[channel * 36 + y * 6 + x];
+    assert_eq!(cell(0, 2, 2), 1.0);
+    assert_eq!(cell(2, 3, 2), 1.0);
+    // One head, one fruit, no body, walls nor rivals yet
+    let counts: Vec<f32> = grid.chunks(36).map(|c| c.iter().sum()).collect();
+    assert_eq!(counts, [1.0, 0.0, 1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn features_are_seen_from_the_head() {
+    let mut env = Env::new(config(Encoding::Features)).unwrap();
+    assert_eq!(env.shape(), [FEATURES]);
+    let features = env.reset(1);
+    assert_eq!(features.len(), FEATURES);
+    // Nothing deadly around, going right, the fruit on the right
+    assert_eq!(
+        features[..11],
+        [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+    );
+
+    // Up to the top edge: going straight on is deadly
+    env.step(Action::Left);
+    let (features, ..) = env.step(Action::Straight);
+    assert_eq!(features[..4], [1.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn rewards_follow_the_fruits_and_the_death() {
+    let mut env = Env::new(config(Encoding::Features)).unwrap();
+    env.reset(1);
+
+    let (_, reward, done, info) = env.step(Action::Straight);
+    assert_eq!(reward, 1.0);
+    assert!(!done);
+    assert_eq!((info.tick, info.score, info.length), (1, 10, 2));
+
+    // Into the right edge
+    let mut last = env.step(Action::Straight);
+    while !last.2 {
+        last = env.step(Action::Straight);
+    }
+    let (_, reward, _, info) = last;
+    assert_eq!(reward, -1.0);
+    assert_eq!(info.death, Some(Death::HitWall));
+    assert!(!info.truncated);
+
+    // Nothing more happens until the next reset
+    let (_, reward, done, _) = env.step(Action::Left);
+    assert_eq!((reward, done), (0.0, true));
+    env.reset(1);
+    assert!(!env.step(Action::Straight).2);
+}
+
+#[test]
+fn rewards_can_be_shaped() {
+    let mut config = config(Encoding::Grid);
+    config.rules.fruit_points = 25;
+    let rewards = Rewards {
+        step: -0.5,
+        ..Rewards::default()
+    };
+    let mut env = Env::new(config.clone()).unwrap().with_reward(rewards);
+    env.reset(1);
+    // Two fruits and a half for 25 points
+    assert_eq!(env.step(Action::Straight).1, 2.0);
+    assert_eq!(env.step(Action::Left).1, -0.5);
+
+    let mut env = Env::new(config)
+        .unwrap()
+        .with_reward(|sim: &Simulation, events: &[Event]| {
+            sim.tick() as f32 + events.len() as f32 * 100.0
+        });
+    env.reset(1);
+    // Moved and ate
+    assert_eq!(env.step(Action::Straight).1, 201.0);
+}
+
+#[test]
+fn long_games_are_cut_short() {
+    let mut config = config(Encoding::Features);
+    config.rules.boundary = Boundary::Wrap;
+    config.rules.fruit_spawns.clear();
+    config.max_ticks = 5;
+    let mut env = Env::new(config).unwrap();
+    env.reset(2);
+    for tick in 1..=5 {
+        let (_, _, done, info) = env.step(Action::Straight);
+        assert_eq!(done, tick == 5);
+        assert_eq!(info.truncated, tick == 5);
+    }
+}
+
+#[test]
+fn bots_play_against_the_agent() {
+    let config = EnvConfig {
+        rules: Rules {
+            boundary: Boundary::Walls,
+            ..Rules::default()
+        },
+        bots: vec![Difficulty::Cautious, Difficulty::Greedy],
+        ..EnvConfig::default()
+    };
+    let mut env = Env::new(config).unwrap();
+    let grid = env.reset(4);
+    let cells = 25 * 20;
+    let rivals: f32 = grid[4 * cells..].iter().sum();
+    assert_eq!(rivals, 2.0);
+    assert_eq!(env.sim().snakes().len(), 3);
+
+    // The game is over when the agent dies, however the bots do
+    let mut last = env.step(Action::Straight);
+    while !last.2 {
+        last = env.step(Action::Straight);
+    }
+    let (_, reward, _, info) = last;
+    assert!(info.death.is_some());
+    assert!(info.tick <= 21);
+    assert!(reward < 0.0);
+    assert_eq!(env.sim().state(), GameState::GameOver);
+
+    let too_many = EnvConfig {
+        bots: vec![Difficulty::Greedy; 4],
+        ..EnvConfig::default()
+    };
+    assert!(Env::new(too_many).is_err());
+}
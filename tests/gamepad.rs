@@ -0,0 +1,90 @@
+use ggez::input::gamepad::gilrs::Axis;
+use ggez::input::gamepad::gilrs::Button;
+use snake_ggez::controls::Action;
+use snake_ggez::gamepad::Pad;
+use snake_ggez::gamepad::DEAD_ZONE;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::turns::TurnQueue;
+
+/// The turn of a stick event, if it gives one
+fn stick(pad: &mut Pad, axis: Axis, value: f32) -> Option<Direction> {
+    pad.axis(axis, value).and_then(|press| press.turn())
+}
+
+#[test]
+fn buttons_have_the_actions_of_the_keyboard() {
+    let mut pad = Pad::new();
+    assert_eq!(pad.button_down(Button::DPadUp).turn(), Some(Direction::Up));
+    assert_eq!(
+        pad.button_down(Button::DPadLeft).turn(),
+        Some(Direction::Left)
+    );
+    assert!(pad.button_down(Button::Start).is(Action::Pause));
+    assert!(pad.button_down(Button::South).is(Action::Confirm));
+    assert!(pad.button_down(Button::East).is(Action::Cancel));
+
+    // Still a press, to start the game
+    let press = pad.button_down(Button::RightThumb);
+    assert_eq!(press.key, None);
+    assert!(press.actions.is_empty());
+}
+
+#[test]
+fn the_stick_turns_when_pushed_out_of_the_dead_zone() {
+    let mut pad = Pad::new();
+    assert_eq!(stick(&mut pad, Axis::LeftStickX, DEAD_ZONE / 2.0), None);
+    assert_eq!(
+        stick(&mut pad, Axis::LeftStickX, 0.8),
+        Some(Direction::Right)
+    );
+    // Held: no more turns
+    assert_eq!(stick(&mut pad, Axis::LeftStickX, 0.9), None);
+    assert_eq!(stick(&mut pad, Axis::LeftStickY, 0.3), None);
+
+    // Rolled up: the larger axis wins
+    assert_eq!(stick(&mut pad, Axis::LeftStickY, 0.95), Some(Direction::Up));
+
+    // Back to the center, then down again
+    assert_eq!(stick(&mut pad, Axis::LeftStickX, 0.0), None);
+    assert_eq!(stick(&mut pad, Axis::LeftStickY, 0.1), None);
+    assert_eq!(pad.direction(), None);
+    assert_eq!(
+        stick(&mut pad, Axis::LeftStickY, -0.7),
+        Some(Direction::Down)
+    );
+
+    // The other stick doesn't turn
+    assert_eq!(stick(&mut pad, Axis::RightStickX, -1.0), None);
+}
+
+#[test]
+fn quick_stick_moves_are_queued() {
+    let rules = Rules {
+        start_length: 3,
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 1);
+    sim.restart(1);
+    let mut pad = Pad::new();
+    let mut turns = TurnQueue::new();
+
+    // Up, then left, then back to the center, all between two ticks
+    let events = [
+        (Axis::LeftStickY, 1.0),
+        (Axis::LeftStickY, 0.0),
+        (Axis::LeftStickX, -1.0),
+        (Axis::LeftStickX, 0.0),
+    ];
+    for (axis, value) in events {
+        if let Some(dir) = stick(&mut pad, axis, value) {
+            turns.push(&sim, dir);
+        }
+    }
+    assert_eq!(turns.to_vec(), [Direction::Up, Direction::Left]);
+
+    sim.step(turns.pop());
+    sim.step(turns.pop());
+    assert_eq!(sim.dir(), Direction::Left);
+}
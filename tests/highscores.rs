@@ -0,0 +1,126 @@
+use snake_ggez::highscores::clean_name;
+use snake_ggez::highscores::table_name;
+use snake_ggez::highscores::Entry;
+use snake_ggez::highscores::HighScores;
+use snake_ggez::highscores::MAX_ENTRIES;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Rules;
+use std::time::Duration;
+
+fn entry(name: &str, score: u32) -> Entry {
+    Entry {
+        name: name.to_string(),
+        score,
+        seed: 0,
+        assisted: false,
+    }
+}
+
+/// A file in a new temporary directory
+fn temp_file(test: &str) -> std::path::PathBuf {
+    std::env::temp_dir()
+        .join(format!("snake-ggez-{}-{}", test, std::process::id()))
+        .join("highscores.toml")
+}
+
+#[test]
+fn tables_keep_the_best_scores_in_order() {
+    let mut scores = HighScores::default();
+    assert_eq!(scores.insert("t", entry("a", 30)), Some(0));
+    assert_eq!(scores.insert("t", entry("b", 50)), Some(0));
+    // Same score: the older entry stays first
+    assert_eq!(scores.insert("t", entry("c", 30)), Some(2));
+    let names: Vec<_> = scores.table("t").iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["b", "a", "c"]);
+
+    for i in 0..MAX_ENTRIES as u32 {
+        scores.insert("t", entry("x", 100 + i));
+    }
+    assert_eq!(scores.table("t").len(), MAX_ENTRIES);
+    assert!(!scores.qualifies("t", 50));
+    assert_eq!(scores.insert("t", entry("late", 50)), None);
+    assert!(scores.qualifies("t", 200));
+
+    // A score of 0 never qualifies, other tables are separate
+    assert!(!scores.qualifies("other", 0));
+    assert!(scores.qualifies("other", 10));
+    assert_eq!(scores.last_name, "x");
+}
+
+#[test]
+fn the_starting_length_has_its_own_tables() {
+    let speed = Duration::from_millis(300);
+    let long = Rules {
+        start_length: 10,
+        ..Rules::default()
+    };
+    let t = table_name(&Rules::default(), speed, None);
+    assert_ne!(t, table_name(&long, speed, None));
+    assert_eq!(t, "25x20 wrap 300ms 10pts length 1");
+}
+
+#[test]
+fn tables_depend_on_the_settings() {
+    let rules = Rules::default();
+    let walls = Rules {
+        boundary: Boundary::Walls,
+        ..Rules::default()
+    };
+    let speed = Duration::from_millis(300);
+    let t = table_name(&rules, speed, None);
+    assert_ne!(t, table_name(&walls, speed, None));
+    assert_ne!(t, table_name(&rules, Duration::from_millis(200), None));
+    assert_ne!(t, table_name(&rules, speed, Some("box")));
+    assert_eq!(t, table_name(&Rules::default(), speed, None));
+}
+
+#[test]
+fn names_are_cleaned() {
+    assert_eq!(clean_name("  bob \n"), "bob");
+    assert_eq!(clean_name("a very long name indeed"), "a very long");
+}
+
+#[test]
+fn high_scores_are_saved() {
+    let path = temp_file("saved");
+    assert_eq!(HighScores::load(&path).unwrap(), HighScores::default());
+
+    let mut scores = HighScores::default();
+    scores.insert("25x20 wrap 300ms 10pts", entry("ann", 120));
+    scores.insert("25x20 walls 300ms 10pts", entry("bob", 80));
+    // Seeds larger than a TOML integer
+    let mut big = entry("cy", 40);
+    big.seed = u64::MAX;
+    scores.insert("25x20 wrap 300ms 10pts", big);
+    scores.save(&path).unwrap();
+    assert_eq!(HighScores::load(&path).unwrap(), scores);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn corrupted_files_are_reported() {
+    let path = temp_file("corrupted");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+    std::fs::write(&path, "this is [ not toml").unwrap();
+    assert!(HighScores::load(&path).is_err());
+
+    // Entries out of order or too many are fixed
+    let mut text = String::new();
+    for i in 0..20 {
+        text.push_str(&format!(
+            "[[tables.t]]\nname = \"p{}\"\nscore = {}\nseed = \"1\"\n",
+            i, i
+        ));
+    }
+    std::fs::write(&path, text).unwrap();
+    let scores = HighScores::load(&path).unwrap();
+    let table = scores.table("t");
+    assert_eq!(table.len(), MAX_ENTRIES);
+    assert_eq!(table[0].score, 19);
+    // The files of the versions without the autopilot
+    assert!(!table[0].assisted);
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
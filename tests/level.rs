@@ -0,0 +1,131 @@
+use snake_ggez::level::find_level;
+use snake_ggez::level::Level;
+use snake_ggez::level::LevelError;
+use snake_ggez::level::LEVEL_EXTENSION;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Death;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Simulation;
+
+const LEVEL: &str = "\
+; A small test level
+name Test
+boundary walls
+length 2
+map
+#######
+#.....#
+#.v.#.#
+#.....#
+#...*.#
+#######
+";
+
+#[test]
+fn level_is_parsed() {
+    let level: Level = LEVEL.parse().unwrap();
+    assert_eq!(level.name, "Test");
+
+    let rules = level.rules;
+    assert_eq!((rules.board.width, rules.board.height), (7, 6));
+    assert_eq!(rules.boundary, Boundary::Walls);
+    assert_eq!(rules.start_pos, GridPosition::new(2, 2));
+    assert_eq!(rules.start_dir, Direction::Down);
+    assert_eq!(rules.start_length, 2);
+    assert_eq!(rules.walls.len(), 7 * 2 + 4 * 2 + 1);
+    assert!(rules.walls.contains(&GridPosition::new(4, 2)));
+    assert_eq!(rules.fruit_spawns, [GridPosition::new(4, 4)]);
+}
+
+#[test]
+fn snake_starts_where_the_level_says() {
+    let level: Level = LEVEL.parse().unwrap();
+    let mut sim = Simulation::new(level.rules, 1);
+    sim.restart(1);
+    assert_eq!(sim.head_pos(), GridPosition::new(2, 2));
+    assert_eq!(sim.dir(), Direction::Down);
+    assert!(sim.body().iter().eq([GridPosition::new(2, 1)].iter()));
+    assert_eq!(sim.fruit_pos(), GridPosition::new(4, 4));
+}
+
+#[test]
+fn walls_are_deadly() {
+    let level: Level = LEVEL.parse().unwrap();
+    let mut sim = Simulation::new(level.rules, 1);
+    sim.restart(1);
+
+    sim.step(Some(Direction::Right));
+    assert_eq!(sim.head_pos(), GridPosition::new(3, 2));
+    sim.step(None);
+    assert_eq!(sim.state(), GameState::GameOver);
+    assert_eq!(sim.death(), Some(Death::HitWall));
+    assert_eq!(sim.head_pos(), GridPosition::new(3, 2));
+}
+
+#[test]
+fn fruits_never_appear_on_walls() {
+    // No fruit spawns: the fruit can appear on any free cell
+    let text = LEVEL.replace('*', ".");
+    let level: Level = text.parse().unwrap();
+    for seed in 0..50 {
+        let mut sim = Simulation::new(level.rules.clone(), seed);
+        sim.restart(seed);
+        assert!(!sim.is_wall(sim.fruit_pos()));
+        assert!(!level.rules.walls.contains(&sim.fruit_pos()));
+    }
+}
+
+#[test]
+fn invalid_levels_are_rejected() {
+    let bad = |text: &str| matches!(text.parse::<Level>(), Err(LevelError::Parse { .. }));
+
+    // No map, no head, two heads
+    assert!(bad("name Empty\n"));
+    assert!(bad("map\n.....\n.....\n.....\n.....\n.....\n"));
+    assert!(bad("map\n>....\n.....\n..<..\n.....\n.....\n"));
+    // Rows of different lengths, unknown cells, unknown keys
+    assert!(bad("map\n>....\n....\n.....\n.....\n.....\n"));
+    assert!(bad("map\n>....\n..x..\n.....\n.....\n.....\n"));
+    assert!(bad("speed 3\nmap\n>....\n.....\n.....\n.....\n.....\n"));
+    // The body of the snake goes through a wall
+    assert!(bad("length 3\nmap\n.#>..\n.....\n.....\n.....\n.....\n"));
+    // Too small
+    assert!(bad("map\n>...\n....\n....\n....\n"));
+    // Goals that can't be reached
+    assert!(bad(
+        "goal length 1\nmap\n>....\n.....\n.....\n.....\n.....\n"
+    ));
+    assert!(bad(
+        "goal length 26\nmap\n>....\n.....\n.....\n.....\n.....\n"
+    ));
+    assert!(bad(
+        "goal speed 3\nmap\n>....\n.....\n.....\n.....\n.....\n"
+    ));
+}
+
+#[test]
+fn bundled_levels_are_valid() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/levels");
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|e| e != LEVEL_EXTENSION) {
+            continue;
+        }
+        if let Err(e) = Level::load(&path) {
+            panic!("{}: {}", path.display(), e);
+        }
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn levels_are_found_by_name_or_path() {
+    let path = find_level("box").unwrap();
+    assert_eq!(path.file_name().unwrap(), "box.txt");
+    assert_eq!(find_level(path.to_str().unwrap()), Ok(path.clone()));
+    assert!(find_level("no such level").is_err());
+}
@@ -0,0 +1,452 @@
+use oorandom::Rand32;
+use snake_ggez::bot::bot_starts;
+use snake_ggez::bot::Bot;
+use snake_ggez::bot::Difficulty;
+use snake_ggez::lockstep::connect;
+use snake_ggez::lockstep::Handshake;
+use snake_ggez::lockstep::LockstepError;
+use snake_ggez::lockstep::PeerMatch;
+use snake_ggez::lockstep::PeerMessage;
+use snake_ggez::lockstep::Proposal;
+use snake_ggez::lockstep::Rounds;
+use snake_ggez::lockstep::Session;
+use snake_ggez::lockstep::Transport;
+use snake_ggez::lockstep::Udp;
+use snake_ggez::lockstep::MAX_PREDICTION;
+use snake_ggez::lockstep::PROTOCOL;
+use snake_ggez::lockstep::ROUND_PAUSE;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use std::cell::RefCell;
+use std::io;
+use std::net::UdpSocket;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A versus match on a small walled board, for short rounds
+fn proposal(seed: u64) -> Proposal {
+    let mut rules = Rules {
+        board: Board::new(12, 10).unwrap(),
+        boundary: Boundary::Walls,
+        ..Rules::default()
+    };
+    rules.rivals = vec![rules.start().mirrored(rules.board)];
+    Proposal {
+        rules,
+        seed,
+        move_time_ms: 150,
+        rounds_to_win: 3,
+    }
+}
+
+/// Both sides of a match, started by a handshake
+fn sessions(proposal: Proposal) -> [Session; 2] {
+    let a = Handshake::new(proposal.clone(), 2);
+    let b = Handshake::new(proposal, 1);
+    let session_a = a.receive(b.hello()).unwrap().unwrap();
+    let session_b = b.receive(a.hello()).unwrap().unwrap();
+    [session_a, session_b]
+}
+
+/// One way of the harness: a message arrives some frames later, or never
+struct Link {
+    /// The messages on the way, with the frame they arrive at
+    in_flight: Vec<(u64, PeerMessage)>,
+    latency: u64,
+    /// Up to this many more frames for some messages, which then overtake others
+    jitter: u64,
+    /// The share of the messages lost
+    loss: f32,
+    rng: Rand32,
+}
+
+impl Link {
+    fn new(latency: u64, jitter: u64, loss: f32, seed: u64) -> Link {
+        Link {
+            in_flight: Vec::new(),
+            latency,
+            jitter,
+            loss,
+            rng: Rand32::new(seed),
+        }
+    }
+
+    fn send(&mut self, frame: u64, message: PeerMessage) {
+        if self.rng.rand_float() < self.loss {
+            return;
+        }
+        let delay = self.latency + self.rng.rand_range(0..self.jitter as u32 + 1) as u64;
+        self.in_flight.push((frame + delay, message));
+    }
+
+    fn deliver(&mut self, frame: u64) -> Vec<PeerMessage> {
+        let (due, later) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|(at, _)| *at <= frame);
+        self.in_flight = later;
+        let mut due: Vec<(u64, PeerMessage)> = due;
+        due.sort_by_key(|(at, _)| *at);
+        due.into_iter().map(|(_, message)| message).collect()
+    }
+}
+
+/// What happened to a match played through the harness
+struct Played {
+    sessions: [Session; 2],
+    /// The turns each side played, by tick
+    turns: [Vec<Option<Direction>>; 2],
+    /// The frames a side had to wait for the other
+    stalls: u64,
+}
+
+/// Play `ticks` ticks on each side, with bots turning the snakes on the
+/// match they see, then exchange messages until both sides agree
+fn play(proposal: Proposal, ticks: u64, links: [Link; 2]) -> Played {
+    let mut links = links;
+    let mut played = Played {
+        sessions: sessions(proposal),
+        turns: [Vec::new(), Vec::new()],
+        stalls: 0,
+    };
+    let bots = [
+        Bot::new(Difficulty::Pathfinding),
+        Bot::new(Difficulty::Greedy),
+    ];
+
+    for frame in 0..100_000 {
+        for side in 0..2 {
+            let session = &mut played.sessions[side];
+            for message in links[1 - side].deliver(frame) {
+                session.receive(message);
+            }
+            if session.game().tick() < ticks {
+                let snake = session.local();
+                let turn = bots[side].choose(session.game().sim(), snake);
+                if session.advance(turn) {
+                    played.turns[snake].push(turn);
+                } else {
+                    played.stalls += 1;
+                }
+            }
+            for message in session.outgoing() {
+                links[side].send(frame, message);
+            }
+        }
+        if played
+            .sessions
+            .iter()
+            .all(|s| s.confirmed().tick() == ticks)
+        {
+            return played;
+        }
+    }
+    panic!("the sides never agreed");
+}
+
+/// Check that both sides ended with the match played with all the turns
+fn assert_converged(played: &Played, proposal: &Proposal) {
+    let mut reference = Rounds::new(proposal);
+    for (a, b) in played.turns[0].iter().zip(played.turns[1].iter()) {
+        reference.step(&[*a, *b]);
+    }
+    for session in played.sessions.iter() {
+        assert_eq!(session.desync(), None);
+        assert_eq!(session.confirmed().tick(), reference.tick());
+        assert_eq!(session.confirmed().checksum(), reference.checksum());
+        assert_eq!(session.game().checksum(), reference.checksum());
+        assert_eq!(session.game().sim().snakes(), reference.sim().snakes());
+        assert_eq!(session.game().wins(), reference.wins());
+    }
+}
+
+#[test]
+fn rounds_follow_each_other() {
+    let proposal = proposal(7);
+    let mut rounds = Rounds::new(&proposal);
+    assert_eq!(rounds.sim().state(), GameState::Running);
+
+    // Going straight on, both snakes hit the walls and it's a draw
+    while rounds.sim().state() == GameState::Running {
+        rounds.step(&[None, None]);
+    }
+    let ended = rounds.tick();
+    assert_eq!(rounds.last_winner(), None);
+    assert_eq!(rounds.wins(), [0, 0]);
+
+    for _ in 0..ROUND_PAUSE {
+        assert_ne!(rounds.sim().state(), GameState::Running);
+        rounds.step(&[None, None]);
+    }
+    assert_eq!(rounds.tick(), ended + ROUND_PAUSE);
+    assert_eq!(rounds.sim().state(), GameState::Running);
+    assert_eq!(rounds.sim().seed(), 8);
+    assert_eq!(rounds.sim().tick(), 0);
+}
+
+#[test]
+fn the_larger_nonce_chooses_the_match() {
+    let mut theirs = proposal(1);
+    theirs.move_time_ms = 90;
+    let ours = Handshake::new(proposal(2), 10);
+    let other = Handshake::new(theirs.clone(), 20);
+
+    let session = ours.receive(other.hello()).unwrap().unwrap();
+    assert_eq!(session.local(), 1);
+    assert_eq!(session.proposal(), &theirs);
+    let session = other.receive(ours.hello()).unwrap().unwrap();
+    assert_eq!(session.local(), 0);
+    assert_eq!(session.proposal(), &theirs);
+
+    let same = Handshake::new(proposal(3), 10);
+    assert!(matches!(
+        ours.receive(same.hello()),
+        Err(LockstepError::SameNonce)
+    ));
+    let old = PeerMessage::Hello {
+        protocol: PROTOCOL + 1,
+        nonce: 3,
+        proposal: proposal(3),
+    };
+    assert!(matches!(
+        ours.receive(&old),
+        Err(LockstepError::Protocol(_))
+    ));
+}
+
+#[test]
+fn matches_that_cant_be_played_are_refused() {
+    let ours = Handshake::new(proposal(1), 1);
+    let hello = |proposal| PeerMessage::Hello {
+        protocol: PROTOCOL,
+        nonce: 2,
+        proposal,
+    };
+
+    let mut huge = proposal(2);
+    huge.rules.board = Board {
+        width: 100_000,
+        height: 100_000,
+    };
+    let mut off_board = proposal(2);
+    off_board.rules.walls.push(GridPosition::new(40, 3));
+    let mut alone = proposal(2);
+    alone.rules.rivals.clear();
+    let mut three = proposal(2);
+    three.rules.rivals = bot_starts(&three.rules, 2);
+
+    for proposal in [huge, off_board, alone, three] {
+        assert!(matches!(
+            ours.receive(&hello(proposal)),
+            Err(LockstepError::Invalid(_))
+        ));
+    }
+}
+
+#[test]
+fn sides_agree_over_a_perfect_link() {
+    let proposal = proposal(11);
+    let links = [Link::new(0, 0, 0.0, 1), Link::new(0, 0, 0.0, 2)];
+    let played = play(proposal.clone(), 400, links);
+    assert_converged(&played, &proposal);
+    assert_eq!(played.stalls, 0);
+}
+
+#[test]
+fn sides_converge_despite_latency_and_loss() {
+    for seed in 0..5 {
+        let proposal = proposal(seed);
+        let links = [
+            Link::new(3, 3, 0.25, seed * 2),
+            Link::new(3, 3, 0.25, seed * 2 + 1),
+        ];
+        let played = play(proposal.clone(), 600, links);
+        assert_converged(&played, &proposal);
+        // The turns came late: the sides predicted wrong, and rolled back
+        assert!(played.sessions.iter().any(|s| s.rollbacks() > 0));
+    }
+}
+
+#[test]
+fn a_slow_link_makes_the_sides_wait() {
+    let proposal = proposal(5);
+    let latency = MAX_PREDICTION * 2;
+    let links = [Link::new(latency, 0, 0.1, 3), Link::new(latency, 0, 0.1, 4)];
+    let played = play(proposal.clone(), 300, links);
+    assert_converged(&played, &proposal);
+    assert!(played.stalls > 0);
+}
+
+#[test]
+fn desyncs_are_detected() {
+    let [mut a, mut b] = sessions(proposal(9));
+    for _ in 0..5 {
+        a.advance(None);
+        b.advance(Some(Direction::Up));
+        for message in a.outgoing() {
+            b.receive(message);
+        }
+        for message in b.outgoing() {
+            a.receive(message);
+        }
+    }
+    assert_eq!(a.confirmed().tick(), 5);
+    assert_eq!(a.desync(), None);
+    assert_eq!(b.desync(), None);
+
+    // A checksum of a tick played on both sides
+    let wrong = |tick| PeerMessage::Turns {
+        start: 5,
+        turns: Vec::new(),
+        ack: 5,
+        checksum: Some((tick, 12345)),
+    };
+    a.receive(wrong(3));
+    assert_eq!(a.desync(), Some(3));
+
+    // A checksum of a tick to come, checked once played
+    b.receive(wrong(7));
+    assert_eq!(b.desync(), None);
+    for _ in 0..2 {
+        a.advance(None);
+        b.advance(None);
+        for message in a.outgoing() {
+            b.receive(message);
+        }
+    }
+    assert_eq!(b.confirmed().tick(), 7);
+    assert_eq!(b.desync(), Some(7));
+}
+
+/// One side of a transport in memory, where every message arrives at once
+struct Pipe {
+    inbox: Rc<RefCell<Vec<PeerMessage>>>,
+    outbox: Rc<RefCell<Vec<PeerMessage>>>,
+}
+
+impl Transport for Pipe {
+    fn send(&mut self, message: &PeerMessage) -> io::Result<()> {
+        self.outbox.borrow_mut().push(message.clone());
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<PeerMessage>> {
+        Ok(self.inbox.borrow_mut().drain(..).collect())
+    }
+}
+
+/// The two ends of a transport in memory
+fn pipes() -> [Pipe; 2] {
+    let a = Rc::new(RefCell::new(Vec::new()));
+    let b = Rc::new(RefCell::new(Vec::new()));
+    [
+        Pipe {
+            inbox: a.clone(),
+            outbox: b.clone(),
+        },
+        Pipe {
+            inbox: b,
+            outbox: a,
+        },
+    ]
+}
+
+#[test]
+fn matches_are_played_at_their_pace() {
+    let proposal = proposal(4);
+    let move_time = Duration::from_millis(proposal.move_time_ms);
+    let [a, b] = sessions(proposal);
+    let [pipe_a, pipe_b] = pipes();
+    let inbox = pipe_a.inbox.clone();
+    let mut a = PeerMatch::new(a, pipe_a);
+    let mut b = PeerMatch::new(b, pipe_b);
+    assert_eq!(a.move_time(), move_time);
+
+    // No tick before its time
+    a.tick(move_time / 2).unwrap();
+    assert_eq!(a.game().tick(), 0);
+
+    // The turn of the player goes to their snake at the next tick, and to the other side
+    let local = a.local();
+    assert!(a.input(Direction::Up));
+    a.tick(move_time / 2).unwrap();
+    assert_eq!(a.game().tick(), 1);
+    assert_eq!(a.game().sim().snakes()[local].dir(), Direction::Up);
+    b.tick(move_time).unwrap();
+    a.tick(Duration::from_millis(0)).unwrap();
+    assert_eq!(b.game().sim().snakes()[local].dir(), Direction::Up);
+    assert_eq!(a.session().confirmed().tick(), 1);
+
+    // A match that differs from the other side's ends it
+    inbox.borrow_mut().push(PeerMessage::Turns {
+        start: 0,
+        turns: Vec::new(),
+        ack: 0,
+        checksum: Some((1, 12345)),
+    });
+    assert!(matches!(
+        a.tick(Duration::from_millis(0)),
+        Err(LockstepError::Desync(1))
+    ));
+}
+
+#[test]
+fn sides_meet_over_udp() {
+    // Two free ports on the loopback
+    let port = || {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.local_addr().unwrap()
+    };
+    let addresses = [port(), port()];
+    let ticks = 150;
+
+    let sides: Vec<_> = (0..2)
+        .map(|side| {
+            thread::spawn(move || {
+                let mut udp = Udp::bind(addresses[side], addresses[1 - side]).unwrap();
+                let handshake = Handshake::new(proposal(side as u64), side as u64 + 1);
+                let mut session = connect(&mut udp, &handshake, Duration::from_secs(10)).unwrap();
+                let bot = Bot::new(Difficulty::Pathfinding);
+
+                // Play, then answer a little longer for the other side to finish
+                let deadline = Instant::now() + Duration::from_secs(20);
+                let mut done: Option<Instant> = None;
+                while done.is_none_or(|done| done.elapsed() < Duration::from_millis(300)) {
+                    assert!(Instant::now() < deadline, "timed out");
+                    for message in udp.receive().unwrap() {
+                        session.receive(message);
+                    }
+                    if session.game().tick() < ticks {
+                        let turn = bot.choose(session.game().sim(), session.local());
+                        session.advance(turn);
+                    }
+                    for message in session.outgoing() {
+                        udp.send(&message).unwrap();
+                    }
+                    if done.is_none() && session.confirmed().tick() == ticks {
+                        done = Some(Instant::now());
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+                (
+                    session.local(),
+                    session.confirmed().checksum(),
+                    session.desync(),
+                )
+            })
+        })
+        .collect();
+    let results: Vec<_> = sides.into_iter().map(|s| s.join().unwrap()).collect();
+
+    let mut locals: Vec<usize> = results.iter().map(|r| r.0).collect();
+    locals.sort();
+    assert_eq!(locals, [0, 1]);
+    assert_eq!(results[0].1, results[1].1);
+    assert_eq!(results[0].2, None);
+    assert_eq!(results[1].2, None);
+}
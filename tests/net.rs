@@ -0,0 +1,351 @@
+use snake_ggez::bot::bot_starts;
+use snake_ggez::net::with_port;
+use snake_ggez::net::Client;
+use snake_ggez::net::LanEvent;
+use snake_ggez::net::LanGame;
+use snake_ggez::net::NetError;
+use snake_ggez::net::Server;
+use snake_ggez::net::ServerEvent;
+use snake_ggez::net::ServerMessage;
+use snake_ggez::net::DEFAULT_PORT;
+use snake_ggez::net::MAX_PLAYERS;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::turns::TurnQueue;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A host on the loopback, on a port chosen by the system
+fn host() -> (Server, SocketAddr) {
+    let server = Server::bind("127.0.0.1:0", "Host").unwrap();
+    let addr = server.local_addr().unwrap();
+    (server, addr)
+}
+
+/// Poll until `done` says so, or fail after a while
+fn wait_for(mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// The next messages of a client, waiting for at least one
+fn receive(client: &mut Client, server: &mut Server) -> Vec<ServerMessage> {
+    let mut messages = Vec::new();
+    wait_for(|| {
+        server.poll();
+        messages = client.poll().unwrap();
+        !messages.is_empty()
+    });
+    messages
+}
+
+/// The rules of a round with a snake for each player
+fn rules(players: usize) -> Rules {
+    let mut rules = Rules::default();
+    rules.rivals = bot_starts(&rules, players - 1);
+    rules
+}
+
+#[test]
+fn addresses_get_the_default_port() {
+    assert_eq!(with_port("7000", "0.0.0.0"), "0.0.0.0:7000");
+    assert_eq!(
+        with_port("example.org", "0.0.0.0"),
+        format!("example.org:{}", DEFAULT_PORT)
+    );
+    assert_eq!(with_port("10.0.0.2:7000", "0.0.0.0"), "10.0.0.2:7000");
+}
+
+#[test]
+fn frames_show_the_game_of_the_host() {
+    let rules = rules(2);
+    let mut host = Simulation::new(rules.clone(), 7);
+    host.restart(7);
+    let mut shown = Simulation::new(rules, 99);
+    shown.restart(99);
+
+    for _ in 0..30 {
+        host.step_all(&[Some(Direction::Down), None]);
+        shown.apply_frame(host.frame()).unwrap();
+        assert_eq!(shown.frame(), host.frame());
+        assert_eq!(shown.snakes(), host.snakes());
+    }
+
+    let mut frame = host.frame();
+    frame.snakes.pop();
+    assert!(shown.apply_frame(frame).is_err());
+}
+
+#[test]
+fn players_meet_in_the_lobby() {
+    let (mut server, addr) = host();
+    let mut ada = Client::connect(addr, "Ada").unwrap();
+
+    let mut events = Vec::new();
+    wait_for(|| {
+        events.extend(server.poll());
+        !events.is_empty()
+    });
+    assert_eq!(events, [ServerEvent::Joined("Ada".to_string())]);
+    assert_eq!(
+        receive(&mut ada, &mut server),
+        [ServerMessage::Lobby {
+            players: vec!["Host".to_string(), "Ada".to_string()],
+            you: 1,
+        }]
+    );
+
+    let mut bob = Client::connect(addr, "  Bob\n").unwrap();
+    let lobby = ServerMessage::Lobby {
+        players: vec!["Host".to_string(), "Ada".to_string(), "Bob".to_string()],
+        you: 2,
+    };
+    assert_eq!(receive(&mut bob, &mut server), [lobby]);
+    assert_eq!(server.players(), ["Host", "Ada", "Bob"]);
+
+    drop(ada);
+    let mut events = Vec::new();
+    wait_for(|| {
+        events.extend(server.poll());
+        !events.is_empty()
+    });
+    assert_eq!(events, [ServerEvent::Left("Ada".to_string(), None)]);
+    let lobby = ServerMessage::Lobby {
+        players: vec!["Host".to_string(), "Bob".to_string()],
+        you: 1,
+    };
+    assert_eq!(receive(&mut bob, &mut server), [lobby]);
+}
+
+#[test]
+fn a_round_is_played_on_the_host() {
+    let (mut server, addr) = host();
+    let mut client = Client::connect(addr, "Ada").unwrap();
+    receive(&mut client, &mut server);
+
+    let rules = rules(2);
+    let mut sim = Simulation::new(rules.clone(), 3);
+    sim.restart(3);
+    let players = server.start(&rules);
+    assert_eq!(players, ["Host", "Ada"]);
+    server.send_frame(&sim.frame());
+    let messages = receive(&mut client, &mut server);
+    assert_eq!(
+        messages[0],
+        ServerMessage::Start {
+            rules: rules.clone(),
+            players,
+            you: 1,
+        }
+    );
+    let mut shown = Simulation::new(rules, 0);
+    let mut frames = messages[1..].to_vec();
+    if frames.is_empty() {
+        frames = receive(&mut client, &mut server);
+    }
+    let ServerMessage::Frame(frame) = frames.remove(0) else {
+        panic!("expected a frame");
+    };
+    shown.apply_frame(frame).unwrap();
+    assert_eq!(shown.frame(), sim.frame());
+
+    // The turns of the client go to its snake on the host
+    client.send_turn(Direction::Up).unwrap();
+    client.send_turn(Direction::Left).unwrap();
+    let mut turns = Vec::new();
+    wait_for(|| {
+        server.poll();
+        turns.extend(server.turns(1));
+        turns.len() == 2
+    });
+    assert_eq!(turns, [Direction::Up, Direction::Left]);
+    assert!(server.turns(0).is_empty());
+
+    sim.step_all(&[None, Some(turns[0])]);
+    server.send_frame(&sim.frame());
+    server.round_over(Some(1));
+    let mut messages = Vec::new();
+    wait_for(|| {
+        server.poll();
+        messages.extend(client.poll().unwrap());
+        messages.len() == 2
+    });
+    let ServerMessage::Frame(frame) = messages.remove(0) else {
+        panic!("expected a frame");
+    };
+    assert_eq!(frame.tick, 1);
+    assert_eq!(frame.state, GameState::Running);
+    assert_eq!(frame.snakes[1].dir(), Direction::Up);
+    assert_eq!(messages[0], ServerMessage::RoundOver { winner: Some(1) });
+
+    // A player joining during the round waits in the lobby
+    let mut late = Client::connect(addr, "Bob").unwrap();
+    let lobby = ServerMessage::Lobby {
+        players: vec!["Host".to_string(), "Ada".to_string(), "Bob".to_string()],
+        you: 2,
+    };
+    assert_eq!(receive(&mut late, &mut server), [lobby]);
+    assert!(client.poll().unwrap().is_empty());
+
+    server.lobby();
+    let lobby = ServerMessage::Lobby {
+        players: vec!["Host".to_string(), "Ada".to_string(), "Bob".to_string()],
+        you: 1,
+    };
+    assert_eq!(receive(&mut client, &mut server), [lobby]);
+}
+
+#[test]
+fn lan_games_follow_the_host() {
+    let server = Server::bind("127.0.0.1:0", "Host").unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut host = LanGame::host(server, Rules::default());
+    let mut hosted = Simulation::new(Rules::default(), 1);
+    assert!(host.is_host());
+    assert_eq!(host.local_addr(), Some(addr));
+    assert!(host.start_round(3).is_none());
+
+    let mut ada = LanGame::join(Client::connect(addr, "Ada").unwrap());
+    let mut shown = Simulation::new(Rules::default(), 2);
+    wait_for(|| {
+        host.poll(&mut hosted, 1).unwrap();
+        ada.poll(&mut shown, 2).unwrap();
+        ada.players().len() == 2
+    });
+    assert_eq!(host.players(), ["Host", "Ada"]);
+    assert_eq!(ada.you(), 1);
+    assert!(!ada.following());
+
+    // The round starts on the host, and the player who joined follows it
+    hosted = host.start_round(3).unwrap();
+    assert_eq!(hosted.snakes().len(), 2);
+    let mut events = Vec::new();
+    wait_for(|| {
+        events.extend(ada.poll(&mut shown, 2).unwrap());
+        !events.is_empty()
+    });
+    assert_eq!(events, [LanEvent::NewGame]);
+    assert!(ada.following());
+    assert_eq!(shown.rules(), hosted.rules());
+
+    // The turns of the player go to their snake on the host
+    assert!(ada.input(&shown, Direction::Up).unwrap());
+    let mut turns = vec![TurnQueue::new(); 2];
+    wait_for(|| {
+        host.poll(&mut hosted, 1).unwrap();
+        host.tick(&hosted, &mut turns);
+        !turns[1].is_empty()
+    });
+    assert_eq!(turns[1].to_vec(), [Direction::Up]);
+    assert!(turns[0].is_empty());
+
+    let inputs: Vec<_> = turns.iter_mut().map(TurnQueue::pop).collect();
+    hosted.step_all(&inputs);
+    host.round_over(&hosted, Some(1));
+    assert_eq!(host.last_winner(), Some(1));
+    wait_for(|| {
+        ada.poll(&mut shown, 2).unwrap();
+        ada.last_winner() == Some(1)
+    });
+    assert_eq!(shown.frame(), hosted.frame());
+
+    // Back in the lobby, the player who joined gets a demo
+    hosted = host.open_lobby(4).unwrap();
+    assert_eq!(hosted.state(), GameState::Starting);
+    wait_for(|| {
+        ada.poll(&mut shown, 2).unwrap();
+        !ada.following()
+    });
+    assert_eq!(shown.snakes().len(), 1);
+    assert!(!ada.input(&shown, Direction::Down).unwrap());
+}
+
+#[test]
+fn the_lobby_has_room_for_four() {
+    let (mut server, addr) = host();
+    let mut clients: Vec<Client> = (1..MAX_PLAYERS)
+        .map(|i| Client::connect(addr, &format!("P{}", i)).unwrap())
+        .collect();
+    wait_for(|| {
+        server.poll();
+        server.players().len() == MAX_PLAYERS
+    });
+
+    let mut late = Client::connect(addr, "Late").unwrap();
+    let mut result = Ok(Vec::new());
+    wait_for(|| {
+        server.poll();
+        result = late.poll();
+        !matches!(result, Ok(ref messages) if messages.is_empty())
+    });
+    assert!(matches!(result, Err(NetError::Rejected(_))));
+    assert_eq!(server.players().len(), MAX_PLAYERS);
+    for client in clients.iter_mut() {
+        assert!(client.poll().is_ok());
+    }
+}
+
+#[test]
+fn other_versions_are_turned_away() {
+    let (mut server, addr) = host();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    writeln!(stream, r#"{{"type":"hello","protocol":999,"name":"Old"}}"#).unwrap();
+
+    let mut line = String::new();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    stream.set_nonblocking(true).unwrap();
+    wait_for(|| {
+        server.poll();
+        reader.read_line(&mut line).is_ok_and(|n| n > 0)
+    });
+    assert!(line.contains(r#""type":"rejected""#), "{}", line);
+    assert_eq!(server.players(), ["Host"]);
+}
+
+#[test]
+fn rounds_that_cant_be_played_are_refused() {
+    let huge = Rules {
+        board: Board {
+            width: 100_000,
+            height: 100_000,
+        },
+        ..Rules::default()
+    };
+    let mut walled = Rules::default();
+    walled.walls.push(GridPosition::new(500, 3));
+    let bad = [(huge, 0), (walled, 0), (rules(2), 2)];
+
+    for (rules, you) in bad {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = Client::connect(listener.local_addr().unwrap(), "Ada").unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let start = ServerMessage::Start {
+            rules,
+            players: vec!["Host".to_string(), "Ada".to_string()],
+            you,
+        };
+        serde_json::to_writer(&mut stream, &start).unwrap();
+        writeln!(stream).unwrap();
+
+        let mut result = Ok(Vec::new());
+        wait_for(|| {
+            result = client.poll();
+            !matches!(&result, Ok(messages) if messages.is_empty())
+        });
+        assert!(matches!(result, Err(NetError::Invalid(_))));
+    }
+}
@@ -0,0 +1,57 @@
+use ggez::glam::Vec2;
+use snake_ggez::pointer::direction_to;
+use snake_ggez::pointer::Drag;
+use snake_ggez::simulation::Direction;
+
+const HEAD: Vec2 = Vec2::new(100.0, 100.0);
+
+#[test]
+fn the_quadrant_gives_the_direction() {
+    // The y axis points down
+    assert_eq!(
+        direction_to(HEAD, Vec2::new(100.0, 20.0), 16.0),
+        Some(Direction::Up)
+    );
+    assert_eq!(
+        direction_to(HEAD, Vec2::new(90.0, 180.0), 16.0),
+        Some(Direction::Down)
+    );
+    assert_eq!(
+        direction_to(HEAD, Vec2::new(20.0, 130.0), 16.0),
+        Some(Direction::Left)
+    );
+    assert_eq!(
+        direction_to(HEAD, Vec2::new(300.0, 0.0), 16.0),
+        Some(Direction::Right)
+    );
+}
+
+#[test]
+fn the_head_itself_gives_no_direction() {
+    assert_eq!(direction_to(HEAD, Vec2::new(110.0, 95.0), 16.0), None);
+    assert_eq!(direction_to(HEAD, HEAD, 16.0), None);
+}
+
+#[test]
+fn a_drag_turns_once_per_quadrant() {
+    let mut drag = Drag::new();
+    assert_eq!(drag.motion(HEAD, Vec2::new(200.0, 100.0), 16.0), None);
+
+    assert_eq!(
+        drag.press(HEAD, Vec2::new(200.0, 100.0), 16.0),
+        Some(Direction::Right)
+    );
+    assert!(drag.is_pressed());
+    assert_eq!(drag.motion(HEAD, Vec2::new(210.0, 120.0), 16.0), None);
+    assert_eq!(
+        drag.motion(HEAD, Vec2::new(110.0, 200.0), 16.0),
+        Some(Direction::Down)
+    );
+    // Back near the head: no turn, and the quadrant is kept
+    assert_eq!(drag.motion(HEAD, Vec2::new(105.0, 105.0), 16.0), None);
+    assert_eq!(drag.motion(HEAD, Vec2::new(100.0, 250.0), 16.0), None);
+
+    drag.release();
+    assert!(!drag.is_pressed());
+    assert_eq!(drag.motion(HEAD, Vec2::new(0.0, 100.0), 16.0), None);
+}
@@ -0,0 +1,157 @@
+use snake_ggez::replay::Replay;
+use snake_ggez::replay::ReplayError;
+use snake_ggez::replay::ReplayPlayer;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Fruits;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Goal;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::simulation::Start;
+
+/// Play a game with some turns, recording it
+fn record_game(seed: u64) -> (Replay, Simulation) {
+    let mut sim = Simulation::new(Rules::default(), seed);
+    sim.restart(seed);
+    let mut replay = Replay::new(seed, Rules::default());
+
+    let turns = [
+        Direction::Down,
+        Direction::Right,
+        Direction::Up,
+        Direction::Right,
+    ];
+    for i in 0..300 {
+        let input = if i % 7 == 3 {
+            Some(turns[i / 7 % 4])
+        } else {
+            None
+        };
+        replay.record(sim.tick(), input);
+        sim.step(input);
+        if sim.state() != GameState::Running {
+            break;
+        }
+    }
+
+    (replay, sim)
+}
+
+#[test]
+fn replay_round_trips_through_text() {
+    let (replay, _) = record_game(1234);
+    let text = replay.to_string();
+    assert!(text.starts_with("snake-replay 6\n"));
+    assert_eq!(text.parse::<Replay>().unwrap(), replay);
+}
+
+#[test]
+fn playback_reproduces_the_game() {
+    let (replay, recorded) = record_game(99);
+
+    let mut sim = Simulation::new(Rules::default(), 0);
+    sim.restart(replay.seed);
+    let mut player = ReplayPlayer::new(replay);
+    while !player.is_finished(sim.tick()) {
+        let input = player.input(sim.tick());
+        sim.step(input);
+    }
+
+    assert_eq!(sim.head_pos(), recorded.head_pos());
+    assert_eq!(sim.body(), recorded.body());
+    assert_eq!(sim.fruit_pos(), recorded.fruit_pos());
+    assert_eq!(sim.score(), recorded.score());
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let text = "snake-replay 1\nseed 1\ngrid 25 20\nend 10\n";
+    assert!(matches!(
+        text.parse::<Replay>(),
+        Err(ReplayError::Version(1))
+    ));
+}
+
+#[test]
+fn truncated_files_are_rejected() {
+    let text =
+        "snake-replay 6\nseed 1\ngrid 25 20\nboundary wrap\npoints 10\nstart 4 4 1 R\nturn 3 U\n";
+    assert!(matches!(
+        text.parse::<Replay>(),
+        Err(ReplayError::Parse { .. })
+    ));
+    assert!("".parse::<Replay>().is_err());
+    assert!(
+        "snake-replay 6\nseed 1\ngrid 25 20\nboundary wrap\npoints 10\nstart 4 4 1 R\nturn 3 X\nend 5\n"
+            .parse::<Replay>()
+            .is_err()
+    );
+}
+
+#[test]
+fn rules_are_recorded() {
+    let rules = Rules {
+        boundary: Boundary::WrapY,
+        fruit_points: 5,
+        start_pos: GridPosition::new(10, 2),
+        start_dir: Direction::Down,
+        start_length: 3,
+        walls: vec![GridPosition::new(0, 0), GridPosition::new(5, 5)],
+        fruit_spawns: vec![GridPosition::new(7, 7)],
+        goal: Some(Goal::Score(100)),
+        ..Rules::default()
+    };
+    let replay = Replay::new(1, rules.clone());
+    let loaded: Replay = replay.to_string().parse().unwrap();
+    assert_eq!(loaded.rules, rules);
+
+    // A snake that does not fit on the board
+    let text =
+        "snake-replay 6\nseed 1\ngrid 25 20\nboundary wrap\npoints 10\nstart 30 4 1 R\nend 5\n";
+    assert!(text.parse::<Replay>().is_err());
+}
+
+#[test]
+fn the_other_snakes_are_recorded() {
+    let rules = Rules {
+        rivals: vec![Start {
+            pos: GridPosition::new(20, 15),
+            dir: Direction::Left,
+        }],
+        fruits: Fruits::Separate,
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules.clone(), 8);
+    sim.restart(8);
+    let mut replay = Replay::new(8, rules);
+    for i in 0..40 {
+        let inputs = match i % 10 {
+            2 => [Some(Direction::Down), None],
+            5 => [None, Some(Direction::Up)],
+            7 => [Some(Direction::Right), Some(Direction::Left)],
+            _ => [None, None],
+        };
+        replay.record_all(sim.tick(), &inputs);
+        sim.step_all(&inputs);
+    }
+    assert_eq!(replay.rival_turns.len(), 8);
+
+    let loaded: Replay = replay.to_string().parse().unwrap();
+    assert_eq!(loaded, replay);
+
+    let mut played = Simulation::new(loaded.rules.clone(), 0);
+    played.restart(loaded.seed);
+    let mut player = ReplayPlayer::new(loaded);
+    while !player.is_finished(played.tick()) {
+        let inputs = player.inputs(played.tick());
+        played.step_all(&inputs);
+    }
+    assert_eq!(played.snakes(), sim.snakes());
+    assert_eq!(played.fruits(), sim.fruits());
+
+    // A turn of a snake that is not on the board
+    let text = replay.to_string().replace("turn 5 U 1", "turn 5 U 2");
+    assert!(text.parse::<Replay>().is_err());
+}
@@ -0,0 +1,109 @@
+use snake_ggez::level::Level;
+use snake_ggez::replay::Replay;
+use snake_ggez::savegame::SaveGame;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use std::time::Duration;
+
+/// A file in a new temporary directory
+fn temp_file(test: &str) -> std::path::PathBuf {
+    std::env::temp_dir()
+        .join(format!("snake-ggez-{}-{}", test, std::process::id()))
+        .join("savegame.toml")
+}
+
+/// The input of a test game at each tick: zigzagging between two rows
+fn input(tick: u64) -> Option<Direction> {
+    match tick % 8 {
+        0 => Some(Direction::Down),
+        1 | 5 => Some(Direction::Right),
+        4 => Some(Direction::Up),
+        _ => None,
+    }
+}
+
+/// Play a game up to the given tick, recording it
+fn play(rules: Rules, seed: u64, ticks: u64) -> (Simulation, Replay) {
+    let mut sim = Simulation::new(rules.clone(), seed);
+    sim.restart(seed);
+    let mut recording = Replay::new(seed, rules);
+    while sim.tick() < ticks && sim.state() == GameState::Running {
+        recording.record(sim.tick(), input(sim.tick()));
+        sim.step(input(sim.tick()));
+    }
+    (sim, recording)
+}
+
+#[test]
+fn a_restored_game_plays_out_identically() {
+    let level: Level = "boundary wrap-x\nlength 3\nmap\n..........\n..........\n.*.>...*..\n...*...*..\n....#.....\n..........\n"
+        .parse()
+        .unwrap();
+    let seed = u64::MAX - 12345;
+    let (mut sim, recording) = play(level.rules.clone(), seed, 40);
+    assert_eq!(sim.state(), GameState::Running);
+    assert!(sim.score() > 0);
+
+    let path = temp_file("restore");
+    let save = SaveGame {
+        turns: vec![Direction::Left, Direction::Up],
+        head_timer: Duration::from_millis(123),
+        level: Some("test".to_string()),
+        stage: Some(2),
+        recording,
+        game: sim.snapshot(),
+        assisted: false,
+    };
+    save.save(&path).unwrap();
+    let loaded = SaveGame::load(&path).unwrap().unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert_eq!(loaded, save);
+
+    let mut restored = Simulation::from_snapshot(loaded.game).unwrap();
+    assert_eq!(restored.snapshot(), sim.snapshot());
+
+    // Same fruits, same deaths: the RNG was restored too
+    for _ in 0..200 {
+        let tick = sim.tick();
+        assert_eq!(restored.step(input(tick)), sim.step(input(tick)));
+        assert_eq!(restored.snapshot(), sim.snapshot());
+    }
+}
+
+#[test]
+fn a_missing_save_is_no_save() {
+    let path = temp_file("missing");
+    assert!(SaveGame::load(&path).unwrap().is_none());
+}
+
+#[test]
+fn invalid_saves_are_rejected() {
+    let (sim, recording) = play(Rules::default(), 7, 10);
+    let path = temp_file("invalid");
+    let mut save = SaveGame {
+        turns: Vec::new(),
+        head_timer: Duration::ZERO,
+        level: None,
+        stage: None,
+        recording,
+        game: sim.snapshot(),
+        assisted: false,
+    };
+
+    // A snake out of the board
+    save.game.head_pos.x = 1000;
+    save.save(&path).unwrap();
+    assert!(SaveGame::load(&path).is_err());
+
+    // A recording of another game
+    save.game = sim.snapshot();
+    save.recording.seed += 1;
+    save.save(&path).unwrap();
+    assert!(SaveGame::load(&path).is_err());
+
+    std::fs::write(&path, "game = 3\n").unwrap();
+    assert!(SaveGame::load(&path).is_err());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,125 @@
+use snake_ggez::autopilot::Strategy;
+use snake_ggez::controls::Action;
+use snake_ggez::controls::Controls;
+use snake_ggez::settings::parse_color;
+use snake_ggez::settings::Overrides;
+use snake_ggez::settings::Settings;
+use snake_ggez::settings::SettingsError;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::GridPosition;
+
+/// Parse and check settings as `Settings::load` does
+fn parse(text: &str) -> Result<Settings, String> {
+    let settings: Settings = toml::from_str(text).map_err(|e| e.to_string())?;
+    settings.validate().map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+#[test]
+fn missing_keys_take_the_defaults() {
+    let settings = parse("[game]\nfruit_points = 50\n").unwrap();
+    let rules = settings.rules().unwrap();
+    assert_eq!(rules.fruit_points, 50);
+    assert_eq!(rules.board.width, 25);
+    assert_eq!(rules.start_pos, GridPosition::new(4, 4));
+    assert!(settings.display.show_grid);
+}
+
+#[test]
+fn errors_name_the_key() {
+    let err = parse("[board]\nwidth = 500\n").unwrap_err();
+    assert!(err.contains("board.width"), "{}", err);
+
+    let err = parse("[game]\nmove_time = 0\n").unwrap_err();
+    assert!(err.contains("game.move_time"), "{}", err);
+
+    let err = parse("[display]\ngrid = \"blue\"\n").unwrap_err();
+    assert!(err.contains("display.grid"), "{}", err);
+
+    let err = parse("[game]\nboundary = \"bouncy\"\n").unwrap_err();
+    assert!(err.contains("game.boundary"), "{}", err);
+
+    let err = parse("[snake]\nx = 30\n").unwrap_err();
+    assert!(err.contains("snake.x"), "{}", err);
+
+    // Unknown keys and wrong types are reported by the TOML parser
+    let err = parse("[game]\nspeed = 3\n").unwrap_err();
+    assert!(err.contains("speed"), "{}", err);
+    let err = parse("[game]\nfruit_points = \"ten\"\n").unwrap_err();
+    assert!(err.contains("fruit_points"), "{}", err);
+}
+
+#[test]
+fn keys_are_resolved_by_name() {
+    let settings = parse("[keys]\nup = [\"W\", \"up\"]\n").unwrap();
+    let controls = Controls::from_settings(&settings.keys).unwrap();
+    assert_eq!(controls.keys(Action::TurnUp).len(), 2);
+
+    let settings = parse("[keys]\nquit = [\"Nope\"]\n").unwrap();
+    match Controls::from_settings(&settings.keys) {
+        Err(SettingsError::Invalid { key, .. }) => assert_eq!(key, "keys.quit"),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn colors_are_parsed() {
+    assert_eq!(parse_color("#ff8000"), Ok((255, 128, 0)));
+    assert!(parse_color("ff8000").is_err());
+    assert!(parse_color("#ff80").is_err());
+    assert!(parse_color("#gg8000").is_err());
+    // Six bytes, but not six digits
+    assert!(parse_color("#a€bc").is_err());
+}
+
+#[test]
+fn the_mouse_is_off_by_default() {
+    let settings = parse("").unwrap();
+    assert!(!settings.mouse.steering);
+    assert!(!settings.mouse.buttons);
+
+    let settings = parse("[mouse]\nsteering = true\nbuttons = true\n").unwrap();
+    assert!(settings.mouse.steering);
+    assert!(settings.mouse.buttons);
+}
+
+#[test]
+fn bots_are_checked() {
+    let settings = parse("[bots]\ncount = 2\ndifficulty = \"cautious\"\n").unwrap();
+    assert_eq!(settings.bots.count, 2);
+
+    let err = parse("[bots]\ncount = 4\n").unwrap_err();
+    assert!(err.contains("bots.count"), "{}", err);
+    let err = parse("[bots]\ndifficulty = \"expert\"\n").unwrap_err();
+    assert!(err.contains("bots.difficulty"), "{}", err);
+}
+
+#[test]
+fn the_autopilot_strategy_is_checked() {
+    assert_eq!(parse("").unwrap().strategy().unwrap(), Strategy::SafePath);
+    let settings = parse("[autopilot]\nstrategy = \"cycle\"\n").unwrap();
+    assert_eq!(settings.strategy().unwrap(), Strategy::Cycle);
+
+    let err = parse("[autopilot]\nstrategy = \"random\"\n").unwrap_err();
+    assert!(err.contains("autopilot.strategy"), "{}", err);
+}
+
+#[test]
+fn the_command_line_wins_over_the_settings() {
+    let mut settings = parse("[bots]\ncount = 1\ndifficulty = \"cautious\"").unwrap();
+    let overrides = Overrides {
+        board: Some(Board::new(30, 12).unwrap()),
+        bots: Some(2),
+        ..Overrides::default()
+    };
+    settings.apply(&overrides).unwrap();
+    assert_eq!((settings.board.width, settings.board.height), (30, 12));
+    assert_eq!(settings.bots.count, 2);
+    assert_eq!(settings.bots.difficulty, "cautious");
+
+    let too_many = Overrides {
+        bots: Some(100),
+        ..Overrides::default()
+    };
+    assert!(settings.apply(&too_many).is_err());
+}
@@ -0,0 +1,268 @@
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Death;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Event;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::simulation::FRUIT_POINTS;
+use snake_ggez::simulation::VICTORY_BONUS_FRUITS;
+
+/// A running game with the fruit out of the way
+fn running_sim() -> Simulation {
+    let mut sim = Simulation::new(Rules::default(), 42);
+    sim.restart(42);
+    sim.place_fruit(GridPosition::new(20, 15));
+    sim
+}
+
+#[test]
+fn snake_moves_and_wraps_around() {
+    let mut sim = running_sim();
+    assert_eq!(sim.head_pos(), GridPosition::new(4, 4));
+
+    let events = sim.step(None);
+    assert_eq!(events, vec![Event::Moved(GridPosition::new(5, 4))]);
+
+    for _ in 0..5 {
+        sim.step(Some(Direction::Up));
+    }
+    assert_eq!(sim.head_pos(), GridPosition::new(5, 19));
+}
+
+#[test]
+fn eating_fruit_grows_the_snake() {
+    let mut sim = running_sim();
+    sim.place_fruit(GridPosition::new(5, 4));
+
+    let events = sim.step(None);
+    assert!(events.contains(&Event::FruitEaten {
+        snake: 0,
+        points: FRUIT_POINTS
+    }));
+    assert_eq!(sim.score(), FRUIT_POINTS);
+    assert_eq!(sim.body().len(), 1);
+    assert_ne!(sim.fruit_pos(), sim.head_pos());
+}
+
+#[test]
+fn snake_cannot_reverse_onto_its_body() {
+    let mut sim = running_sim();
+    sim.place_fruit(GridPosition::new(5, 4));
+    sim.step(None);
+
+    assert!(!sim.can_turn(Direction::Left));
+    sim.step(Some(Direction::Left));
+    assert_eq!(sim.dir(), Direction::Right);
+    assert_eq!(sim.state(), GameState::Running);
+}
+
+#[test]
+fn biting_the_body_is_game_over() {
+    let mut sim = running_sim();
+    for x in 5..9 {
+        sim.place_fruit(GridPosition::new(x, 4));
+        sim.step(None);
+    }
+    assert_eq!(sim.body().len(), 4);
+
+    sim.step(Some(Direction::Down));
+    sim.step(Some(Direction::Left));
+    let events = sim.step(Some(Direction::Up));
+    assert!(events.contains(&Event::GameOver(Death::BitItself)));
+    assert_eq!(sim.death(), Some(Death::BitItself));
+    assert_eq!(sim.state(), GameState::GameOver);
+
+    // A finished game doesn't move anymore
+    assert!(sim.step(None).is_empty());
+}
+
+#[test]
+fn same_seed_same_game() {
+    let inputs = [
+        None,
+        Some(Direction::Down),
+        None,
+        Some(Direction::Left),
+        None,
+    ];
+
+    let play = |seed: u64| {
+        let mut sim = Simulation::new(Rules::default(), seed);
+        // Let the attract mode run for a while
+        for _ in 0..50 {
+            sim.step(None);
+        }
+        let mut trace = vec![(sim.head_pos(), sim.fruit_pos())];
+        sim.restart(seed);
+        for input in inputs.iter().cycle().take(200) {
+            sim.step(*input);
+            trace.push((sim.head_pos(), sim.fruit_pos()));
+        }
+        trace
+    };
+
+    assert_eq!(play(7), play(7));
+    assert_ne!(play(7), play(8));
+}
+
+#[test]
+fn board_size_is_checked() {
+    assert!(Board::new(5, 5).is_ok());
+    assert!(Board::new(200, 200).is_ok());
+    assert!(Board::new(4, 10).is_err());
+    assert!(Board::new(10, 201).is_err());
+}
+
+#[test]
+fn board_size_is_parsed() {
+    assert_eq!("30x12".parse(), Board::new(30, 12));
+    assert!("30".parse::<Board>().is_err());
+    assert!("30xtwelve".parse::<Board>().is_err());
+    assert!("300x12".parse::<Board>().is_err());
+}
+
+#[test]
+fn snake_wraps_on_a_tiny_board() {
+    let rules = Rules {
+        board: Board::new(5, 5).unwrap(),
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 3);
+    sim.restart(3);
+    sim.place_fruit(GridPosition::new(0, 0));
+
+    sim.step(None);
+    assert_eq!(sim.head_pos(), GridPosition::new(0, 4));
+    sim.step(Some(Direction::Down));
+    assert_eq!(sim.head_pos(), GridPosition::new(0, 0));
+    assert_eq!(sim.score(), FRUIT_POINTS);
+}
+
+#[test]
+fn snake_starts_as_the_rules_say() {
+    let rules = Rules {
+        fruit_points: 25,
+        start_pos: GridPosition::new(6, 3),
+        start_length: 3,
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 1);
+    sim.restart(1);
+    assert_eq!(sim.head_pos(), GridPosition::new(6, 3));
+    let body: Vec<_> = sim.body().iter().copied().collect();
+    assert_eq!(body, [GridPosition::new(5, 3), GridPosition::new(4, 3)]);
+
+    sim.place_fruit(GridPosition::new(7, 3));
+    sim.step(None);
+    assert_eq!(sim.score(), 25);
+}
+
+/// A running game on a 5x5 board with the given boundary, the head at 2,2
+fn bounded_sim(boundary: Boundary) -> Simulation {
+    let rules = Rules {
+        board: Board::new(5, 5).unwrap(),
+        boundary,
+        start_pos: GridPosition::new(2, 2),
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 1);
+    sim.restart(1);
+    sim.place_fruit(GridPosition::new(0, 0));
+    sim
+}
+
+#[test]
+fn walls_end_the_game() {
+    let mut sim = bounded_sim(Boundary::Walls);
+    sim.step(None);
+    sim.step(None);
+    assert_eq!(sim.head_pos(), GridPosition::new(4, 2));
+    assert_eq!(sim.death(), None);
+
+    let events = sim.step(None);
+    assert_eq!(events, [Event::GameOver(Death::HitWall)]);
+    assert_eq!(sim.state(), GameState::GameOver);
+    assert_eq!(sim.death(), Some(Death::HitWall));
+    // The snake does not go through the wall
+    assert_eq!(sim.head_pos(), GridPosition::new(4, 2));
+}
+
+#[test]
+fn mixed_boundaries_wrap_only_some_edges() {
+    let mut sim = bounded_sim(Boundary::WrapX);
+    for _ in 0..3 {
+        sim.step(None);
+    }
+    assert_eq!(sim.head_pos(), GridPosition::new(0, 2));
+    sim.step(Some(Direction::Up));
+    sim.step(None);
+    assert_eq!(sim.state(), GameState::Running);
+    sim.step(None);
+    assert_eq!(sim.death(), Some(Death::HitWall));
+
+    let mut sim = bounded_sim(Boundary::WrapY);
+    sim.step(Some(Direction::Up));
+    sim.step(None);
+    sim.step(None);
+    assert_eq!(sim.head_pos(), GridPosition::new(2, 4));
+    sim.step(Some(Direction::Right));
+    sim.step(None);
+    assert_eq!(sim.state(), GameState::Running);
+    sim.step(None);
+    assert_eq!(sim.death(), Some(Death::HitWall));
+}
+
+#[test]
+fn starting_body_must_not_cross_a_wall() {
+    let rules = Rules {
+        boundary: Boundary::Walls,
+        start_pos: GridPosition::new(2, 2),
+        start_length: 4,
+        ..Rules::default()
+    };
+    assert!(rules.validate().is_err());
+    let rules = Rules {
+        boundary: Boundary::Wrap,
+        ..rules
+    };
+    assert!(rules.validate().is_ok());
+}
+
+#[test]
+fn filling_the_board_is_a_victory() {
+    let rules = Rules {
+        board: Board::new(5, 5).unwrap(),
+        start_pos: GridPosition::new(0, 0),
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 11);
+    sim.restart(11);
+
+    // Four moves right and one down, over and over: on the toroidal 5x5 board
+    // the snake visits every cell before coming back, so it never bites itself
+    let mut events = Vec::new();
+    for i in 0..25 * 25 {
+        if sim.state() != GameState::Running {
+            break;
+        }
+        let input = if i % 5 == 4 {
+            Direction::Down
+        } else {
+            Direction::Right
+        };
+        events = sim.step(Some(input));
+    }
+
+    let bonus = VICTORY_BONUS_FRUITS * FRUIT_POINTS;
+    assert_eq!(sim.state(), GameState::Victory);
+    assert!(events.contains(&Event::Victory { bonus }));
+    assert_eq!(sim.length(), 25);
+    assert_eq!(sim.score(), 24 * FRUIT_POINTS + bonus);
+    assert_eq!(sim.death(), None);
+
+    // The game is over, nothing moves anymore
+    assert!(sim.step(Some(Direction::Down)).is_empty());
+}
@@ -0,0 +1,158 @@
+use snake_ggez::bot::bot_starts;
+use snake_ggez::level::Level;
+use snake_ggez::simulation::Board;
+use snake_ggez::simulation::Boundary;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Fruits;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::tui::render;
+use snake_ggez::tui::size;
+use snake_ggez::tui::status;
+use snake_ggez::tui::tiles;
+use snake_ggez::tui::Charset;
+use snake_ggez::tui::Edge;
+use snake_ggez::tui::Tile;
+
+const LEVEL: &str = "\
+name Test
+boundary walls
+goal length 5
+map
+#######
+#.....#
+#.>.#.#
+#.....#
+#...*.#
+#######
+";
+
+/// A game started with these rules
+fn game(rules: Rules) -> Simulation {
+    let mut sim = Simulation::new(rules, 1);
+    sim.restart(1);
+    sim
+}
+
+/// A game on a small board
+fn small(boundary: Boundary) -> Simulation {
+    game(Rules {
+        board: Board::new(10, 6).unwrap(),
+        boundary,
+        ..Rules::default()
+    })
+}
+
+fn count(lines: &[String], glyph: &str) -> usize {
+    lines.iter().map(|line| line.matches(glyph).count()).sum()
+}
+
+#[test]
+fn the_board_is_framed() {
+    let sim = small(Boundary::Walls);
+    let lines = render(&sim, Charset::Unicode);
+    assert_eq!(lines.len(), 6 + 2);
+    for line in lines.iter() {
+        assert_eq!(line.chars().count(), 10 * 2 + 2);
+    }
+    assert_eq!(size(&sim), (22, 8));
+    assert!(lines[0].starts_with('┌') && lines[0].ends_with('┐'));
+    assert!(lines[7].starts_with('└') && lines[7].ends_with('┘'));
+
+    // The snake heads right, and there is one fruit
+    assert_eq!(count(&lines, "▶▶"), 1);
+    assert_eq!(count(&lines, "● "), 1);
+    let head = sim.head_pos();
+    let row: Vec<char> = lines[head.y as usize + 1].chars().collect();
+    assert_eq!(row[head.x as usize * 2 + 1], '▶');
+}
+
+#[test]
+fn heads_are_drawn_over_the_rest() {
+    let mut sim = small(Boundary::Wrap);
+    sim.step_all(&[Some(Direction::Down)]);
+    let rows = tiles(&sim);
+    let head = sim.head_pos();
+    assert_eq!(
+        rows[head.y as usize + 1][head.x as usize + 1],
+        Tile::Head {
+            snake: 0,
+            dir: Direction::Down,
+            alive: true
+        }
+    );
+}
+
+#[test]
+fn levels_show_their_walls() {
+    let level: Level = LEVEL.parse().unwrap();
+    let walls = level.rules.walls.len();
+    let sim = game(level.rules);
+    let lines = render(&sim, Charset::Unicode);
+    assert_eq!(count(&lines, "▓▓"), walls);
+
+    let lines = render(&sim, Charset::Ascii);
+    assert_eq!(count(&lines, "##"), walls);
+    assert_eq!(count(&lines, ">>"), 1);
+    assert!(lines.iter().all(|line| line.is_ascii()));
+}
+
+#[test]
+fn edges_that_wrap_are_dotted() {
+    let edges = |boundary| {
+        let rows = tiles(&small(boundary));
+        (rows[0][1], rows[1][0])
+    };
+    let top = |wraps| Edge::Horizontal { wraps };
+    let side = |wraps| Edge::Vertical { wraps };
+    assert_eq!(
+        edges(Boundary::Wrap),
+        (Tile::Edge(top(true)), Tile::Edge(side(true)))
+    );
+    assert_eq!(
+        edges(Boundary::Walls),
+        (Tile::Edge(top(false)), Tile::Edge(side(false)))
+    );
+    assert_eq!(
+        edges(Boundary::WrapX),
+        (Tile::Edge(top(false)), Tile::Edge(side(true)))
+    );
+    assert_eq!(
+        edges(Boundary::WrapY),
+        (Tile::Edge(top(true)), Tile::Edge(side(false)))
+    );
+}
+
+#[test]
+fn separate_fruits_have_an_owner() {
+    let mut rules = Rules {
+        fruits: Fruits::Separate,
+        ..Rules::default()
+    };
+    rules.rivals = bot_starts(&rules, 1);
+    let sim = game(rules);
+    let rows = tiles(&sim);
+    for snake in 0..2 {
+        let fruit = sim.fruit_of(snake);
+        assert_eq!(
+            rows[fruit.y as usize + 1][fruit.x as usize + 1],
+            Tile::Fruit { owner: Some(snake) }
+        );
+    }
+}
+
+#[test]
+fn the_status_line_shows_the_scores() {
+    let sim = small(Boundary::Wrap);
+    assert_eq!(status(&sim, &[]), "Points: 0   Tick 0");
+
+    let level: Level = LEVEL.parse().unwrap();
+    let sim = game(level.rules);
+    assert_eq!(status(&sim, &[]), "Points: 0   Length: 1 / 5   Tick 0");
+
+    let mut rules = Rules::default();
+    rules.rivals = bot_starts(&rules, 2);
+    let sim = game(rules);
+    let names = ["You".to_string(), "Bot 1".to_string()];
+    assert_eq!(status(&sim, &names), "You: 0  Bot 1: 0  P3: 0   Tick 0");
+}
@@ -0,0 +1,98 @@
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::turns::TurnQueue;
+use snake_ggez::turns::MAX_QUEUED_TURNS;
+
+/// A game just started, with a snake of the given length going right
+fn game(length: u32) -> Simulation {
+    let rules = Rules {
+        start_length: length,
+        ..Rules::default()
+    };
+    let mut sim = Simulation::new(rules, 1);
+    sim.restart(1);
+    sim
+}
+
+#[test]
+fn quick_turns_are_all_taken_one_per_tick() {
+    let mut sim = game(3);
+    let mut turns = TurnQueue::new();
+    assert!(turns.push(&sim, Direction::Up));
+    assert!(turns.push(&sim, Direction::Left));
+    assert_eq!(turns.len(), 2);
+
+    let start = sim.head_pos();
+    sim.step(turns.pop());
+    assert_eq!(sim.dir(), Direction::Up);
+    sim.step(turns.pop());
+    assert_eq!(sim.dir(), Direction::Left);
+    assert!(turns.is_empty());
+
+    assert_eq!(sim.state(), GameState::Running);
+    assert_eq!(sim.head_pos().x, start.x - 1);
+    assert_eq!(sim.head_pos().y, start.y - 1);
+}
+
+#[test]
+fn u_turns_are_refused() {
+    let sim = game(3);
+    let mut turns = TurnQueue::new();
+
+    // Straight back onto the body
+    assert!(!turns.push(&sim, Direction::Left));
+
+    // Back onto the queued direction
+    assert!(turns.push(&sim, Direction::Up));
+    assert!(!turns.push(&sim, Direction::Down));
+
+    // A U-turn in two steps is fine
+    assert!(turns.push(&sim, Direction::Left));
+    assert_eq!(turns.to_vec(), [Direction::Up, Direction::Left]);
+}
+
+#[test]
+fn a_u_turn_in_two_quick_turns_does_not_kill_the_snake() {
+    let mut sim = game(4);
+    let mut turns = TurnQueue::new();
+    assert!(turns.push(&sim, Direction::Down));
+    assert!(turns.push(&sim, Direction::Left));
+    while !turns.is_empty() {
+        sim.step(turns.pop());
+    }
+    assert_eq!(sim.dir(), Direction::Left);
+    assert_eq!(sim.state(), GameState::Running);
+}
+
+#[test]
+fn a_snake_without_body_can_reverse() {
+    let sim = game(1);
+    let mut turns = TurnQueue::new();
+    assert!(turns.push(&sim, Direction::Left));
+    assert!(turns.push(&sim, Direction::Right));
+}
+
+#[test]
+fn useless_turns_are_ignored() {
+    let sim = game(3);
+    let mut turns = TurnQueue::new();
+
+    // Already going that way
+    assert!(!turns.push(&sim, Direction::Right));
+    assert!(turns.push(&sim, Direction::Up));
+    assert!(!turns.push(&sim, Direction::Up));
+
+    // Too many keys at once
+    let mut dir = Direction::Up;
+    while turns.len() < MAX_QUEUED_TURNS {
+        dir = match dir {
+            Direction::Up => Direction::Right,
+            _ => Direction::Up,
+        };
+        assert!(turns.push(&sim, dir));
+    }
+    assert!(!turns.push(&sim, Direction::Left));
+    assert_eq!(turns.len(), MAX_QUEUED_TURNS);
+}
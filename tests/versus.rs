@@ -0,0 +1,133 @@
+use snake_ggez::simulation::Death;
+use snake_ggez::simulation::Direction;
+use snake_ggez::simulation::Event;
+use snake_ggez::simulation::Fruits;
+use snake_ggez::simulation::GameState;
+use snake_ggez::simulation::GridPosition;
+use snake_ggez::simulation::Rules;
+use snake_ggez::simulation::Simulation;
+use snake_ggez::simulation::Start;
+
+/// A running match against a snake starting there, with the fruit out of the way
+fn versus_sim(rival: Start, length: u32, fruits: Fruits) -> Simulation {
+    let rules = Rules {
+        start_length: length,
+        rivals: vec![rival],
+        fruits,
+        ..Rules::default()
+    };
+    rules.validate().unwrap();
+    let mut sim = Simulation::new(rules, 42);
+    sim.restart(42);
+    sim.place_fruit(GridPosition::new(20, 15));
+    sim
+}
+
+#[test]
+fn the_second_player_starts_mirrored() {
+    let rules = Rules::default();
+    let start = rules.start().mirrored(rules.board);
+    assert_eq!(start.pos, GridPosition::new(20, 15));
+    assert_eq!(start.dir, Direction::Left);
+
+    let mut sim = versus_sim(start, 3, Fruits::Shared);
+    assert_eq!(sim.snakes().len(), 2);
+    assert_eq!(sim.snakes()[1].head_pos(), GridPosition::new(20, 15));
+    assert_eq!(sim.snakes()[1].body().len(), 2);
+
+    sim.step_all(&[Some(Direction::Down), Some(Direction::Up)]);
+    assert_eq!(sim.head_pos(), GridPosition::new(4, 5));
+    assert_eq!(sim.snakes()[1].head_pos(), GridPosition::new(20, 14));
+}
+
+#[test]
+fn snakes_starting_on_each_other_are_rejected() {
+    let rules = Rules {
+        start_length: 3,
+        rivals: vec![Start {
+            pos: GridPosition::new(3, 5),
+            dir: Direction::Down,
+        }],
+        ..Rules::default()
+    };
+    assert!(rules.validate().is_err());
+}
+
+#[test]
+fn heads_meeting_is_a_draw() {
+    let rival = Start {
+        pos: GridPosition::new(6, 4),
+        dir: Direction::Left,
+    };
+    let mut sim = versus_sim(rival, 1, Fruits::Shared);
+
+    let events = sim.step_all(&[None, None]);
+    assert!(events.contains(&Event::Died {
+        snake: 0,
+        death: Death::HeadOn
+    }));
+    assert!(events.contains(&Event::Died {
+        snake: 1,
+        death: Death::HeadOn
+    }));
+    assert!(events.contains(&Event::RoundOver { winner: None }));
+    assert_eq!(sim.state(), GameState::GameOver);
+}
+
+#[test]
+fn running_into_a_body_loses_the_round() {
+    let rival = Start {
+        pos: GridPosition::new(5, 7),
+        dir: Direction::Up,
+    };
+    let mut sim = versus_sim(rival, 3, Fruits::Shared);
+
+    let events = sim.step_all(&[None, None]);
+    assert_eq!(sim.state(), GameState::Running);
+    assert!(!events.iter().any(|e| matches!(e, Event::Died { .. })));
+
+    // The rival runs into the middle of the body of the player
+    sim.step_all(&[None, None]);
+    let events = sim.step_all(&[None, None]);
+    assert!(events.contains(&Event::Died {
+        snake: 1,
+        death: Death::HitSnake
+    }));
+    assert!(events.contains(&Event::RoundOver { winner: Some(0) }));
+    assert!(sim.player().is_alive());
+    assert_eq!(sim.snakes()[1].death(), Some(Death::HitSnake));
+}
+
+#[test]
+fn separate_fruits_are_eaten_only_by_their_snake() {
+    let rival = Start {
+        pos: GridPosition::new(10, 10),
+        dir: Direction::Right,
+    };
+    let mut sim = versus_sim(rival, 1, Fruits::Separate);
+    assert_eq!(sim.fruits().len(), 2);
+
+    // The fruit of the player is in the way of the rival
+    sim.place_fruit(GridPosition::new(11, 10));
+    sim.step_all(&[None, None]);
+    assert_eq!(sim.snakes()[1].head_pos(), GridPosition::new(11, 10));
+    assert_eq!(sim.snakes()[1].score(), 0);
+    assert_eq!(sim.fruit_pos(), GridPosition::new(11, 10));
+}
+
+#[test]
+fn a_match_survives_a_snapshot() {
+    let rival = Rules::default().start().mirrored(Rules::default().board);
+    let mut sim = versus_sim(rival, 2, Fruits::Separate);
+    sim.step_all(&[Some(Direction::Down), None]);
+
+    let mut copy = Simulation::from_snapshot(sim.snapshot()).unwrap();
+    for tick in 0..30 {
+        let inputs = [
+            Some([Direction::Right, Direction::Down][tick % 2]),
+            Some([Direction::Up, Direction::Left][tick % 2]),
+        ];
+        assert_eq!(sim.step_all(&inputs), copy.step_all(&inputs));
+    }
+    assert_eq!(sim.snapshot(), copy.snapshot());
+}